@@ -0,0 +1,64 @@
+//! Bearer tokens and the read grants they carry.
+//!
+//! Tokens are HS256 JWTs signed with `JWT_SECRET`. A token's `grants` claim
+//! lists GROQ filters limiting which documents it can read; a token without
+//! the claim reads everything. Requests without a token get the configured
+//! `PUBLIC_GRANTS`, which by default grant nothing.
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+};
+use content_lake_core::grants::Grants;
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Claims of a bearer token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub exp: u64,
+    /// GROQ filters over readable documents; absent means unrestricted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grants: Option<Vec<String>>,
+}
+
+/// The requester's read grants, or `None` when unrestricted.
+#[derive(Debug, Clone)]
+pub struct ReadGrants(pub Option<Grants>);
+
+impl ReadGrants {
+    /// Whether the requester may read `doc`.
+    pub fn allows(&self, doc: &Value) -> bool {
+        self.0.as_ref().is_none_or(|grants| grants.allows(doc))
+    }
+}
+
+impl FromRequestParts<AppState> for ReadGrants {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, ApiError> {
+        let Some(value) = parts.headers.get(header::AUTHORIZATION) else {
+            return Ok(ReadGrants(state.public_grants().cloned()));
+        };
+        let token = value
+            .to_str()
+            .ok()
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or(ApiError::Unauthorized)?;
+        let key = DecodingKey::from_secret(state.config().jwt_secret.as_bytes());
+        let claims = decode::<Claims>(token.trim(), &key, &Validation::default())
+            .map_err(|_| ApiError::Unauthorized)?
+            .claims;
+        let grants = claims
+            .grants
+            .map(|filters| Grants::compile(&filters))
+            .transpose()
+            .map_err(|_| ApiError::Unauthorized)?;
+        Ok(ReadGrants(grants))
+    }
+}
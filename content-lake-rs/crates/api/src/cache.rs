@@ -0,0 +1,390 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use content_lake_groq::ast::Expr;
+use content_lake_groq::parser::{parse, ParseError};
+use content_lake_groq::sql_gen::{count_binds, count_filter, fingerprint, SqlFilter};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Least-recently-used cache of parsed GROQ queries, keyed by query text.
+///
+/// Shared across handlers through `AppState`; a capacity of zero disables
+/// caching. Parse failures are never cached.
+pub struct ExprCache {
+    entries: Lru<Arc<Expr>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ExprCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Lru::new(capacity),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Return the parsed form of `query`, parsing and caching it on a miss.
+    pub fn parse(&self, query: &str) -> Result<Arc<Expr>, ParseError> {
+        if let Some(expr) = self.entries.get(query) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(expr);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        // Parse outside the lock so a slow query doesn't block other handlers.
+        let expr = Arc::new(parse(query)?);
+        self.entries.insert(query, expr.clone());
+        Ok(expr)
+    }
+
+    /// Number of lookups answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of lookups that had to parse.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// Least-recently-used cache of GROQ filters translated to SQL, keyed by
+/// [`fingerprint`], so structurally identical queries reuse one SQL string
+/// (and the prepared statement behind it) and skip translation.
+///
+/// Shapes that don't translate are cached too, so they go straight to the
+/// in-memory evaluator.
+pub struct SqlCache {
+    entries: Lru<Option<Arc<str>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl SqlCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Lru::new(capacity),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// The filter of a `count(*[...])` query, as [`count_filter`] would
+    /// translate it with placeholders from `first_param`.
+    pub fn count_filter(
+        &self,
+        expr: &Expr,
+        params: &Value,
+        first_param: usize,
+    ) -> Option<SqlFilter> {
+        let key = format!("{first_param}:{}", fingerprint(expr, params));
+        if let Some(sql) = self.entries.get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            let sql = sql?;
+            return count_binds(expr, params).map(|binds| SqlFilter {
+                sql: sql.to_string(),
+                binds,
+            });
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let filter = count_filter(expr, params, first_param);
+        self.entries
+            .insert(&key, filter.as_ref().map(|f| Arc::from(f.sql.as_str())));
+        filter
+    }
+
+    /// Number of lookups answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of lookups that had to translate.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+/// A least-recently-used map from text keys; a capacity of zero stores
+/// nothing.
+struct Lru<V> {
+    capacity: usize,
+    entries: Mutex<Entries<V>>,
+}
+
+struct Entries<V> {
+    /// Key to its value and last-use tick.
+    by_key: HashMap<String, (V, u64)>,
+    /// Last-use tick to key; the first entry is the eviction candidate.
+    by_use: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl<V: Clone> Lru<V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(Entries {
+                by_key: HashMap::new(),
+                by_use: BTreeMap::new(),
+                tick: 0,
+            }),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let tick = entries.next_tick();
+        let Entries { by_key, by_use, .. } = &mut *entries;
+        let (value, used) = by_key.get_mut(key)?;
+        let key = by_use.remove(used)?;
+        by_use.insert(tick, key);
+        *used = tick;
+        Some(value.clone())
+    }
+
+    fn insert(&self, key: &str, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let tick = entries.next_tick();
+        // Another handler may have cached the same key meanwhile.
+        if let Some((_, used)) = entries.by_key.insert(key.to_string(), (value, tick)) {
+            entries.by_use.remove(&used);
+        }
+        entries.by_use.insert(tick, key.to_string());
+        while entries.by_key.len() > self.capacity {
+            let Some((_, oldest)) = entries.by_use.pop_first() else {
+                break;
+            };
+            entries.by_key.remove(&oldest);
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries.lock().unwrap().by_key.len()
+    }
+}
+
+impl<V> Entries<V> {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+/// Dataset name to id, filled as route paths are resolved.
+///
+/// Only names that exist are cached, and entries expire after `ttl`, so a
+/// dataset deleted or recreated through another node is seen again within
+/// that time. Expired entries are dropped as new ones are added; a zero
+/// `ttl` disables caching.
+pub struct DatasetIdCache {
+    ttl: Duration,
+    ids: Mutex<HashMap<String, (Uuid, Instant)>>,
+}
+
+impl DatasetIdCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            ids: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Uuid> {
+        self.lock()
+            .get(name)
+            .filter(|(_, at)| at.elapsed() < self.ttl)
+            .map(|(id, _)| *id)
+    }
+
+    pub fn insert(&self, name: &str, id: Uuid) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut ids = self.lock();
+        ids.retain(|_, (_, at)| at.elapsed() < self.ttl);
+        ids.insert(name.to_string(), (id, Instant::now()));
+    }
+
+    /// Forget `name`, e.g. once its dataset is deleted.
+    pub fn remove(&self, name: &str) {
+        self.lock().remove(name);
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, (Uuid, Instant)>> {
+        self.ids.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// When the database last passed a health check.
+///
+/// A check within `ttl` of the last success is answered from the cache, so
+/// frequent probes don't each cost a database round trip. Failures aren't
+/// cached; a zero `ttl` disables caching.
+pub struct HealthCache {
+    ttl: Duration,
+    last_ok: Mutex<Option<Instant>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl HealthCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            last_ok: Mutex::new(None),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether the last successful check is recent enough to reuse. A miss
+    /// means the caller should probe the database.
+    pub fn fresh(&self) -> bool {
+        let fresh = self.lock().is_some_and(|at| at.elapsed() < self.ttl);
+        let counter = if fresh { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        fresh
+    }
+
+    /// Record a successful check.
+    pub fn record_ok(&self) {
+        *self.lock() = Some(Instant::now());
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Instant>> {
+        self.last_ok.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use content_lake_groq::sql_gen::SqlBind;
+    use serde_json::json;
+
+    #[test]
+    fn second_parse_hits_the_cache() {
+        let cache = ExprCache::new(4);
+        let first = cache.parse("*[_type == \"post\"]").unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (0, 1));
+        let second = cache.parse("*[_type == \"post\"]").unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+        assert!(Arc::ptr_eq(&first, &second));
+
+        assert!(cache.parse("*[").is_err());
+        assert!(cache.parse("*[").is_err());
+        assert_eq!((cache.hits(), cache.misses()), (1, 3));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = ExprCache::new(2);
+        cache.parse("*[a]").unwrap();
+        cache.parse("*[b]").unwrap();
+        cache.parse("*[a]").unwrap();
+        cache.parse("*[c]").unwrap();
+        assert_eq!(cache.len(), 2);
+
+        cache.parse("*[a]").unwrap();
+        assert_eq!(cache.hits(), 2);
+        cache.parse("*[b]").unwrap();
+        assert_eq!(cache.misses(), 4);
+
+        let disabled = ExprCache::new(0);
+        disabled.parse("*[a]").unwrap();
+        disabled.parse("*[a]").unwrap();
+        assert_eq!((disabled.hits(), disabled.len()), (0, 0));
+    }
+
+    #[test]
+    fn reuses_sql_for_queries_of_the_same_shape() {
+        let cache = SqlCache::new(4);
+        let query = parse("count(*[_type == $type])").unwrap();
+        let post = cache
+            .count_filter(&query, &json!({"type": "post"}), 3)
+            .unwrap();
+        let page = cache
+            .count_filter(&query, &json!({"type": "page"}), 3)
+            .unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+        assert_eq!(post.sql, page.sql);
+        assert_eq!(page.binds, [SqlBind::Text("page".into())]);
+        assert_eq!(
+            Some(page),
+            count_filter(&query, &json!({"type": "page"}), 3)
+        );
+
+        let untranslatable = parse("count(*[a == b])").unwrap();
+        assert_eq!(cache.count_filter(&untranslatable, &json!({}), 3), None);
+        assert_eq!(cache.count_filter(&untranslatable, &json!({}), 3), None);
+        assert_eq!((cache.hits(), cache.misses()), (2, 2));
+    }
+
+    #[test]
+    fn dataset_ids_are_cached_until_removed() {
+        let cache = DatasetIdCache::new(Duration::from_secs(60));
+        let id = Uuid::new_v4();
+        assert_eq!(cache.get("production"), None);
+        cache.insert("production", id);
+        assert_eq!(cache.get("production"), Some(id));
+        assert_eq!(cache.get("staging"), None);
+        cache.remove("production");
+        assert_eq!(cache.get("production"), None);
+    }
+
+    #[test]
+    fn dataset_ids_expire_after_the_ttl() {
+        let id = Uuid::new_v4();
+        let cache = DatasetIdCache::new(Duration::from_millis(20));
+        cache.insert("a", id);
+        assert_eq!(cache.get("a"), Some(id));
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(cache.get("a"), None);
+        cache.insert("b", id);
+        assert_eq!(cache.len(), 1, "expired entries are dropped");
+
+        let disabled = DatasetIdCache::new(Duration::ZERO);
+        disabled.insert("a", id);
+        assert_eq!(disabled.get("a"), None);
+    }
+
+    #[test]
+    fn health_checks_are_reused_within_the_ttl() {
+        let cache = HealthCache::new(Duration::from_secs(60));
+        assert!(!cache.fresh());
+        cache.record_ok();
+        assert!(cache.fresh());
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        let disabled = HealthCache::new(Duration::ZERO);
+        disabled.record_ok();
+        assert!(!disabled.fresh());
+    }
+}
@@ -1,4 +1,49 @@
 use std::env;
+use std::str::FromStr;
+
+/// Output format of the tracing subscriber.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One JSON object per line, for log aggregation.
+    #[default]
+    Json,
+    /// Multi-line, human-readable output for local development.
+    Pretty,
+    /// Single-line, human-readable output.
+    Compact,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(LogFormat::Json),
+            "pretty" => Ok(LogFormat::Pretty),
+            "compact" => Ok(LogFormat::Compact),
+            other => Err(format!("unknown log format: {other}")),
+        }
+    }
+}
+
+/// Where uploaded asset files are stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AssetBackend {
+    /// Files under `ASSET_DIR` on the local filesystem.
+    #[default]
+    Local,
+}
+
+impl FromStr for AssetBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "local" => Ok(AssetBackend::Local),
+            other => Err(format!("unknown asset backend: {other}")),
+        }
+    }
+}
 
 /// Application configuration loaded from environment variables.
 #[derive(Debug, Clone)]
@@ -14,12 +59,48 @@ pub struct AppConfig {
     pub db_max_connections: u32,
     /// Minimum database connections in the pool.
     pub db_min_connections: u32,
+    /// A successful `/health` database check is reused for this many
+    /// milliseconds; zero checks the database on every call.
+    pub health_cache_ms: u64,
     /// JWT signing secret.
     pub jwt_secret: String,
+    /// Read grants of requests without a bearer token; `None` lets them read
+    /// everything.
+    pub public_grants: Option<Vec<String>>,
     /// Event bus channel capacity.
     pub event_bus_capacity: usize,
     /// Log level (e.g., "info", "debug", "trace").
     pub log_level: String,
+    /// Log output format.
+    pub log_format: LogFormat,
+    /// Queries slower than this many milliseconds are logged as warnings.
+    pub slow_query_ms: u64,
+    /// Database statements scanning a dataset for a query or export are
+    /// cancelled after this many milliseconds; zero disables the limit.
+    pub query_timeout_ms: u64,
+    /// Queries whose complexity score exceeds this are rejected.
+    pub max_query_complexity: u32,
+    /// Upper bound on the `limit` query parameter of the query endpoint.
+    pub max_query_limit: usize,
+    /// Interval between keepalive comments on idle listen streams, in
+    /// milliseconds; never zero.
+    pub listen_keepalive_ms: u64,
+    /// Document types exposed through the GraphQL endpoint.
+    pub graphql_types: Vec<String>,
+    /// Entries kept in the parsed-query and SQL caches; zero disables them.
+    pub query_cache_size: usize,
+    /// A resolved dataset name is reused for this many milliseconds; zero
+    /// resolves it on every request.
+    pub dataset_cache_ms: u64,
+    /// Largest serialized size, in bytes, of a document a mutation may write.
+    pub max_document_bytes: usize,
+    /// Storage backend for uploaded asset files.
+    pub asset_backend: AssetBackend,
+    /// Directory uploaded asset files are stored under by the local backend.
+    pub asset_dir: String,
+    /// URL prefix asset files are served from; asset URLs are this plus the
+    /// asset's path.
+    pub asset_base_url: String,
 }
 
 impl AppConfig {
@@ -40,13 +121,70 @@ impl AppConfig {
                 .unwrap_or_else(|_| "5".to_string())
                 .parse()
                 .expect("DB_MIN_CONNECTIONS must be a valid u32"),
+            health_cache_ms: env::var("HEALTH_CACHE_MS")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()
+                .expect("HEALTH_CACHE_MS must be a valid u64"),
             jwt_secret: env::var("JWT_SECRET")
                 .unwrap_or_else(|_| "dev-secret-change-me-in-production".to_string()),
+            public_grants: public_grants(env::var("PUBLIC_GRANTS").ok().as_deref())
+                .expect("PUBLIC_GRANTS must be * or a JSON array of GROQ filters"),
             event_bus_capacity: env::var("EVENT_BUS_CAPACITY")
                 .unwrap_or_else(|_| "1024".to_string())
                 .parse()
                 .expect("EVENT_BUS_CAPACITY must be a valid usize"),
             log_level: env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string()),
+            log_format: env::var("LOG_FORMAT")
+                .unwrap_or_else(|_| "json".to_string())
+                .parse()
+                .expect("LOG_FORMAT must be json, pretty or compact"),
+            slow_query_ms: env::var("SLOW_QUERY_MS")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()
+                .expect("SLOW_QUERY_MS must be a valid u64"),
+            query_timeout_ms: env::var("QUERY_TIMEOUT_MS")
+                .unwrap_or_else(|_| "30000".to_string())
+                .parse()
+                .expect("QUERY_TIMEOUT_MS must be a valid u64"),
+            max_query_complexity: env::var("MAX_QUERY_COMPLEXITY")
+                .unwrap_or_else(|_| "500".to_string())
+                .parse()
+                .expect("MAX_QUERY_COMPLEXITY must be a valid u32"),
+            max_query_limit: env::var("MAX_QUERY_LIMIT")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()
+                .expect("MAX_QUERY_LIMIT must be a valid usize"),
+            listen_keepalive_ms: env::var("LISTEN_KEEPALIVE_MS")
+                .unwrap_or_else(|_| "15000".to_string())
+                .parse::<u64>()
+                .ok()
+                .filter(|ms| *ms > 0)
+                .expect("LISTEN_KEEPALIVE_MS must be a positive u64"),
+            graphql_types: env::var("GRAPHQL_TYPES")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect(),
+            query_cache_size: env::var("QUERY_CACHE_SIZE")
+                .unwrap_or_else(|_| "256".to_string())
+                .parse()
+                .expect("QUERY_CACHE_SIZE must be a valid usize"),
+            dataset_cache_ms: env::var("DATASET_CACHE_MS")
+                .unwrap_or_else(|_| "5000".to_string())
+                .parse()
+                .expect("DATASET_CACHE_MS must be a valid u64"),
+            max_document_bytes: env::var("MAX_DOCUMENT_BYTES")
+                .unwrap_or_else(|_| "33554432".to_string())
+                .parse()
+                .expect("MAX_DOCUMENT_BYTES must be a valid usize"),
+            asset_backend: env::var("ASSET_BACKEND")
+                .unwrap_or_else(|_| "local".to_string())
+                .parse()
+                .expect("ASSET_BACKEND must be local"),
+            asset_dir: env::var("ASSET_DIR").unwrap_or_else(|_| "./uploads".to_string()),
+            asset_base_url: env::var("ASSET_BASE_URL").unwrap_or_default(),
         })
     }
 
@@ -55,3 +193,50 @@ impl AppConfig {
         format!("{}:{}", self.host, self.port)
     }
 }
+
+/// `PUBLIC_GRANTS`: `*` for unrestricted, or a JSON array of GROQ filters.
+/// Unset grants nothing.
+fn public_grants(value: Option<&str>) -> Result<Option<Vec<String>>, serde_json::Error> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(Some(Vec::new())),
+        Some("*") => Ok(None),
+        Some(filters) => serde_json::from_str(filters).map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_public_grants() {
+        assert_eq!(public_grants(None).unwrap(), Some(vec![]));
+        assert_eq!(public_grants(Some(" * ")).unwrap(), None);
+        assert_eq!(
+            public_grants(Some(r#"["_type == \"post\""]"#)).unwrap(),
+            Some(vec![r#"_type == "post""#.to_string()])
+        );
+        assert!(public_grants(Some("_type == \"post\"")).is_err());
+    }
+
+    #[test]
+    fn parses_log_format() {
+        assert_eq!("json".parse(), Ok(LogFormat::Json));
+        assert_eq!("Pretty".parse(), Ok(LogFormat::Pretty));
+        assert_eq!(" compact ".parse(), Ok(LogFormat::Compact));
+        assert!("yaml".parse::<LogFormat>().is_err());
+        assert_eq!(LogFormat::default(), LogFormat::Json);
+
+        let config = AppConfig {
+            log_format: "pretty".parse().unwrap(),
+            ..crate::test_support::test_config("postgres://localhost/test")
+        };
+        assert_eq!(config.log_format, LogFormat::Pretty);
+    }
+
+    #[test]
+    fn parses_asset_backend() {
+        assert_eq!(" Local".parse(), Ok(AssetBackend::Local));
+        assert!("s3".parse::<AssetBackend>().is_err());
+    }
+}
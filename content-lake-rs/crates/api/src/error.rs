@@ -3,7 +3,17 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use content_lake_core::document::validate::ValidationError;
+use content_lake_core::mutation::executor::MutationError;
+use content_lake_core::mutation::patch::PatchError;
+use content_lake_core::store::datasets::DatasetError;
+use content_lake_groq::eval::EvalError;
+use content_lake_groq::params::ParamError;
+use content_lake_groq::parser::ParseError;
+use serde::{Deserialize, Serialize};
+
+/// SQLSTATE of a statement cancelled, e.g. by `statement_timeout`.
+const QUERY_CANCELED: &str = "57014";
 
 /// API error type that maps to Sanity-compatible JSON error responses.
 #[derive(Debug, thiserror::Error)]
@@ -24,54 +34,467 @@ pub enum ApiError {
     #[error("conflict: {0}")]
     Conflict(String),
 
+    #[error("validation failed: {}", summary(.0))]
+    ValidationFailed(Vec<FieldError>),
+
     #[error("internal error: {0}")]
     Internal(String),
 
     #[error("database error: {0}")]
-    Database(#[from] sqlx::Error),
+    Database(sqlx::Error),
 }
 
-impl IntoResponse for ApiError {
-    fn into_response(self) -> Response {
-        let (status, error_type, message) = match &self {
-            ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, "notFound", msg.clone()),
-            ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "badRequest", msg.clone()),
-            ApiError::Unauthorized => (
-                StatusCode::UNAUTHORIZED,
-                "unauthorized",
-                "Authentication required".to_string(),
-            ),
-            ApiError::Forbidden(msg) => (StatusCode::FORBIDDEN, "forbidden", msg.clone()),
-            ApiError::Conflict(msg) => (StatusCode::CONFLICT, "conflict", msg.clone()),
-            ApiError::Internal(msg) => {
-                tracing::error!("Internal error: {msg}");
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "internalError",
-                    "An internal error occurred".to_string(),
-                )
+/// A field that failed validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// Path of the offending field, e.g. `_type`.
+    pub path: String,
+    pub message: String,
+}
+
+/// The messages of `errors`, joined for a single-line message.
+fn summary(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|e| e.message.as_str())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// JSON body of an error response:
+/// `{ "error": { "type", "description", "message", "statusCode", "items"? } }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub error: ErrorDetail,
+}
+
+/// The `error` object inside an [`ErrorBody`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorDetail {
+    #[serde(rename = "type")]
+    pub error_type: String,
+    /// Human-readable description; what Sanity's clients show.
+    pub description: String,
+    pub message: String,
+    pub status_code: u16,
+    /// Per-field details of a validation error.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<ErrorItem>,
+    /// Id of the failed request, filled in by the request-id middleware.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// An entry of [`ErrorDetail::items`]: `{ "error": { "type", "description", "path" } }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorItem {
+    pub error: ItemDetail,
+}
+
+/// The `error` object inside an [`ErrorItem`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemDetail {
+    #[serde(rename = "type")]
+    pub error_type: String,
+    pub description: String,
+    pub path: String,
+}
+
+impl ApiError {
+    /// HTTP status and Sanity error type string for this error.
+    pub fn status_and_type(&self) -> (StatusCode, &'static str) {
+        match self {
+            ApiError::NotFound(_) => (StatusCode::NOT_FOUND, "notFound"),
+            ApiError::BadRequest(_) => (StatusCode::BAD_REQUEST, "badRequest"),
+            ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized"),
+            ApiError::Forbidden(_) => (StatusCode::FORBIDDEN, "forbidden"),
+            ApiError::Conflict(_) => (StatusCode::CONFLICT, "conflict"),
+            ApiError::ValidationFailed(_) => (StatusCode::BAD_REQUEST, "mutationError"),
+            ApiError::Internal(_) | ApiError::Database(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "internalError")
             }
-            ApiError::Database(err) => {
-                tracing::error!("Database error: {err}");
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "internalError",
-                    "An internal error occurred".to_string(),
-                )
+        }
+    }
+
+    /// Build the typed JSON body for this error.
+    pub fn body(&self) -> ErrorBody {
+        let (status, error_type) = self.status_and_type();
+        let message = match self {
+            ApiError::NotFound(msg)
+            | ApiError::BadRequest(msg)
+            | ApiError::Forbidden(msg)
+            | ApiError::Conflict(msg) => msg.clone(),
+            ApiError::ValidationFailed(errors) => summary(errors),
+            ApiError::Unauthorized => "Authentication required".to_string(),
+            ApiError::Internal(_) | ApiError::Database(_) => {
+                "An internal error occurred".to_string()
             }
         };
+        let items = match self {
+            ApiError::ValidationFailed(errors) => errors
+                .iter()
+                .map(|e| ErrorItem {
+                    error: ItemDetail {
+                        error_type: "validationError".to_string(),
+                        description: e.message.clone(),
+                        path: e.path.clone(),
+                    },
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
 
-        let body = json!({
-            "error": {
-                "type": error_type,
-                "message": message,
-                "statusCode": status.as_u16(),
-            }
-        });
+        ErrorBody {
+            error: ErrorDetail {
+                error_type: error_type.to_string(),
+                description: message.clone(),
+                message,
+                status_code: status.as_u16(),
+                items,
+                request_id: None,
+            },
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match &self {
+            ApiError::Internal(msg) => tracing::error!("Internal error: {msg}"),
+            ApiError::Database(err) => tracing::error!("Database error: {err}"),
+            _ => {}
+        }
+
+        let (status, _) = self.status_and_type();
+        let body = self.body();
+        let mut response = (status, Json(body.clone())).into_response();
+        // Lets the request-id middleware re-render the body with the id.
+        response.extensions_mut().insert(body);
+        response
+    }
+}
+
+impl From<ParseError> for ApiError {
+    fn from(err: ParseError) -> Self {
+        ApiError::BadRequest(err.to_string())
+    }
+}
+
+impl From<ParamError> for ApiError {
+    fn from(err: ParamError) -> Self {
+        ApiError::BadRequest(err.to_string())
+    }
+}
+
+impl From<EvalError> for ApiError {
+    fn from(err: EvalError) -> Self {
+        ApiError::BadRequest(err.to_string())
+    }
+}
+
+impl From<PatchError> for ApiError {
+    fn from(err: PatchError) -> Self {
+        ApiError::BadRequest(err.to_string())
+    }
+}
+
+impl From<ValidationError> for FieldError {
+    fn from(err: ValidationError) -> Self {
+        let path = match &err {
+            ValidationError::MissingId | ValidationError::EmptyId => "_id",
+            ValidationError::MissingType | ValidationError::EmptyType => "_type",
+            ValidationError::NotAnObject => "",
+            ValidationError::InvalidPatchPath { path, .. }
+            | ValidationError::DuplicateKey { path, .. } => path,
+            ValidationError::ReservedKey(key) => key,
+        };
+        FieldError {
+            path: path.to_string(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<ValidationError> for ApiError {
+    fn from(err: ValidationError) -> Self {
+        ApiError::ValidationFailed(vec![err.into()])
+    }
+}
+
+impl From<MutationError> for ApiError {
+    fn from(err: MutationError) -> Self {
+        match err {
+            MutationError::AlreadyExists(_)
+            | MutationError::DuplicateTransaction(_)
+            | MutationError::RevisionMismatch { .. }
+            | MutationError::StillReferenced { .. } => ApiError::Conflict(err.to_string()),
+            MutationError::NotFound(_) => ApiError::NotFound(err.to_string()),
+            MutationError::ValidationFailed(err) => err.into(),
+            MutationError::PatchFailed(_)
+            | MutationError::InvalidQuery(_)
+            | MutationError::DocumentTooLarge { .. } => ApiError::BadRequest(err.to_string()),
+            MutationError::Database(err) => ApiError::Database(err),
+        }
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    /// Statements cancelled by the query timeout are the client's to fix.
+    fn from(err: sqlx::Error) -> Self {
+        let code = err.as_database_error().and_then(|e| e.code());
+        if code.as_deref() == Some(QUERY_CANCELED) {
+            ApiError::BadRequest("query timed out".into())
+        } else {
+            ApiError::Database(err)
+        }
+    }
+}
 
-        (status, Json(body)).into_response()
+impl From<DatasetError> for ApiError {
+    fn from(err: DatasetError) -> Self {
+        match err {
+            DatasetError::InvalidName(_) => ApiError::BadRequest(err.to_string()),
+            DatasetError::AlreadyExists(_) | DatasetError::NotEmpty { .. } => {
+                ApiError::Conflict(err.to_string())
+            }
+            DatasetError::NotFound(_) => ApiError::NotFound(err.to_string()),
+            DatasetError::Database(err) => ApiError::Database(err),
+        }
     }
 }
 
 /// Convenience type alias for route handlers.
 pub type ApiResult<T> = Result<T, ApiError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn render(err: ApiError) -> (StatusCode, ErrorBody) {
+        let response = err.into_response();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn not_found_body_shape() {
+        let (status, body) = render(ApiError::NotFound("document missing".into())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(
+            body,
+            ErrorBody {
+                error: ErrorDetail {
+                    error_type: "notFound".into(),
+                    description: "document missing".into(),
+                    message: "document missing".into(),
+                    status_code: 404,
+                    items: Vec::new(),
+                    request_id: None,
+                },
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn internal_error_hides_message() {
+        let (status, body) = render(ApiError::Internal("secret detail".into())).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body.error.error_type, "internalError");
+        assert_eq!(body.error.message, "An internal error occurred");
+    }
+
+    #[tokio::test]
+    async fn parse_error_maps_to_bad_request() {
+        let err = content_lake_groq::parser::parse("*[").unwrap_err();
+        let (status, body) = render(err.into()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body.error.error_type, "badRequest");
+        assert_eq!(body.error.status_code, 400);
+    }
+
+    #[tokio::test]
+    async fn eval_error_maps_to_bad_request() {
+        let (status, body) = render(EvalError::Unsupported.into()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body.error.error_type, "badRequest");
+        assert_eq!(body.error.message, "unsupported expression");
+    }
+
+    #[tokio::test]
+    async fn patch_error_maps_to_bad_request() {
+        let err = PatchError::InvalidPath("a[".into());
+        let (status, body) = render(err.into()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body.error.error_type, "badRequest");
+        assert_eq!(body.error.message, "invalid path: a[");
+    }
+
+    #[tokio::test]
+    async fn validation_error_maps_to_bad_request() {
+        let (status, body) = render(ValidationError::MissingType.into()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body.error.error_type, "mutationError");
+        assert_eq!(body.error.message, "document _type is required");
+    }
+
+    #[tokio::test]
+    async fn mutation_errors_map_to_statuses() {
+        let cases = [
+            (
+                MutationError::RevisionMismatch {
+                    id: "p1".into(),
+                    expected: "r1".into(),
+                    found: "r2".into(),
+                },
+                StatusCode::CONFLICT,
+                "conflict",
+            ),
+            (
+                MutationError::AlreadyExists("p1".into()),
+                StatusCode::CONFLICT,
+                "conflict",
+            ),
+            (
+                MutationError::DuplicateTransaction("t1".into()),
+                StatusCode::CONFLICT,
+                "conflict",
+            ),
+            (
+                MutationError::StillReferenced {
+                    id: "a1".into(),
+                    by: vec!["p1".into()],
+                },
+                StatusCode::CONFLICT,
+                "conflict",
+            ),
+            (
+                MutationError::NotFound("p1".into()),
+                StatusCode::NOT_FOUND,
+                "notFound",
+            ),
+            (
+                ValidationError::MissingType.into(),
+                StatusCode::BAD_REQUEST,
+                "mutationError",
+            ),
+            (
+                PatchError::TypeMismatch {
+                    path: "views".into(),
+                    expected: "number".into(),
+                }
+                .into(),
+                StatusCode::BAD_REQUEST,
+                "badRequest",
+            ),
+            (
+                MutationError::InvalidQuery("*[".into()),
+                StatusCode::BAD_REQUEST,
+                "badRequest",
+            ),
+            (
+                MutationError::DocumentTooLarge {
+                    id: "p1".into(),
+                    size: 2048,
+                    limit: 1024,
+                },
+                StatusCode::BAD_REQUEST,
+                "badRequest",
+            ),
+            (
+                sqlx::Error::PoolTimedOut.into(),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internalError",
+            ),
+        ];
+        for (err, status, error_type) in cases {
+            let message = err.to_string();
+            let (actual, body) = render(err.into()).await;
+            assert_eq!(
+                (actual, body.error.error_type.as_str()),
+                (status, error_type)
+            );
+            if status != StatusCode::INTERNAL_SERVER_ERROR {
+                assert!(message.ends_with(&body.error.description), "{message}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelled_statements_map_to_bad_request() {
+        let Some(state) = crate::test_support::test_state().await else {
+            return;
+        };
+        let dataset = crate::test_support::create_dataset(&state).await;
+        crate::test_support::seed(
+            &state,
+            &dataset,
+            serde_json::json!([{"create": {"_id": "p1", "_type": "post"}}]),
+        )
+        .await;
+        let dataset_id = state.dataset_id(&dataset).await.unwrap();
+        let store = state
+            .store()
+            .clone()
+            .with_statement_timeout(Some(std::time::Duration::from_millis(10)));
+        let slow = content_lake_groq::sql_gen::SqlFilter {
+            sql: "(pg_sleep(0.5) IS NOT NULL)".into(),
+            binds: vec![],
+        };
+
+        let err = store
+            .count_matching(dataset_id, &slow, false)
+            .await
+            .unwrap_err();
+        let (status, body) = render(err.into()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body.error.message, "query timed out");
+    }
+
+    #[tokio::test]
+    async fn error_envelope_json_shape() {
+        let json = |err: ApiError| serde_json::to_value(err.body()).unwrap();
+        assert_eq!(
+            json(ApiError::ValidationFailed(vec![
+                FieldError {
+                    path: "_id".into(),
+                    message: "document _id is required".into(),
+                },
+                FieldError {
+                    path: "title".into(),
+                    message: "title must be a string".into(),
+                },
+            ])),
+            serde_json::json!({"error": {
+                "type": "mutationError",
+                "description": "document _id is required; title must be a string",
+                "message": "document _id is required; title must be a string",
+                "statusCode": 400,
+                "items": [
+                    {"error": {
+                        "type": "validationError",
+                        "description": "document _id is required",
+                        "path": "_id",
+                    }},
+                    {"error": {
+                        "type": "validationError",
+                        "description": "title must be a string",
+                        "path": "title",
+                    }},
+                ],
+            }})
+        );
+        assert_eq!(
+            json(ApiError::NotFound("document missing".into())),
+            serde_json::json!({"error": {
+                "type": "notFound",
+                "description": "document missing",
+                "message": "document missing",
+                "statusCode": 404,
+            }})
+        );
+    }
+}
@@ -1,9 +1,14 @@
+mod auth;
+mod cache;
 mod config;
 mod error;
 mod middleware;
 mod routes;
 mod state;
 
+#[cfg(test)]
+mod test_support;
+
 use content_lake_core::events::bus::EventBus;
 use sqlx::postgres::PgPoolOptions;
 use tracing_subscriber::EnvFilter;
@@ -18,12 +23,14 @@ async fn main() -> anyhow::Result<()> {
         .map_err(|e| anyhow::anyhow!("Failed to load config: {e}. Is DATABASE_URL set?"))?;
 
     // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.log_level)),
-        )
-        .json()
-        .init();
+    let subscriber = tracing_subscriber::fmt().with_env_filter(
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.log_level)),
+    );
+    match config.log_format {
+        config::LogFormat::Json => subscriber.json().init(),
+        config::LogFormat::Pretty => subscriber.pretty().init(),
+        config::LogFormat::Compact => subscriber.compact().init(),
+    }
 
     tracing::info!("Starting Content Lake API server");
 
@@ -54,6 +61,9 @@ async fn main() -> anyhow::Result<()> {
     // Build router with middleware
     let app = routes::build_router(state)
         .layer(middleware::request_tracing::trace_layer())
+        .layer(axum::middleware::from_fn(
+            middleware::request_id::propagate_request_id,
+        ))
         .layer(middleware::cors::cors_layer());
 
     // Start server
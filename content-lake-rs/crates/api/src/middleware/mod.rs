@@ -1,2 +1,3 @@
 pub mod cors;
+pub mod request_id;
 pub mod request_tracing;
@@ -0,0 +1,114 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+use uuid::Uuid;
+
+use crate::error::ErrorBody;
+
+/// Header carrying the request id in both directions.
+pub static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest incoming request id that is accepted rather than replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Id of the current request, available to handlers as an extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Take the request id from `X-Request-Id`, or generate one, and attach it
+/// to the request extensions, the tracing span, the response header and the
+/// body of `ApiError` responses.
+pub async fn propagate_request_id(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&X_REQUEST_ID)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .map_or_else(|| Uuid::new_v4().to_string(), str::to_string);
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let span = tracing::info_span!("request", request_id = %id);
+    let mut response = next.run(request).instrument(span).await;
+
+    if let Some(mut body) = response.extensions_mut().remove::<ErrorBody>() {
+        body.error.request_id = Some(id.clone());
+        let json = serde_json::to_vec(&body).expect("error bodies serialize to JSON");
+        response.headers_mut().remove(header::CONTENT_LENGTH);
+        *response.body_mut() = Body::from(json);
+    }
+    // Visible ASCII was checked by `to_str`, and a generated UUID is valid.
+    let value = HeaderValue::from_str(&id).expect("request id is a valid header value");
+    response.headers_mut().insert(X_REQUEST_ID.clone(), value);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::{routing::get, Router};
+    use tower::ServiceExt;
+
+    use crate::error::ApiError;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route(
+                "/missing",
+                get(|| async { Err::<(), _>(ApiError::NotFound("no such document".into())) }),
+            )
+            .layer(axum::middleware::from_fn(propagate_request_id))
+    }
+
+    async fn call(uri: &str, id: Option<&str>) -> (StatusCode, Option<String>, Vec<u8>) {
+        let mut request = Request::get(uri);
+        if let Some(id) = id {
+            request = request.header(&X_REQUEST_ID, id);
+        }
+        let response = app()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let id = response
+            .headers()
+            .get(&X_REQUEST_ID)
+            .map(|v| v.to_str().unwrap().to_string());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, id, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn echoes_incoming_request_id() {
+        let (status, id, _) = call("/ok", Some("trace-123")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(id.as_deref(), Some("trace-123"));
+
+        let (status, id, body) = call("/missing", Some("trace-456")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(id.as_deref(), Some("trace-456"));
+        let body: ErrorBody = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.error.request_id.as_deref(), Some("trace-456"));
+        assert_eq!(body.error.message, "no such document");
+    }
+
+    #[tokio::test]
+    async fn generates_request_id_when_missing() {
+        let (_, id, _) = call("/ok", None).await;
+        let id = id.expect("generated request id");
+        assert!(Uuid::parse_str(&id).is_ok(), "{id}");
+
+        let (_, first, _) = call("/ok", Some("")).await;
+        let (_, second, _) = call("/ok", None).await;
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use content_lake_core::assets::image::ImageInfo;
+use content_lake_core::assets::transform::{Format, Transform, TransformError};
+use content_lake_core::mutation::executor::{apply_transaction, TransactionOptions};
+use content_lake_core::mutation::types::{CreateIfNotExistsMutation, Mutation};
+use content_lake_core::store::DocumentStore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+
+/// Maximum accepted upload size.
+const ASSET_BODY_LIMIT: usize = 32 * 1024 * 1024;
+
+/// Assets are addressed by content, so any copy can be kept indefinitely.
+const CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// Asset routes.
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/v1/assets/images/{dataset}",
+            post(upload_image).layer(DefaultBodyLimit::max(ASSET_BODY_LIMIT)),
+        )
+        .route("/assets/images/{dataset}/{file}", get(serve_image))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct UploadParams {
+    /// Name of the uploaded file, kept as `originalFilename`.
+    filename: Option<String>,
+}
+
+/// Sanity-compatible upload response.
+#[derive(Debug, Serialize, Deserialize)]
+struct AssetResponse {
+    document: Value,
+}
+
+/// Store an image sent as the raw request body and describe it with a
+/// `sanity.imageAsset` document. Assets are addressed by content, so
+/// uploading the same bytes again returns the existing document.
+async fn upload_image(
+    State(state): State<AppState>,
+    Path(dataset): Path<String>,
+    Query(params): Query<UploadParams>,
+    body: Bytes,
+) -> ApiResult<Json<AssetResponse>> {
+    let dataset_id = state.dataset_id(&dataset).await?;
+    let info = ImageInfo::from_bytes(&body).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    let path = info.path(&dataset);
+    let storage_error = |e: std::io::Error| ApiError::Internal(format!("asset storage: {e}"));
+    let blobs = state.blob_store();
+    blobs.put(&path, &body).await.map_err(storage_error)?;
+    let url = blobs.url(&path).await.map_err(storage_error)?;
+    let document = info.document(&dataset, &url, params.filename.as_deref());
+    let mutations = [Mutation::CreateIfNotExists(CreateIfNotExistsMutation {
+        document,
+    })];
+    let options = TransactionOptions {
+        max_document_bytes: Some(state.config().max_document_bytes),
+        ..Default::default()
+    };
+    apply_transaction(state.store(), dataset_id, &mutations, options)
+        .await
+        .map_err(|err| state.dataset_write_error(&dataset, err))?
+        .publish(state.event_bus());
+
+    let row = state
+        .store()
+        .get(dataset_id, &info.document_id())
+        .await?
+        .ok_or_else(|| ApiError::Internal("asset document missing after upload".into()))?;
+    Ok(Json(AssetResponse {
+        document: row.to_document(),
+    }))
+}
+
+/// Serve an uploaded image, transformed by Sanity's `w`, `h`, `fit` and `fm`
+/// parameters (see [`Transform`]). Each variant is rendered once and kept in
+/// the blob store under `transforms/`.
+async fn serve_image(
+    State(state): State<AppState>,
+    Path((dataset, file)): Path<(String, String)>,
+    Query(raw): Query<HashMap<String, String>>,
+) -> ApiResult<Response> {
+    let transform = Transform::from_query(&raw).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let not_found = || ApiError::NotFound(format!("asset not found: {file}"));
+    let original_format = file
+        .rsplit_once('.')
+        .and_then(|(_, ext)| Format::from_extension(ext))
+        .ok_or_else(not_found)?;
+
+    let storage_error = |e: std::io::Error| ApiError::Internal(format!("asset storage: {e}"));
+    let blobs = state.blob_store();
+    let path = format!("images/{dataset}/{file}");
+    let format = transform.format.unwrap_or(original_format);
+    let variant = format!(
+        "transforms/{path}/{}.{}",
+        transform.key(),
+        format.extension()
+    );
+
+    let cached = if transform.is_identity() {
+        None
+    } else {
+        blobs.get(&variant).await.map_err(storage_error)?
+    };
+    let bytes = match cached {
+        Some(bytes) => bytes,
+        None => {
+            let original = blobs
+                .get(&path)
+                .await
+                .map_err(storage_error)?
+                .ok_or_else(not_found)?;
+            if transform.is_identity() {
+                original
+            } else {
+                let rendered = tokio::task::spawn_blocking(move || {
+                    transform.apply(&original, original_format)
+                })
+                .await
+                .map_err(|e| ApiError::Internal(e.to_string()))?
+                .map_err(|e| match e {
+                    TransformError::InvalidParam { .. } => ApiError::BadRequest(e.to_string()),
+                    _ => ApiError::Internal(e.to_string()),
+                })?;
+                blobs
+                    .put(&variant, &rendered)
+                    .await
+                    .map_err(storage_error)?;
+                rendered
+            }
+        }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.mime_type()),
+            (header::CACHE_CONTROL, CACHE_CONTROL),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header, Request, StatusCode};
+
+    use super::*;
+    use crate::test_support::{create_dataset, send, test_state};
+
+    /// A 2x1 RGBA PNG.
+    const PNG: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0xf4,
+        0x22, 0x7f, 0x8a, 0x00, 0x00, 0x00, 0x0e, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0xf8,
+        0xcf, 0xc0, 0x00, 0x42, 0xff, 0x01, 0x0f, 0xf9, 0x03, 0xfd, 0x98, 0x79, 0xd7, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    fn upload(uri: String, body: &'static [u8]) -> Request<axum::body::Body> {
+        Request::post(uri)
+            .header(header::CONTENT_TYPE, "image/png")
+            .body(body.into())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn uploads_png_and_creates_asset_document() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+
+        let uri = format!("/v1/assets/images/{dataset}?filename=dot.png");
+        let (status, _, body) = send(&state, upload(uri.clone(), PNG)).await;
+        assert_eq!(status, StatusCode::OK);
+        let doc = serde_json::from_slice::<AssetResponse>(&body)
+            .unwrap()
+            .document;
+        let sha1 = doc["sha1hash"].as_str().unwrap().to_string();
+        assert_eq!(doc["_id"], format!("image-{sha1}-2x1-png"));
+        assert_eq!(doc["_type"], "sanity.imageAsset");
+        assert_eq!(doc["mimeType"], "image/png");
+        assert_eq!(doc["size"], PNG.len());
+        assert_eq!(doc["originalFilename"], "dot.png");
+        assert_eq!(
+            doc["url"],
+            format!("http://localhost/assets/images/{dataset}/{sha1}-2x1.png")
+        );
+        assert_eq!(doc["metadata"]["dimensions"]["width"], 2);
+        assert_eq!(doc["metadata"]["dimensions"]["height"], 1);
+
+        let path = doc["path"].as_str().unwrap();
+        assert_eq!(path, format!("images/{dataset}/{sha1}-2x1.png"));
+        let stored = state.blob_store().get(path).await.unwrap();
+        assert_eq!(stored.as_deref(), Some(PNG));
+
+        // The same bytes map to the same document.
+        let (status, _, body) = send(&state, upload(uri, PNG)).await;
+        assert_eq!(status, StatusCode::OK);
+        let again = serde_json::from_slice::<AssetResponse>(&body)
+            .unwrap()
+            .document;
+        assert_eq!(again["_rev"], doc["_rev"]);
+
+        let (status, _, _) = send(
+            &state,
+            upload(format!("/v1/assets/images/{dataset}"), b"not an image"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn serves_resized_and_converted_variants() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        let (_, _, body) = send(&state, upload(format!("/v1/assets/images/{dataset}"), PNG)).await;
+        let doc = serde_json::from_slice::<AssetResponse>(&body)
+            .unwrap()
+            .document;
+        let path = doc["path"].as_str().unwrap().to_string();
+        let get = |query: &str| {
+            Request::get(format!("/assets/{path}{query}"))
+                .body(Default::default())
+                .unwrap()
+        };
+        let content_type = |headers: &axum::http::HeaderMap| {
+            headers[header::CONTENT_TYPE].to_str().unwrap().to_string()
+        };
+
+        let (status, headers, body) = send(&state, get("")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type(&headers), "image/png");
+        assert_eq!(&body[..], PNG);
+
+        for _ in 0..2 {
+            let (status, headers, body) = send(&state, get("?w=8")).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(content_type(&headers), "image/png");
+            let img = image::load_from_memory(&body).unwrap();
+            assert_eq!((img.width(), img.height()), (8, 4));
+        }
+        let variant = format!("transforms/{path}/w8-hauto-clip-orig.png");
+        assert!(state.blob_store().get(&variant).await.unwrap().is_some());
+
+        let (status, headers, body) = send(&state, get("?fm=jpg&w=4&h=4&fit=crop")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type(&headers), "image/jpeg");
+        assert_eq!(
+            image::guess_format(&body).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+        let img = image::load_from_memory(&body).unwrap();
+        assert_eq!((img.width(), img.height()), (4, 4));
+
+        let (status, _, _) = send(&state, get("?w=huge")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _, _) = send(&state, get("?fm=tiff")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let missing = Request::get(format!("/assets/images/{dataset}/nope-1x1.png"))
+            .body(Default::default())
+            .unwrap();
+        let (status, _, _) = send(&state, missing).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    routing::get,
+    Json, Router,
+};
+use content_lake_core::store::datasets::Dataset;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+
+/// Dataset management routes.
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/v1/datasets", get(list_datasets))
+        .route(
+            "/v1/datasets/{dataset}",
+            get(get_dataset).put(create_dataset).delete(delete_dataset),
+        )
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DatasetsResponse {
+    datasets: Vec<Dataset>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DeleteResponse {
+    deleted: bool,
+}
+
+/// All datasets with their live document counts, ordered by name.
+async fn list_datasets(State(state): State<AppState>) -> ApiResult<Json<DatasetsResponse>> {
+    let datasets = state.store().list_datasets().await?;
+    Ok(Json(DatasetsResponse { datasets }))
+}
+
+async fn get_dataset(
+    State(state): State<AppState>,
+    Path(dataset): Path<String>,
+) -> ApiResult<Json<Dataset>> {
+    state
+        .store()
+        .dataset(&dataset)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("dataset not found: {dataset}")))
+}
+
+/// Create an empty dataset. Names already taken are a conflict.
+async fn create_dataset(
+    State(state): State<AppState>,
+    Path(dataset): Path<String>,
+) -> ApiResult<Json<Dataset>> {
+    Ok(Json(state.store().create_dataset(&dataset).await?))
+}
+
+/// Delete a dataset with its documents, history and references. Refused
+/// with a conflict while it holds live documents, unless `purge=true`.
+async fn delete_dataset(
+    State(state): State<AppState>,
+    Path(dataset): Path<String>,
+    Query(raw): Query<HashMap<String, String>>,
+) -> ApiResult<Json<DeleteResponse>> {
+    let purge = raw.get("purge").is_some_and(|v| v == "true");
+    state.delete_dataset(&dataset, purge).await?;
+    Ok(Json(DeleteResponse { deleted: true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Request, StatusCode};
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::test_support::{seed, send, test_state};
+
+    fn request(method: &str, uri: &str) -> Request<axum::body::Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Default::default())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn creates_lists_and_deletes_datasets() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let name = format!("ds-{}", Uuid::new_v4().simple());
+        let uri = format!("/v1/datasets/{name}");
+
+        let (status, _, body) = send(&state, request("PUT", &uri)).await;
+        assert_eq!(status, StatusCode::OK);
+        let created: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(created["name"], name.as_str());
+        assert_eq!(created["documentCount"], 0);
+        assert!(state.dataset_id(&name).await.is_ok());
+
+        let (status, _, _) = send(&state, request("PUT", &uri)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _, _) = send(&state, request("PUT", "/v1/datasets/Not%20Valid")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        seed(
+            &state,
+            &name,
+            json!([{"create": {"_id": "p1", "_type": "post"}}]),
+        )
+        .await;
+        let (status, _, body) = send(&state, request("GET", "/v1/datasets")).await;
+        assert_eq!(status, StatusCode::OK);
+        let listed = serde_json::from_slice::<DatasetsResponse>(&body).unwrap();
+        let entry = listed
+            .datasets
+            .iter()
+            .find(|d| d.name == name)
+            .expect("new dataset is listed");
+        assert_eq!(entry.document_count, 1);
+
+        let (status, _, body) = send(&state, request("DELETE", &uri)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let error: Value = serde_json::from_slice(&body).unwrap();
+        assert!(error["error"]["description"]
+            .as_str()
+            .unwrap()
+            .contains("purge=true"));
+        let dataset_id = state.dataset_id(&name).await.unwrap();
+
+        let (status, _, body) = send(&state, request("DELETE", &format!("{uri}?purge=true"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            serde_json::from_slice::<DeleteResponse>(&body)
+                .unwrap()
+                .deleted
+        );
+        let (status, _, _) = send(&state, request("GET", &uri)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let remaining: i64 =
+            sqlx::query_scalar("SELECT count(*) FROM documents WHERE dataset_id = $1")
+                .bind(dataset_id)
+                .fetch_one(state.pool())
+                .await
+                .unwrap();
+        assert_eq!(remaining, 0);
+
+        let (status, _, _) = send(&state, request("DELETE", &uri)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn resolves_dataset_names_in_paths() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let name = format!("ds-{}", Uuid::new_v4().simple());
+        let (status, _, _) = send(&state, request("PUT", &format!("/v1/datasets/{name}"))).await;
+        assert_eq!(status, StatusCode::OK);
+        seed(
+            &state,
+            &name,
+            json!([{"create": {"_id": "p1", "_type": "post"}}]),
+        )
+        .await;
+
+        let id = state.dataset_id(&name).await.unwrap();
+        assert_eq!(state.store().dataset_id(&name).await.unwrap(), Some(id));
+        let (status, _, body) =
+            send(&state, request("GET", &format!("/v1/data/doc/{name}/p1"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["documents"][0]["_id"], "p1");
+
+        let unknown = format!("ds-{}", Uuid::new_v4().simple());
+        let (status, _, body) = send(
+            &state,
+            request("GET", &format!("/v1/data/doc/{unknown}/p1")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["error"]["description"],
+            format!("dataset not found: {unknown}")
+        );
+
+        // Deleting evicts the cached id, so the name stops resolving.
+        let uri = format!("/v1/datasets/{name}?purge=true");
+        let (status, _, _) = send(&state, request("DELETE", &uri)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(matches!(
+            state.dataset_id(&name).await,
+            Err(ApiError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn names_are_unique_per_project_and_resolved_in_the_default_one() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let name = format!("ds-{}", Uuid::new_v4().simple());
+        let other: Uuid = sqlx::query_scalar(
+            "WITH p AS (INSERT INTO projects (name) VALUES ($1) RETURNING id) \
+             INSERT INTO datasets (project_id, name) SELECT id, $2 FROM p RETURNING id",
+        )
+        .bind(format!("other-{}", Uuid::new_v4()))
+        .bind(&name)
+        .fetch_one(state.pool())
+        .await
+        .unwrap();
+
+        // Another project's dataset is invisible to the API...
+        let uri = format!("/v1/datasets/{name}");
+        let (status, _, _) = send(&state, request("GET", &uri)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // ...and doesn't keep the default project from using its name.
+        let (status, _, _) = send(&state, request("PUT", &uri)).await;
+        assert_eq!(status, StatusCode::OK);
+        let id = state.dataset_id(&name).await.unwrap();
+        assert_ne!(id, other);
+    }
+
+    #[tokio::test]
+    async fn writes_to_a_dataset_deleted_elsewhere_are_not_found() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let name = format!("ds-{}", Uuid::new_v4().simple());
+        let (status, _, _) = send(&state, request("PUT", &format!("/v1/datasets/{name}"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let id = state.dataset_id(&name).await.unwrap();
+
+        // Deleted by another node: this one still has the id cached.
+        sqlx::query("DELETE FROM datasets WHERE id = $1")
+            .bind(id)
+            .execute(state.pool())
+            .await
+            .unwrap();
+        assert_eq!(state.dataset_id(&name).await.unwrap(), id);
+
+        let (status, _, body) = send(
+            &state,
+            Request::post(format!("/v1/data/mutate/{name}"))
+                .header("content-type", "application/json")
+                .body(
+                    json!({"mutations": [{"create": {"_id": "p1", "_type": "post"}}]})
+                        .to_string()
+                        .into(),
+                )
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["error"]["description"],
+            format!("dataset not found: {name}")
+        );
+        assert!(matches!(
+            state.dataset_id(&name).await,
+            Err(ApiError::NotFound(_))
+        ));
+    }
+}
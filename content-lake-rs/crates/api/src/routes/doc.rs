@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use content_lake_core::document::model::DocumentRow;
+use content_lake_core::store::DocumentStore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha1::{Digest, Sha1};
+
+use crate::auth::ReadGrants;
+use crate::error::ApiResult;
+use crate::state::AppState;
+
+/// Clients may cache documents but must revalidate them with `If-None-Match`.
+const CACHE_CONTROL: &str = "private, no-cache";
+
+/// Document routes.
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/v1/data/doc/{dataset}/{ids}", get(get_documents))
+}
+
+/// Sanity-compatible document response.
+#[derive(Debug, Serialize, Deserialize)]
+struct DocResponse {
+    documents: Vec<Value>,
+    omitted: Vec<Omitted>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Omitted {
+    id: String,
+    reason: String,
+}
+
+/// Fetch one or more documents by id (`a,b,c`). Ids that don't exist are
+/// listed under `omitted`. Responses carry an `ETag` built from the
+/// documents' revisions and answer a matching `If-None-Match` with 304.
+/// Documents the requester's grants don't cover are omitted for `permission`.
+/// Soft-deleted documents are returned only with `includeDeleted=true`.
+async fn get_documents(
+    State(state): State<AppState>,
+    Path((dataset, ids)): Path<(String, String)>,
+    Query(raw): Query<HashMap<String, String>>,
+    grants: ReadGrants,
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    let dataset_id = state.dataset_id(&dataset).await?;
+    let include_deleted = raw.get("includeDeleted").is_some_and(|v| v == "true");
+    let mut rows: Vec<(String, Option<DocumentRow>)> = Vec::new();
+    let mut hidden = Vec::new();
+    for id in ids.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+        let row = if include_deleted {
+            state.store().get_including_deleted(dataset_id, id).await?
+        } else {
+            state.store().get(dataset_id, id).await?
+        };
+        let row = match row {
+            Some(row) if !grants.allows(&row.to_document()) => {
+                hidden.push(id.to_string());
+                None
+            }
+            row => row,
+        };
+        rows.push((id.to_string(), row));
+    }
+
+    let etag = etag(&rows);
+    let cache_headers = [
+        (
+            header::ETAG,
+            HeaderValue::from_str(&etag).expect("hex etag is a valid header"),
+        ),
+        (
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(CACHE_CONTROL),
+        ),
+    ];
+    if if_none_match(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+    }
+
+    let mut response = DocResponse {
+        documents: Vec::new(),
+        omitted: Vec::new(),
+    };
+    for (id, row) in rows {
+        match row {
+            Some(row) => response.documents.push(row.to_document()),
+            None => {
+                let reason = if hidden.contains(&id) {
+                    "permission"
+                } else {
+                    "existence"
+                };
+                response.omitted.push(Omitted {
+                    id,
+                    reason: reason.into(),
+                });
+            }
+        }
+    }
+    Ok((cache_headers, Json(response)).into_response())
+}
+
+/// Strong entity tag: the hex SHA-1 of the requested ids' revisions, each
+/// length-prefixed, with a marker for missing ones. Revisions are
+/// client-supplied transaction ids, so they're hashed rather than quoted.
+fn etag(rows: &[(String, Option<DocumentRow>)]) -> String {
+    let mut hasher = Sha1::new();
+    for (_, row) in rows {
+        match row {
+            Some(row) => {
+                hasher.update([1]);
+                hasher.update((row.revision.len() as u64).to_be_bytes());
+                hasher.update(row.revision.as_bytes());
+            }
+            None => hasher.update([0]),
+        }
+    }
+    format!("\"{:x}\"", hasher.finalize())
+}
+
+/// Whether `If-None-Match` lists `etag` (weak comparison) or is `*`.
+fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use serde_json::json;
+
+    use crate::test_support::{create_dataset, seed, send, test_state, token};
+
+    #[test]
+    fn if_none_match_compares_weakly() {
+        let mut headers = HeaderMap::new();
+        assert!(!if_none_match(&headers, "\"r1\""));
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_static("\"r0\", W/\"r1\""),
+        );
+        assert!(if_none_match(&headers, "\"r1\""));
+        assert!(!if_none_match(&headers, "\"r2\""));
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("*"));
+        assert!(if_none_match(&headers, "\"r2\""));
+    }
+
+    fn get(dataset: &str, ids: &str, if_none_match: Option<&str>) -> Request<axum::body::Body> {
+        let mut request = Request::get(format!("/v1/data/doc/{dataset}/{ids}"));
+        if let Some(tag) = if_none_match {
+            request = request.header(header::IF_NONE_MATCH, tag);
+        }
+        request.body(Default::default()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn conditional_request_returns_not_modified() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        seed(
+            &state,
+            &dataset,
+            json!([{"create": {"_id": "a", "_type": "post", "title": "v1"}}]),
+        )
+        .await;
+
+        let (status, headers, body) = send(&state, get(&dataset, "a,missing", None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CACHE_CONTROL], CACHE_CONTROL);
+        let body: DocResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.documents[0]["title"], "v1");
+        assert_eq!(body.omitted[0].id, "missing");
+        let tag = headers[header::ETAG].to_str().unwrap().to_string();
+        assert_eq!(tag.len(), 42, "{tag}");
+
+        let (status, headers, body) = send(&state, get(&dataset, "a,missing", Some(&tag))).await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+        assert_eq!(headers[header::ETAG], tag.as_str());
+        assert!(body.is_empty());
+
+        seed(
+            &state,
+            &dataset,
+            json!([{"patch": {"id": "a", "set": {"title": "v2"}}}]),
+        )
+        .await;
+        let (status, headers, _) = send(&state, get(&dataset, "a,missing", Some(&tag))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_ne!(headers[header::ETAG], tag.as_str());
+    }
+
+    #[tokio::test]
+    async fn documents_outside_grants_are_omitted_for_permission() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        seed(
+            &state,
+            &dataset,
+            json!([
+                {"create": {"_id": "p1", "_type": "post"}},
+                {"create": {"_id": "s1", "_type": "secret"}}
+            ]),
+        )
+        .await;
+
+        let mut request = get(&dataset, "p1,s1,missing", None);
+        request.headers_mut().insert(
+            header::AUTHORIZATION,
+            token(&state, Some(&[r#"_type == "post""#]))
+                .parse()
+                .unwrap(),
+        );
+        let (status, _, body) = send(&state, request).await;
+        assert_eq!(status, StatusCode::OK);
+        let body: DocResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.documents.len(), 1);
+        assert_eq!(body.documents[0]["_id"], "p1");
+        let omitted: Vec<_> = body
+            .omitted
+            .iter()
+            .map(|o| (o.id.as_str(), o.reason.as_str()))
+            .collect();
+        assert_eq!(omitted, [("s1", "permission"), ("missing", "existence")]);
+    }
+
+    #[tokio::test]
+    async fn soft_deleted_documents_are_hidden_unless_requested() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        seed(
+            &state,
+            &dataset,
+            json!([
+                {"create": {"_id": "a", "_type": "post", "title": "v1"}},
+                {"create": {"_id": "b", "_type": "post"}}
+            ]),
+        )
+        .await;
+        seed(&state, &dataset, json!([{"delete": {"id": "a"}}])).await;
+        let fetch = |uri: String| async {
+            let (status, _, body) =
+                send(&state, Request::get(uri).body(Default::default()).unwrap()).await;
+            assert_eq!(status, StatusCode::OK);
+            body
+        };
+        let json = |body: axum::body::Bytes| serde_json::from_slice::<Value>(&body).unwrap();
+
+        let body = json(fetch(format!("/v1/data/doc/{dataset}/a")).await);
+        assert_eq!(body["documents"], json!([]));
+        assert_eq!(body["omitted"][0]["reason"], "existence");
+        let body = json(fetch(format!("/v1/data/doc/{dataset}/a?includeDeleted=true")).await);
+        assert_eq!(body["documents"][0]["title"], "v1");
+
+        let query = format!("/v1/data/query/{dataset}?query=count(*)");
+        assert_eq!(json(fetch(query.clone()).await)["result"], 1);
+        assert_eq!(
+            json(fetch(format!("{query}&includeDeleted=true")).await)["result"],
+            2
+        );
+
+        let export = format!("/v1/data/export/{dataset}");
+        let lines =
+            |body: axum::body::Bytes| String::from_utf8(body.to_vec()).unwrap().lines().count();
+        assert_eq!(lines(fetch(export.clone()).await), 1);
+        assert_eq!(
+            lines(fetch(format!("{export}?includeDeleted=true")).await),
+            2
+        );
+
+        // Re-creating the id revives the document rather than conflicting.
+        seed(
+            &state,
+            &dataset,
+            json!([{"create": {"_id": "a", "_type": "post", "title": "v2"}}]),
+        )
+        .await;
+        let body = json(fetch(format!("/v1/data/doc/{dataset}/a")).await);
+        assert_eq!(body["documents"][0]["title"], "v2");
+    }
+
+    #[tokio::test]
+    async fn revisions_from_any_transaction_id_make_valid_etags() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        let transaction_id = format!("tx \"é\", {}", uuid::Uuid::new_v4());
+        let (status, _, _) = send(
+            &state,
+            Request::post(format!("/v1/data/mutate/{dataset}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(
+                    json!({
+                        "mutations": [{"create": {"_id": "a", "_type": "post"}}],
+                        "transactionId": transaction_id,
+                    })
+                    .to_string()
+                    .into(),
+                )
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, headers, _) = send(&state, get(&dataset, "a", None)).await;
+        assert_eq!(status, StatusCode::OK);
+        let tag = headers[header::ETAG].to_str().unwrap().to_string();
+        let (status, _, _) = send(&state, get(&dataset, "a", Some(&tag))).await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+    }
+}
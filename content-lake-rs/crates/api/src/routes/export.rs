@@ -0,0 +1,191 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use futures::StreamExt;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use crate::auth::ReadGrants;
+use crate::error::ApiResult;
+use crate::state::AppState;
+
+/// Rows buffered between the database cursor and the response body.
+const EXPORT_BUFFER: usize = 64;
+
+/// Export routes.
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/v1/data/export/{dataset}", get(export))
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportParams {
+    /// Comma-separated list of document types to include.
+    types: Option<String>,
+    /// Also export soft-deleted documents.
+    #[serde(default, rename = "includeDeleted")]
+    include_deleted: bool,
+}
+
+/// Stream every live document in the dataset as newline-delimited JSON;
+/// soft-deleted ones too with `includeDeleted=true`. Documents the
+/// requester's grants don't cover are left out.
+async fn export(
+    State(state): State<AppState>,
+    Path(dataset): Path<String>,
+    Query(params): Query<ExportParams>,
+    grants: ReadGrants,
+) -> ApiResult<Response> {
+    let dataset_id = state.dataset_id(&dataset).await?;
+    let types: Option<Vec<String>> = params.types.map(|types| {
+        types
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(str::to_string)
+            .collect()
+    });
+
+    let store = state.store().clone();
+    let (tx, rx) = mpsc::channel::<Result<Bytes, std::io::Error>>(EXPORT_BUFFER);
+
+    tokio::spawn(async move {
+        let mut rows = store.stream(dataset_id, types, params.include_deleted);
+
+        while let Some(row) = rows.next().await {
+            let chunk = match row {
+                Ok(row) => {
+                    let doc = row.to_document();
+                    if !grants.allows(&doc) {
+                        continue;
+                    }
+                    let mut line = serde_json::to_vec(&doc).expect("documents serialize to JSON");
+                    line.push(b'\n');
+                    Ok(Bytes::from(line))
+                }
+                Err(err) => {
+                    tracing::error!("export of dataset {dataset_id} failed: {err}");
+                    Err(std::io::Error::other(err))
+                }
+            };
+            let failed = chunk.is_err();
+            // A closed channel means the client went away; stop reading.
+            if tx.send(chunk).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Request, StatusCode};
+    use serde_json::{json, Value};
+
+    use crate::test_support::{create_dataset, seed, send, test_state, token};
+
+    #[tokio::test]
+    async fn exports_live_documents_as_ndjson() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        seed(
+            &state,
+            &dataset,
+            json!([
+                {"create": {"_id": "post-1", "_type": "post", "title": "One"}},
+                {"create": {"_id": "post-2", "_type": "post", "title": "Two"}},
+                {"create": {"_id": "page-1", "_type": "page", "title": "Home"}},
+                {"create": {"_id": "gone", "_type": "post"}},
+                {"delete": {"id": "gone"}},
+            ]),
+        )
+        .await;
+
+        let (status, headers, body) = send(
+            &state,
+            Request::get(format!("/v1/data/export/{dataset}"))
+                .body(Default::default())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers["content-type"], "application/x-ndjson");
+
+        let docs: Vec<Value> = body
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(docs.len(), 3);
+        assert!(docs
+            .iter()
+            .all(|d| d["_id"].is_string() && d["_rev"].is_string()));
+
+        let (_, _, body) = send(
+            &state,
+            Request::get(format!("/v1/data/export/{dataset}?types=page"))
+                .body(Default::default())
+                .unwrap(),
+        )
+        .await;
+        let lines: Vec<&[u8]> = body
+            .split(|b| *b == b'\n')
+            .filter(|l| !l.is_empty())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        let doc: Value = serde_json::from_slice(lines[0]).unwrap();
+        assert_eq!(doc["_id"], "page-1");
+    }
+
+    #[tokio::test]
+    async fn grants_hide_documents_from_the_export() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        seed(
+            &state,
+            &dataset,
+            json!([
+                {"create": {"_id": "post-1", "_type": "post"}},
+                {"create": {"_id": "secret-1", "_type": "secret"}},
+            ]),
+        )
+        .await;
+
+        let (status, _, body) = send(
+            &state,
+            Request::get(format!("/v1/data/export/{dataset}"))
+                .header(
+                    "authorization",
+                    token(&state, Some(&[r#"_type == "post""#])),
+                )
+                .body(Default::default())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let ids: Vec<Value> = body
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice::<Value>(line).unwrap()["_id"].clone())
+            .collect();
+        assert_eq!(ids, vec![json!("post-1")]);
+    }
+}
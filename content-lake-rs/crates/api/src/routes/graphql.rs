@@ -0,0 +1,633 @@
+use axum::{
+    extract::{Path, State},
+    routing::post,
+    Json, Router,
+};
+use content_lake_core::document::perspective::Perspective;
+use content_lake_groq::ast::Expr;
+use content_lake_groq::eval::{eval_query_in, EvalContext};
+use graphql_parser::query::{
+    parse_query, Definition, Field, OperationDefinition, Selection, SelectionSet,
+    Value as GraphQlValue,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::auth::ReadGrants;
+use crate::error::ApiResult;
+use crate::routes::query::query_documents;
+use crate::state::AppState;
+
+/// GraphQL routes.
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/v1/graphql/{dataset}", post(graphql))
+}
+
+/// A GraphQL request body.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQlRequest {
+    pub query: String,
+    #[serde(default)]
+    pub variables: Option<Map<String, Value>>,
+    #[serde(default)]
+    pub operation_name: Option<String>,
+}
+
+/// A GraphQL response: `data` on success, `errors` when the query can't be
+/// answered.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphQlResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<GraphQlError>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphQlError {
+    pub message: String,
+}
+
+/// Answer a read-only GraphQL query over the dataset's documents.
+///
+/// Each document type in `graphql_types` gets two root fields, named after
+/// the type with its first letter capitalised (`post` → `Post`):
+///
+/// - `Post(id: ID!)`: the document with that `_id`, or `null`;
+/// - `allPost(where, sort, limit, offset)`: the matching documents. `where`
+///   maps fields to `{eq, neq, gt, gte, lt, lte, in}` conditions, nesting
+///   for object fields, and `sort` is a list of `{field: ASC | DESC}`.
+///
+/// Root fields are translated to GROQ and evaluated as by the query
+/// endpoint, over the documents the requester's grants cover. Queries that
+/// can't be translated are answered with `errors` rather than an HTTP error
+/// status.
+async fn graphql(
+    State(state): State<AppState>,
+    Path(dataset): Path<String>,
+    grants: ReadGrants,
+    Json(request): Json<GraphQlRequest>,
+) -> ApiResult<Json<GraphQlResponse>> {
+    let config = state.config();
+    let fields = match translate(&request, &config.graphql_types, config.max_query_limit) {
+        Ok(fields) => fields,
+        Err(message) => {
+            return Ok(Json(GraphQlResponse {
+                data: None,
+                errors: vec![GraphQlError { message }],
+            }))
+        }
+    };
+
+    let dataset_id = state.dataset_id(&dataset).await?;
+    let docs = query_documents(&state, dataset_id, Perspective::Raw, false, &grants).await?;
+    let params = Value::Object(Map::new());
+    let ctx = EvalContext::new(&params);
+    let mut data = Map::new();
+    for (key, expr) in fields {
+        data.insert(key, eval_query_in(&expr, &docs, &ctx)?);
+    }
+    Ok(Json(GraphQlResponse {
+        data: Some(Value::Object(data)),
+        errors: Vec::new(),
+    }))
+}
+
+type Error = String;
+
+/// The root fields of the request's operation as `(response key, query)`
+/// pairs.
+fn translate(
+    request: &GraphQlRequest,
+    types: &[String],
+    max_limit: usize,
+) -> Result<Vec<(String, Expr)>, Error> {
+    let document = parse_query::<String>(&request.query).map_err(|err| err.to_string())?;
+    let operations: Vec<_> = document
+        .definitions
+        .iter()
+        .filter_map(|definition| match definition {
+            Definition::Operation(operation) => Some(operation),
+            Definition::Fragment(_) => None,
+        })
+        .collect();
+    let operation = match &request.operation_name {
+        Some(name) => *operations
+            .iter()
+            .find(|operation| operation_name(operation) == Some(name))
+            .ok_or_else(|| format!("unknown operation {name}"))?,
+        None => match operations.as_slice() {
+            [operation] => *operation,
+            _ => return Err("operationName is required with several operations".into()),
+        },
+    };
+    let (selection_set, definitions) = match operation {
+        OperationDefinition::SelectionSet(set) => (set, &[][..]),
+        OperationDefinition::Query(query) => {
+            (&query.selection_set, query.variable_definitions.as_slice())
+        }
+        OperationDefinition::Mutation(_) | OperationDefinition::Subscription(_) => {
+            return Err("only queries are supported".into())
+        }
+    };
+
+    let mut variables = request.variables.clone().unwrap_or_default();
+    for definition in definitions {
+        if let (false, Some(default)) = (
+            variables.contains_key(&definition.name),
+            &definition.default_value,
+        ) {
+            let default = to_json(default, &variables)?;
+            variables.insert(definition.name.clone(), default);
+        }
+    }
+
+    let translator = Translator {
+        types,
+        max_limit,
+        variables,
+    };
+    fields(selection_set)?
+        .into_iter()
+        .map(|field| Ok((response_key(field), translator.root_field(field)?)))
+        .collect()
+}
+
+fn operation_name<'a>(operation: &'a OperationDefinition<'_, String>) -> Option<&'a String> {
+    match operation {
+        OperationDefinition::SelectionSet(_) => None,
+        OperationDefinition::Query(query) => query.name.as_ref(),
+        OperationDefinition::Mutation(mutation) => mutation.name.as_ref(),
+        OperationDefinition::Subscription(subscription) => subscription.name.as_ref(),
+    }
+}
+
+/// The fields of a selection set; fragments are not supported.
+fn fields<'s, 'a>(set: &'s SelectionSet<'a, String>) -> Result<Vec<&'s Field<'a, String>>, Error> {
+    set.items
+        .iter()
+        .map(|selection| match selection {
+            Selection::Field(field) => Ok(field),
+            Selection::FragmentSpread(_) | Selection::InlineFragment(_) => {
+                Err("fragments are not supported".into())
+            }
+        })
+        .collect()
+}
+
+fn response_key(field: &Field<'_, String>) -> String {
+    field.alias.clone().unwrap_or_else(|| field.name.clone())
+}
+
+/// The GraphQL name of a document type: `post` → `Post`.
+fn type_name(doc_type: &str) -> String {
+    let mut chars = doc_type.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+struct Translator<'a> {
+    types: &'a [String],
+    max_limit: usize,
+    variables: Map<String, Value>,
+}
+
+impl Translator<'_> {
+    /// `allPost(...)` or `Post(id: ...)` as a query over the document set.
+    fn root_field(&self, field: &Field<'_, String>) -> Result<Expr, Error> {
+        let (doc_type, list) = self
+            .types
+            .iter()
+            .find_map(|doc_type| {
+                let name = type_name(doc_type);
+                if field.name == name {
+                    Some((doc_type, false))
+                } else if field.name.strip_prefix("all") == Some(name.as_str()) {
+                    Some((doc_type, true))
+                } else {
+                    None
+                }
+            })
+            .ok_or_else(|| format!("unknown field {} on Query", field.name))?;
+        if field.selection_set.items.is_empty() {
+            return Err(format!(
+                "field {} needs a selection of subfields",
+                field.name
+            ));
+        }
+        let args = field
+            .arguments
+            .iter()
+            .map(|(name, value)| Ok((name.as_str(), to_json(value, &self.variables)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let mut filter = eq(
+            Expr::Ident("_type".into()),
+            Expr::StringLiteral(doc_type.clone()),
+        );
+        let mut stages = vec![Expr::Everything];
+        if list {
+            let (mut order, mut offset, mut limit) = (Vec::new(), 0, self.max_limit);
+            for (name, value) in args {
+                match name {
+                    "where" => {
+                        for condition in conditions(None, &value)? {
+                            filter = Expr::And(Box::new(filter), Box::new(condition));
+                        }
+                    }
+                    "sort" => order = sort_stages(&value)?,
+                    "offset" => offset = count_argument(name, &value)?,
+                    "limit" => limit = count_argument(name, &value)?.min(self.max_limit),
+                    _ => return Err(format!("unknown argument {name} on {}", field.name)),
+                }
+            }
+            stages.push(Expr::Filter(Box::new(filter)));
+            stages.extend(order);
+            let start = i64::try_from(offset).unwrap_or(i64::MAX);
+            let end = i64::try_from(offset.saturating_add(limit)).unwrap_or(i64::MAX);
+            stages.push(Expr::Slice(Box::new(Expr::This), start, end));
+        } else {
+            let mut id = None;
+            for (name, value) in args {
+                match (name, value) {
+                    ("id", Value::String(value)) => id = Some(value),
+                    ("id", _) => return Err("id must be a string".into()),
+                    _ => return Err(format!("unknown argument {name} on {}", field.name)),
+                }
+            }
+            let id = id.ok_or_else(|| format!("field {} requires an id", field.name))?;
+            filter = Expr::And(
+                Box::new(filter),
+                Box::new(eq(Expr::Ident("_id".into()), Expr::StringLiteral(id))),
+            );
+            stages.push(Expr::Filter(Box::new(filter)));
+            stages.push(Expr::Index(Box::new(Expr::This), 0));
+        }
+        stages.push(Expr::Projection(self.projection(&field.selection_set)?));
+        Ok(Expr::Pipeline(stages))
+    }
+
+    /// A selection set as a projection; subselections project the field's
+    /// value, or each of its items for arrays.
+    fn projection(&self, set: &SelectionSet<'_, String>) -> Result<Vec<(String, Expr)>, Error> {
+        fields(set)?
+            .into_iter()
+            .map(|field| {
+                if !field.arguments.is_empty() {
+                    return Err(format!("field {} takes no arguments", field.name));
+                }
+                let value = Expr::Ident(field.name.clone());
+                let value = if field.selection_set.items.is_empty() {
+                    value
+                } else {
+                    let fields = self.projection(&field.selection_set)?;
+                    Expr::Pipeline(vec![value, Expr::Projection(fields)])
+                };
+                Ok((response_key(field), value))
+            })
+            .collect()
+    }
+}
+
+fn eq(left: Expr, right: Expr) -> Expr {
+    Expr::Eq(Box::new(left), Box::new(right))
+}
+
+/// The conditions of a `where` argument. Keys are field names, or, below a
+/// field, comparison operators.
+fn conditions(path: Option<&Expr>, filter: &Value) -> Result<Vec<Expr>, Error> {
+    let Value::Object(filter) = filter else {
+        return Err("where conditions must be objects".into());
+    };
+    let mut out = Vec::new();
+    for (key, value) in filter {
+        let field = || Box::new(path.cloned().unwrap_or(Expr::This));
+        let operand = || Box::new(literal(value));
+        match (path, key.as_str()) {
+            (Some(_), "eq") => out.push(Expr::Eq(field(), operand())),
+            (Some(_), "neq") => out.push(Expr::Neq(field(), operand())),
+            (Some(_), "gt") => out.push(Expr::Gt(field(), operand())),
+            (Some(_), "gte") => out.push(Expr::Gte(field(), operand())),
+            (Some(_), "lt") => out.push(Expr::Lt(field(), operand())),
+            (Some(_), "lte") => out.push(Expr::Lte(field(), operand())),
+            (Some(_), "in") if value.is_array() => out.push(Expr::In(field(), operand())),
+            (Some(_), "in") => return Err("the in operator takes a list".into()),
+            (path, key) => out.extend(conditions(Some(&field_path(path, key)), value)?),
+        }
+    }
+    Ok(out)
+}
+
+/// `field` of the value at `path`, or of the document at the top level.
+fn field_path(path: Option<&Expr>, field: &str) -> Expr {
+    match path {
+        Some(path) => Expr::DotAccess(Box::new(path.clone()), field.into()),
+        None => Expr::Ident(field.into()),
+    }
+}
+
+/// `order` stages for a `sort` argument: `[{title: ASC}, {author: {name: DESC}}]`.
+/// Ordering is stable, so the last key is applied first.
+fn sort_stages(sort: &Value) -> Result<Vec<Expr>, Error> {
+    fn keys(path: Option<Expr>, value: &Value, out: &mut Vec<Expr>) -> Result<(), Error> {
+        match (path, value) {
+            (Some(path), Value::String(direction)) => {
+                let ascending = match direction.as_str() {
+                    "ASC" => true,
+                    "DESC" => false,
+                    _ => return Err(format!("unknown sort order {direction}")),
+                };
+                out.push(Expr::Order(Box::new(path), ascending));
+            }
+            (path, Value::Object(fields)) => {
+                for (field, value) in fields {
+                    keys(Some(field_path(path.as_ref(), field)), value, out)?;
+                }
+            }
+            _ => return Err("sort takes a list of {field: ASC | DESC} objects".into()),
+        }
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    match sort {
+        Value::Array(items) => {
+            for item in items {
+                keys(None, item, &mut order)?;
+            }
+        }
+        item => keys(None, item, &mut order)?,
+    }
+    order.reverse();
+    Ok(order)
+}
+
+fn count_argument(name: &str, value: &Value) -> Result<usize, Error> {
+    value
+        .as_u64()
+        .and_then(|n| usize::try_from(n).ok())
+        .ok_or_else(|| format!("{name} must be a non-negative integer"))
+}
+
+/// A GraphQL input value as JSON, with variables substituted.
+fn to_json(
+    value: &GraphQlValue<'_, String>,
+    variables: &Map<String, Value>,
+) -> Result<Value, Error> {
+    Ok(match value {
+        GraphQlValue::Variable(name) => variables
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("variable ${name} is not provided"))?,
+        GraphQlValue::Int(n) => n.as_i64().map_or(Value::Null, Value::from),
+        GraphQlValue::Float(n) => Value::from(*n),
+        GraphQlValue::String(s) => Value::String(s.clone()),
+        GraphQlValue::Boolean(b) => Value::Bool(*b),
+        GraphQlValue::Null => Value::Null,
+        GraphQlValue::Enum(name) => Value::String(name.clone()),
+        GraphQlValue::List(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| to_json(item, variables))
+                .collect::<Result<_, _>>()?,
+        ),
+        GraphQlValue::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), to_json(value, variables)?)))
+                .collect::<Result<_, Error>>()?,
+        ),
+    })
+}
+
+/// A JSON value as a GROQ literal.
+fn literal(value: &Value) -> Expr {
+    match value {
+        Value::Null => Expr::Null,
+        Value::Bool(b) => Expr::BoolLiteral(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(n) => Expr::IntLiteral(n),
+            None => Expr::FloatLiteral(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => Expr::StringLiteral(s.clone()),
+        Value::Array(items) => Expr::Array(items.iter().map(literal).collect()),
+        Value::Object(fields) => Expr::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), literal(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{header, Request, StatusCode};
+    use content_lake_groq::print::to_groq;
+    use serde_json::json;
+
+    use crate::test_support::{create_dataset, seed, send, test_state, token};
+
+    fn groq(query: &str, variables: Value) -> Result<Vec<(String, String)>, Error> {
+        let request = GraphQlRequest {
+            query: query.into(),
+            variables: variables.as_object().cloned(),
+            operation_name: None,
+        };
+        let fields = translate(&request, &["post".into(), "author".into()], 100)?;
+        Ok(fields
+            .into_iter()
+            .map(|(key, expr)| (key, to_groq(&expr)))
+            .collect())
+    }
+
+    #[test]
+    fn translates_root_fields_to_groq() {
+        let fields = groq(
+            r#"query ($title: String) {
+                posts: allPost(where: {title: {eq: $title}, slug: {current: {neq: "draft"}}},
+                               sort: [{rank: DESC}, {title: ASC}], offset: 2, limit: 5) {
+                    _id
+                    slug { current }
+                }
+                Author(id: "a1") { name }
+            }"#,
+            json!({"title": "Hello"}),
+        )
+        .unwrap();
+        assert_eq!(
+            fields,
+            vec![
+                (
+                    "posts".to_string(),
+                    r#"*[_type == "post" && slug.current != "draft" && title == "Hello"] | order(title) | order(rank desc)[2...7]{_id, "slug": (slug){current}}"#
+                        .to_string()
+                ),
+                (
+                    "Author".to_string(),
+                    r#"*[_type == "author" && _id == "a1"][0]{name}"#.to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn translates_ordering_and_membership_operators() {
+        let fields = groq(
+            r#"{ allPost(where: {rank: {gt: 1, lte: 9.5}, views: {gte: 10, lt: 20}, tag: {in: ["a", "b"]}}) { _id } }"#,
+            json!({}),
+        )
+        .unwrap();
+        assert_eq!(
+            fields[0].1,
+            r#"*[_type == "post" && rank > 1 && rank <= 9.5 && tag in ["a", "b"] && views >= 10 && views < 20][0...100]{_id}"#
+        );
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_translate() {
+        let error = |query: &str| groq(query, json!({})).unwrap_err();
+        assert_eq!(
+            error("{ allPage { _id } }"),
+            "unknown field allPage on Query"
+        );
+        assert_eq!(
+            error("{ allPost }"),
+            "field allPost needs a selection of subfields"
+        );
+        assert_eq!(error("{ Post { _id } }"), "field Post requires an id");
+        assert_eq!(
+            error("mutation { allPost { _id } }"),
+            "only queries are supported"
+        );
+        assert_eq!(error("{ allPost { ...F } }"), "fragments are not supported");
+        assert_eq!(
+            error("{ allPost(where: {rank: {in: 1}}) { _id } }"),
+            "the in operator takes a list"
+        );
+        assert_eq!(
+            error("query ($id: ID) { Post(id: $id) { _id } }"),
+            "variable $id is not provided"
+        );
+        assert_eq!(
+            error("{ allPost(limit: -1) { _id } }"),
+            "limit must be a non-negative integer"
+        );
+    }
+
+    async fn post(state: &AppState, dataset: &str, body: Value) -> GraphQlResponse {
+        let (status, _, body) = send(
+            state,
+            Request::post(format!("/v1/graphql/{dataset}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(body.to_string().into())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", String::from_utf8_lossy(&body));
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn answers_queries_over_seeded_documents() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        seed(
+            &state,
+            &dataset,
+            json!([
+                {"create": {"_id": "p1", "_type": "post", "title": "First", "rank": 2}},
+                {"create": {"_id": "p2", "_type": "post", "title": "Second", "rank": 1}},
+                {"create": {"_id": "a1", "_type": "author", "name": "Ada"}},
+            ]),
+        )
+        .await;
+
+        let response = post(
+            &state,
+            &dataset,
+            json!({"query": "{ allPost { _id title } }"}),
+        )
+        .await;
+        let mut posts = response.data.unwrap()["allPost"]
+            .as_array()
+            .unwrap()
+            .clone();
+        posts.sort_by_key(|post| post["_id"].as_str().unwrap().to_string());
+        assert_eq!(
+            posts,
+            vec![
+                json!({"_id": "p1", "title": "First"}),
+                json!({"_id": "p2", "title": "Second"}),
+            ]
+        );
+
+        let response = post(
+            &state,
+            &dataset,
+            json!({
+                "query": "query ($id: ID!) { Post(id: $id) { title } allPost(sort: [{rank: ASC}]) { _id } }",
+                "variables": {"id": "p1"},
+            }),
+        )
+        .await;
+        assert_eq!(
+            response.data.unwrap(),
+            json!({"Post": {"title": "First"}, "allPost": [{"_id": "p2"}, {"_id": "p1"}]})
+        );
+
+        let response = post(
+            &state,
+            &dataset,
+            json!({"query": r#"{ allPost(where: {rank: {gte: 2}, title: {in: ["First", "Third"]}}) { _id } }"#}),
+        )
+        .await;
+        assert_eq!(response.data.unwrap(), json!({"allPost": [{"_id": "p1"}]}));
+
+        let response = post(&state, &dataset, json!({"query": "{ allAuthor { name } }"})).await;
+        assert!(response.data.is_none());
+        assert_eq!(
+            response.errors[0].message,
+            "unknown field allAuthor on Query"
+        );
+    }
+
+    #[tokio::test]
+    async fn grants_hide_documents_from_queries() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        seed(
+            &state,
+            &dataset,
+            json!([
+                {"create": {"_id": "p1", "_type": "post", "public": true}},
+                {"create": {"_id": "p2", "_type": "post"}},
+            ]),
+        )
+        .await;
+
+        let (status, _, body) = send(
+            &state,
+            Request::post(format!("/v1/graphql/{dataset}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(
+                    header::AUTHORIZATION,
+                    token(&state, Some(&["public == true"])),
+                )
+                .body(json!({"query": "{ allPost { _id } }"}).to_string().into())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let response: GraphQlResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.data.unwrap(), json!({"allPost": [{"_id": "p1"}]}));
+    }
+}
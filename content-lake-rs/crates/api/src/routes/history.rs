@@ -0,0 +1,142 @@
+use axum::{
+    extract::{Path, State},
+    routing::get,
+    Json, Router,
+};
+use content_lake_core::history::log::{document_history, HistoryEntry};
+use content_lake_core::store::DocumentStore;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::ReadGrants;
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+
+/// History routes.
+pub fn routes() -> Router<AppState> {
+    Router::new().route(
+        "/v1/history/{dataset}/documents/{id}",
+        get(get_document_history),
+    )
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentHistory {
+    document_id: String,
+    revisions: Vec<HistoryEntry>,
+}
+
+/// Ordered list of revisions for a document, oldest first. A requester with
+/// grants sees the history only of a document, live or soft-deleted, that
+/// the grants cover.
+async fn get_document_history(
+    State(state): State<AppState>,
+    Path((dataset, id)): Path<(String, String)>,
+    grants: ReadGrants,
+) -> ApiResult<Json<DocumentHistory>> {
+    let dataset_id = state.dataset_id(&dataset).await?;
+    let covered = match grants.0 {
+        Some(_) => state
+            .store()
+            .get_including_deleted(dataset_id, &id)
+            .await?
+            .is_some_and(|row| grants.allows(&row.to_document())),
+        None => true,
+    };
+    let revisions = if covered {
+        document_history(state.pool(), dataset_id, &id).await?
+    } else {
+        Vec::new()
+    };
+    if revisions.is_empty() {
+        return Err(ApiError::NotFound(format!("no history for document: {id}")));
+    }
+    Ok(Json(DocumentHistory {
+        document_id: id,
+        revisions,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{Request, StatusCode};
+    use serde_json::json;
+
+    use crate::test_support::{create_dataset, seed, send, test_state, token};
+
+    #[tokio::test]
+    async fn returns_revisions_in_order() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        seed(
+            &state,
+            &dataset,
+            json!([{"create": {"_id": "a", "_type": "post", "title": "v1"}}]),
+        )
+        .await;
+        seed(
+            &state,
+            &dataset,
+            json!([{"patch": {"id": "a", "set": {"title": "v2"}}}]),
+        )
+        .await;
+
+        let (status, _, body) = send(
+            &state,
+            Request::get(format!("/v1/history/{dataset}/documents/a"))
+                .body(Default::default())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let history: DocumentHistory = serde_json::from_slice(&body).unwrap();
+        assert_eq!(history.document_id, "a");
+        assert_eq!(history.revisions.len(), 2);
+        assert_eq!(history.revisions[0].previous_rev, None);
+        assert_eq!(
+            history.revisions[1].previous_rev,
+            history.revisions[0].result_rev
+        );
+
+        let (status, _, _) = send(
+            &state,
+            Request::get(format!("/v1/history/{dataset}/documents/missing"))
+                .body(Default::default())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn grants_hide_the_history_of_uncovered_documents() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        seed(
+            &state,
+            &dataset,
+            json!([
+                {"create": {"_id": "post", "_type": "post"}},
+                {"create": {"_id": "secret", "_type": "secret"}},
+            ]),
+        )
+        .await;
+        let posts_only = token(&state, Some(&[r#"_type == "post""#]));
+        let get = |id: &str| {
+            Request::get(format!("/v1/history/{dataset}/documents/{id}"))
+                .header("authorization", &posts_only)
+                .body(Default::default())
+                .unwrap()
+        };
+
+        let (status, _, _) = send(&state, get("post")).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, _) = send(&state, get("secret")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
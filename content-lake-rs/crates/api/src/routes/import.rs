@@ -0,0 +1,302 @@
+use axum::{
+    extract::{DefaultBodyLimit, Path, Query, State},
+    routing::post,
+    Json, Router,
+};
+use content_lake_core::document::validate::{validate_document_fields, ValidationError};
+use content_lake_core::events::types::Transition;
+use content_lake_core::mutation::executor::{apply_transaction, TransactionOptions};
+use content_lake_core::mutation::types::{CreateOrReplaceMutation, Mutation};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{ApiError, ApiResult, ErrorDetail};
+use crate::state::AppState;
+
+/// Documents written per database transaction.
+const IMPORT_BATCH_SIZE: usize = 100;
+
+/// Maximum accepted NDJSON body size.
+const IMPORT_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Import routes.
+pub fn routes() -> Router<AppState> {
+    Router::new().route(
+        "/v1/data/import/{dataset}",
+        post(import).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+    )
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportParams {
+    /// Reject the whole import if any line is invalid.
+    #[serde(default)]
+    fail_fast: bool,
+}
+
+/// Result of an import: documents created and replaced, per-line failures,
+/// and the batch that stopped the import, if any.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub created: usize,
+    pub replaced: usize,
+    pub errors: Vec<ImportLineError>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed: Option<ImportBatchError>,
+}
+
+/// A batch that failed to write, with the error its request would have got.
+/// The batches before it stay written; it and the ones after it are not.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportBatchError {
+    pub first_line: usize,
+    pub last_line: usize,
+    pub error: ErrorDetail,
+}
+
+/// A line that could not be imported, numbered from 1.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportLineError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Bulk-load documents from an NDJSON body, replacing documents with the same `_id`.
+///
+/// A batch that fails to write stops the import. The response is still a
+/// `200` summary of what was written, with the failure in `failed`.
+async fn import(
+    State(state): State<AppState>,
+    Path(dataset): Path<String>,
+    Query(params): Query<ImportParams>,
+    body: String,
+) -> ApiResult<Json<ImportSummary>> {
+    let dataset_id = state.dataset_id(&dataset).await?;
+    let (documents, errors) = parse_ndjson(&body);
+
+    if params.fail_fast {
+        if let Some(first) = errors.first() {
+            return Err(ApiError::BadRequest(format!(
+                "line {}: {}",
+                first.line, first.message
+            )));
+        }
+    }
+
+    let mut summary = ImportSummary {
+        created: 0,
+        replaced: 0,
+        errors,
+        failed: None,
+    };
+    for batch in documents.chunks(IMPORT_BATCH_SIZE) {
+        let mutations: Vec<Mutation> = batch
+            .iter()
+            .map(|(_, document)| {
+                Mutation::CreateOrReplace(CreateOrReplaceMutation {
+                    document: document.clone(),
+                })
+            })
+            .collect();
+        let options = TransactionOptions {
+            max_document_bytes: Some(state.config().max_document_bytes),
+            ..Default::default()
+        };
+        let committed =
+            match apply_transaction(state.store(), dataset_id, &mutations, options).await {
+                Ok(committed) => committed,
+                Err(err) => {
+                    let err = state.dataset_write_error(&dataset, err);
+                    let (status, _) = err.status_and_type();
+                    if status.is_server_error() {
+                        tracing::error!("import into dataset {dataset} failed: {err}");
+                    }
+                    summary.failed = Some(ImportBatchError {
+                        first_line: batch[0].0,
+                        last_line: batch[batch.len() - 1].0,
+                        error: err.body().error,
+                    });
+                    return Ok(Json(summary));
+                }
+            };
+        for event in &committed.events {
+            match event.transition {
+                Transition::Appear => summary.created += 1,
+                Transition::Update => summary.replaced += 1,
+                // createOrReplace never deletes.
+                Transition::Disappear => {}
+            }
+        }
+        committed.publish(state.event_bus());
+    }
+
+    Ok(Json(summary))
+}
+
+/// Parse and validate each non-blank line, collecting documents and
+/// failures by line number.
+fn parse_ndjson(body: &str) -> (Vec<(usize, Value)>, Vec<ImportLineError>) {
+    let mut documents = Vec::new();
+    let mut errors = Vec::new();
+
+    for (i, line) in body.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match parse_document(line) {
+            Ok(doc) => documents.push((i + 1, doc)),
+            Err(message) => errors.push(ImportLineError {
+                line: i + 1,
+                message,
+            }),
+        }
+    }
+
+    (documents, errors)
+}
+
+fn parse_document(line: &str) -> Result<Value, String> {
+    let doc: Value = serde_json::from_str(line).map_err(|e| format!("invalid JSON: {e}"))?;
+    if !doc.is_object() {
+        return Err(ValidationError::NotAnObject.to_string());
+    }
+    validate_document_fields(
+        doc.get("_id").and_then(Value::as_str),
+        doc.get("_type").and_then(Value::as_str),
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{Request, StatusCode};
+
+    use crate::test_support::{create_dataset, send, test_state};
+
+    const MIXED: &str = concat!(
+        r#"{"_id": "a", "_type": "post", "title": "A"}"#,
+        "\n",
+        "not json\n",
+        "\n",
+        r#"{"_id": "b", "title": "missing type"}"#,
+        "\n",
+        r#"{"_id": "c", "_type": "post"}"#,
+        "\n",
+    );
+
+    #[test]
+    fn parse_collects_line_errors() {
+        let (documents, errors) = parse_ndjson(MIXED);
+        assert_eq!(
+            documents.iter().map(|(line, _)| *line).collect::<Vec<_>>(),
+            vec![1, 5]
+        );
+        assert_eq!(
+            errors.iter().map(|e| e.line).collect::<Vec<_>>(),
+            vec![2, 4]
+        );
+        assert_eq!(errors[1].message, "document _type is required");
+    }
+
+    #[tokio::test]
+    async fn import_reports_created_and_errors() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+
+        let (status, _, body) = send(
+            &state,
+            Request::post(format!("/v1/data/import/{dataset}"))
+                .body(MIXED.into())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let summary: ImportSummary = serde_json::from_slice(&body).unwrap();
+        assert_eq!((summary.created, summary.replaced), (2, 0));
+        assert_eq!(summary.errors.len(), 2);
+        assert!(summary.failed.is_none());
+
+        let count: i64 = sqlx::query_scalar(
+            "SELECT count(*) FROM documents d JOIN datasets s ON s.id = d.dataset_id \
+             WHERE s.name = $1 AND NOT d.deleted",
+        )
+        .bind(&dataset)
+        .fetch_one(state.pool())
+        .await
+        .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[tokio::test]
+    async fn fail_fast_rejects_without_writing() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+
+        let (status, _, _) = send(
+            &state,
+            Request::post(format!("/v1/data/import/{dataset}?failFast=true"))
+                .body(MIXED.into())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let count: i64 = sqlx::query_scalar(
+            "SELECT count(*) FROM documents d JOIN datasets s ON s.id = d.dataset_id \
+             WHERE s.name = $1",
+        )
+        .bind(&dataset)
+        .fetch_one(state.pool())
+        .await
+        .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[tokio::test]
+    async fn failed_batch_returns_the_partial_summary() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        let import = |body: String| {
+            send(
+                &state,
+                Request::post(format!("/v1/data/import/{dataset}"))
+                    .body(body.into())
+                    .unwrap(),
+            )
+        };
+        let (status, _, _) = import(r#"{"_id": "d0", "_type": "post"}"#.to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // A full first batch, replacing d0, then a batch over the size limit.
+        let mut lines: Vec<String> = (0..IMPORT_BATCH_SIZE)
+            .map(|i| format!(r#"{{"_id": "d{i}", "_type": "post"}}"#))
+            .collect();
+        let body = "x".repeat(state.config().max_document_bytes);
+        lines.push(format!(
+            r#"{{"_id": "big", "_type": "post", "body": "{body}"}}"#
+        ));
+        let (status, _, body) = import(lines.join("\n")).await;
+        assert_eq!(status, StatusCode::OK);
+        let summary: ImportSummary = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            (summary.created, summary.replaced),
+            (IMPORT_BATCH_SIZE - 1, 1)
+        );
+        let failed = summary.failed.expect("the failing batch");
+        let last = IMPORT_BATCH_SIZE + 1;
+        assert_eq!((failed.first_line, failed.last_line), (last, last));
+        assert_eq!(failed.error.status_code, 400);
+        assert!(failed.error.description.contains("big"), "{failed:?}");
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt::Write;
+
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+
+use crate::state::AppState;
+
+/// Metrics routes.
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/metrics", get(metrics))
+}
+
+/// Connection-pool and event-bus metrics in Prometheus text format.
+async fn metrics(State(state): State<AppState>) -> Response {
+    let pool = state.pool();
+    let metrics = [
+        (
+            "content_lake_db_pool_size",
+            "gauge",
+            "Connections currently open in the database pool.",
+            pool.size() as u64,
+        ),
+        (
+            "content_lake_db_pool_idle",
+            "gauge",
+            "Idle connections in the database pool.",
+            pool.num_idle() as u64,
+        ),
+        (
+            "content_lake_db_pool_max",
+            "gauge",
+            "Maximum connections allowed in the database pool.",
+            pool.options().get_max_connections() as u64,
+        ),
+        (
+            "content_lake_event_bus_subscribers",
+            "gauge",
+            "Active event bus subscribers.",
+            state.event_bus().subscriber_count() as u64,
+        ),
+        (
+            "content_lake_event_bus_lagged_total",
+            "counter",
+            "Times a listener fell behind the event bus and missed events.",
+            state.event_bus().lagged_count(),
+        ),
+        (
+            "content_lake_query_cache_hits_total",
+            "counter",
+            "Queries answered from the parsed-query cache.",
+            state.query_cache().hits(),
+        ),
+        (
+            "content_lake_query_cache_misses_total",
+            "counter",
+            "Queries that had to be parsed.",
+            state.query_cache().misses(),
+        ),
+        (
+            "content_lake_sql_cache_hits_total",
+            "counter",
+            "Count queries whose SQL translation was reused.",
+            state.sql_cache().hits(),
+        ),
+        (
+            "content_lake_sql_cache_misses_total",
+            "counter",
+            "Count queries that had to be translated to SQL.",
+            state.sql_cache().misses(),
+        ),
+        (
+            "content_lake_health_cache_hits_total",
+            "counter",
+            "Count health checks answered from a recent database check.",
+            state.health_cache().hits(),
+        ),
+        (
+            "content_lake_health_cache_misses_total",
+            "counter",
+            "Count health checks that probed the database.",
+            state.health_cache().misses(),
+        ),
+    ];
+
+    let mut body = String::new();
+    for (name, kind, help, value) in metrics {
+        let _ = writeln!(body, "# HELP {name} {help}");
+        let _ = writeln!(body, "# TYPE {name} {kind}");
+        let _ = writeln!(body, "{name} {value}");
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Request, StatusCode};
+
+    use crate::test_support::{send, test_state};
+
+    #[tokio::test]
+    async fn reports_pool_and_subscriber_gauges() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        for _ in 0..2 {
+            sqlx::query("SELECT 1").execute(state.pool()).await.unwrap();
+        }
+        let _subscriber = state.event_bus().subscribe();
+
+        let (status, _, body) = send(
+            &state,
+            Request::get("/metrics").body(Default::default()).unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("# TYPE content_lake_db_pool_size gauge"));
+        assert!(body.contains("content_lake_db_pool_idle "));
+        assert!(body.contains("content_lake_db_pool_max 5"));
+        assert!(body.contains("content_lake_event_bus_subscribers 1"));
+        assert!(body.contains("# TYPE content_lake_event_bus_lagged_total counter"));
+        assert!(body.contains("content_lake_event_bus_lagged_total 0"));
+        assert!(body.contains("content_lake_query_cache_hits_total 0"));
+        let size: u64 = body
+            .lines()
+            .find_map(|line| line.strip_prefix("content_lake_db_pool_size "))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(size >= 1);
+    }
+}
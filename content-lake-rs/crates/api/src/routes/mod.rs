@@ -1,4 +1,18 @@
+pub mod assets;
+pub mod datasets;
+pub mod doc;
+pub mod export;
+pub mod graphql;
 pub mod health;
+pub mod history;
+pub mod import;
+pub mod listen;
+pub mod metrics;
+pub mod mutate;
+pub mod presence;
+pub mod query;
+pub mod references;
+pub mod validate;
 
 use axum::Router;
 
@@ -8,12 +22,20 @@ use crate::state::AppState;
 pub fn build_router(state: AppState) -> Router {
     Router::new()
         .merge(health::routes())
-        // Future: .merge(query::routes())
-        // Future: .merge(mutate::routes())
-        // Future: .merge(doc::routes())
-        // Future: .merge(listen::routes())
+        .merge(datasets::routes())
+        .merge(mutate::routes())
+        .merge(export::routes())
+        .merge(import::routes())
+        .merge(history::routes())
+        .merge(query::routes())
+        .merge(validate::routes())
+        .merge(metrics::routes())
+        .merge(doc::routes())
+        .merge(listen::routes())
+        .merge(graphql::routes())
+        .merge(references::routes())
+        .merge(assets::routes())
+        .merge(presence::routes())
         // Future: .merge(auth::routes())
-        // Future: .merge(assets::routes())
-        // Future: .merge(presence::routes())
         .with_state(state)
 }
@@ -0,0 +1,445 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use axum::{
+    extract::{Path, Query, State},
+    routing::post,
+    Json, Router,
+};
+use content_lake_core::mutation::executor::{
+    apply_transaction, dry_run_transaction, TransactionOptions,
+};
+use content_lake_core::mutation::types::{Mutation, MutationResponse};
+use content_lake_core::mutation::validate;
+use serde::Deserialize;
+
+use crate::error::{ApiError, ApiResult, FieldError};
+use crate::state::AppState;
+
+/// Mutation routes.
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/v1/data/mutate/{dataset}", post(mutate))
+}
+
+/// Request body for `POST /v1/data/mutate/{dataset}`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MutateRequest {
+    mutations: Vec<Mutation>,
+    transaction_id: Option<String>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// When a mutation's effects must be visible relative to its response.
+///
+/// Accepted for compatibility with Sanity's clients; every mode behaves as
+/// `sync`. The transaction has committed and its events have been published
+/// before the response, so its writes are readable by the next query and
+/// listeners have been sent them. Publishing is cheap, and doing it inline
+/// keeps each document's events in commit order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Visibility {
+    Sync,
+    Async,
+    /// Sanity uses it for bulk writes that need not be visible promptly.
+    Deferred,
+}
+
+impl FromStr for Visibility {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sync" => Ok(Visibility::Sync),
+            "async" => Ok(Visibility::Async),
+            "deferred" => Ok(Visibility::Deferred),
+            other => Err(format!("unknown visibility: {other}")),
+        }
+    }
+}
+
+/// Apply a transaction of mutations to the dataset.
+///
+/// Query string: `visibility=sync|async|deferred` (see [`Visibility`]),
+/// `purge=true` and `dryRun=true`. With `dryRun` (query parameter or body
+/// field) the transaction runs in full and is then rolled back: the response
+/// and any errors are what the real transaction would produce, but nothing is
+/// stored or published.
+///
+/// Deleting a document that other documents still reference fails with 409
+/// unless `purge=true` is passed.
+///
+/// Every mutation is validated before the transaction starts; all failures
+/// are reported together as a 400.
+async fn mutate(
+    State(state): State<AppState>,
+    Path(dataset): Path<String>,
+    Query(raw): Query<HashMap<String, String>>,
+    Json(body): Json<MutateRequest>,
+) -> ApiResult<Json<MutationResponse>> {
+    raw.get("visibility")
+        .map(|v| v.parse::<Visibility>().map_err(ApiError::BadRequest))
+        .transpose()?;
+    let dry_run = raw.get("dryRun").is_some_and(|v| v == "true") || body.dry_run;
+    let errors: Vec<FieldError> = body
+        .mutations
+        .iter()
+        .filter_map(|m| validate(m).err())
+        .map(FieldError::from)
+        .collect();
+    if !errors.is_empty() {
+        return Err(ApiError::ValidationFailed(errors));
+    }
+
+    let dataset_id = state.dataset_id(&dataset).await?;
+    let options = TransactionOptions {
+        transaction_id: body.transaction_id,
+        max_document_bytes: Some(state.config().max_document_bytes),
+        purge: raw.get("purge").is_some_and(|v| v == "true"),
+    };
+    if dry_run {
+        let response = dry_run_transaction(state.store(), dataset_id, &body.mutations, options)
+            .await
+            .map_err(|err| state.dataset_write_error(&dataset, err))?;
+        return Ok(Json(response));
+    }
+    let committed = apply_transaction(state.store(), dataset_id, &body.mutations, options)
+        .await
+        .map_err(|err| state.dataset_write_error(&dataset, err))?;
+    // Only reached once the transaction has committed.
+    Ok(Json(committed.publish(state.event_bus())))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header, Request, StatusCode};
+    use content_lake_core::events::types::ContentLakeEvent;
+    use serde_json::{json, Value};
+
+    use content_lake_core::store::DocumentStore;
+
+    use crate::test_support::{create_dataset, send, test_state};
+
+    fn post(dataset: &str, body: Value) -> Request<axum::body::Body> {
+        Request::post(format!("/v1/data/mutate/{dataset}"))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body.to_string().into())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn failed_transaction_emits_no_events() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        let mut rx = state.event_bus().subscribe();
+
+        // The create succeeds inside the transaction before the patch fails.
+        let (status, _, _) = send(
+            &state,
+            post(
+                &dataset,
+                json!({"mutations": [
+                    {"create": {"_id": "a", "_type": "post"}},
+                    {"patch": {"id": "missing", "set": {"title": "x"}}}
+                ]}),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(rx.try_recv().is_err());
+
+        let (status, _, _) = send(
+            &state,
+            post(
+                &dataset,
+                json!({"mutations": [{"create": {"_id": "a", "_type": "post"}}]}),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        match rx.try_recv().map(|e| e.event) {
+            Ok(ContentLakeEvent::Mutation(event)) => assert_eq!(event.document_id, "a"),
+            other => panic!("expected mutation event, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn every_visibility_publishes_before_responding() {
+        use content_lake_core::store::DocumentStore;
+
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        let dataset_id = state.dataset_id(&dataset).await.unwrap();
+        let mut rx = state.event_bus().subscribe();
+        let create = |id: &str| json!({"mutations": [{"create": {"_id": id, "_type": "post"}}]});
+
+        // `sync` has published by the time it responds, and the write reads back.
+        let (status, _, body) = send(
+            &state,
+            post(&format!("{dataset}?visibility=sync"), create("a")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["results"][0]["id"], "a");
+        assert!(matches!(
+            rx.try_recv().map(|e| e.event),
+            Ok(ContentLakeEvent::Mutation(event)) if event.document_id == "a"
+        ));
+        let row = state.store().get(dataset_id, "a").await.unwrap().unwrap();
+        assert_eq!(body["transactionId"], row.revision);
+
+        // `async` answers with the same body, also once the event is published.
+        let (status, _, body) = send(
+            &state,
+            post(&format!("{dataset}?visibility=async"), create("b")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["results"][0]["id"], "b");
+        assert!(state.store().get(dataset_id, "b").await.unwrap().is_some());
+        assert!(matches!(
+            rx.try_recv().map(|e| e.event),
+            Ok(ContentLakeEvent::Mutation(event)) if event.document_id == "b"
+        ));
+
+        let (status, _, body) = send(
+            &state,
+            post(&format!("{dataset}?visibility=later"), create("c")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["message"], "unknown visibility: later");
+        assert!(state.store().get(dataset_id, "c").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn dry_run_leaves_dataset_unchanged() {
+        use content_lake_core::store::DocumentStore;
+
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        let dataset_id = state.dataset_id(&dataset).await.unwrap();
+        let mut rx = state.event_bus().subscribe();
+
+        let create = json!({"mutations": [{"create": {"_id": "a", "_type": "post"}}]});
+        let mut in_body = create.clone();
+        in_body["dryRun"] = json!(true);
+        for request in [
+            post(&format!("{dataset}?dryRun=true"), create),
+            post(&dataset, in_body),
+        ] {
+            let (status, _, body) = send(&state, request).await;
+            assert_eq!(status, StatusCode::OK);
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["results"][0]["id"], "a");
+            assert_eq!(body["results"][0]["operation"], "create");
+        }
+        assert!(state.store().get(dataset_id, "a").await.unwrap().is_none());
+        assert!(rx.try_recv().is_err());
+
+        // Validation errors are reported as for a real transaction.
+        let (status, _, body) = send(
+            &state,
+            post(
+                &format!("{dataset}?dryRun=true"),
+                json!({"mutations": [{"create": {"_id": "b"}}]}),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["items"][0]["error"]["path"], "_type");
+    }
+
+    #[tokio::test]
+    async fn malformed_mutations_are_rejected_before_the_transaction() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+
+        // The dataset does not exist: validation runs before it is looked up.
+        let (status, _, body) = send(
+            &state,
+            post(
+                "no-such-dataset",
+                json!({"mutations": [
+                    {"create": {"_id": "a"}},
+                    {"patch": {"id": "a", "set": {"items[0": 1}}},
+                    {"createOrReplace": {"_id": "b", "_type": "post", "_weird": 1}},
+                    {"delete": {"id": "a"}}
+                ]}),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let items = body["error"]["items"].as_array().unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0]["error"]["path"], "_type");
+        assert_eq!(items[1]["error"]["path"], "items[0");
+        assert_eq!(
+            items[1]["error"]["description"],
+            "invalid patch path: unbalanced brackets in items[0"
+        );
+        assert_eq!(items[2]["error"]["path"], "_weird");
+        assert_eq!(
+            items[2]["error"]["description"],
+            "reserved field _weird is not allowed in document content"
+        );
+    }
+
+    #[tokio::test]
+    async fn documents_over_the_size_limit_are_rejected() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        let limit = state.config().max_document_bytes;
+        // `{"body":"…"}` is the body plus 11 bytes.
+        let body = |len: usize| "x".repeat(len - 11);
+
+        let create = json!({"mutations": [
+            {"create": {"_id": "a", "_type": "post", "body": body(limit)}}
+        ]});
+        let (status, _, _) = send(&state, post(&dataset, create)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let grow = json!({"mutations": [
+            {"patch": {"id": "a", "set": {"body": body(limit + 1)}}}
+        ]});
+        let (status, _, response) = send(&state, post(&dataset, grow)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let response: Value = serde_json::from_slice(&response).unwrap();
+        assert_eq!(
+            response["error"]["message"],
+            format!(
+                "document a is {} bytes, over the limit of {limit}",
+                limit + 1
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn deleting_referenced_documents_requires_purge() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        let (status, _, _) = send(
+            &state,
+            post(
+                &dataset,
+                json!({"mutations": [
+                    {"create": {"_id": "author", "_type": "person"}},
+                    {"create": {"_id": "other", "_type": "person"}},
+                    {"create": {"_id": "p1", "_type": "post", "author": {"_ref": "author"},
+                        "related": {"_ref": "other", "_weak": true}}}
+                ]}),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let delete = |id: &str| json!({"mutations": [{"delete": {"id": id}}]});
+
+        let (status, _, body) = send(&state, post(&dataset, delete("author"))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["error"]["message"],
+            "document author is referenced by p1"
+        );
+
+        let (status, _, _) = send(
+            &state,
+            post(&format!("{dataset}?purge=true"), delete("author")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _, _) = send(&state, post(&dataset, delete("other"))).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn create_conflicts_where_create_or_replace_overwrites() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        let mutate = |mutation: Value| post(&dataset, json!({"mutations": [mutation]}));
+        let (status, _, _) = send(
+            &state,
+            mutate(json!({"create": {"_id": "a", "_type": "post", "title": "v1"}})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _, body) = send(
+            &state,
+            mutate(json!({"create": {"_id": "a", "_type": "post", "title": "v2"}})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["type"], "conflict");
+
+        let (status, _, body) = send(
+            &state,
+            mutate(json!({"createOrReplace": {"_id": "a", "_type": "article"}})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["results"][0]["operation"], "update");
+
+        let doc = state
+            .store()
+            .get(state.dataset_id(&dataset).await.unwrap(), "a")
+            .await
+            .unwrap()
+            .unwrap()
+            .to_document();
+        assert_eq!(doc["_type"], "article");
+        assert!(doc.get("title").is_none(), "replaced, not merged");
+    }
+
+    #[tokio::test]
+    async fn reused_transaction_ids_conflict() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        let transaction_id = format!("tx-{}", uuid::Uuid::new_v4());
+        let mutate = |id: &str| {
+            post(
+                &dataset,
+                json!({
+                    "mutations": [{"create": {"_id": id, "_type": "post"}}],
+                    "transactionId": transaction_id,
+                }),
+            )
+        };
+        let (status, _, _) = send(&state, mutate("a")).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _, body) = send(&state, mutate("b")).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["error"]["description"],
+            format!("transaction id already used: {transaction_id}")
+        );
+        let dataset_id = state.dataset_id(&dataset).await.unwrap();
+        assert!(state.store().get(dataset_id, "b").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,157 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    response::Response,
+    routing::get,
+    Router,
+};
+use content_lake_core::events::presence::PresenceSession;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::ApiResult;
+use crate::state::AppState;
+
+/// Presence routes.
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/v1/presence/{dataset}", get(presence))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PresenceParams {
+    document_id: String,
+}
+
+/// Join a document's presence channel over a WebSocket.
+///
+/// The server opens with `{"type":"welcome","sessionId":…}` and a `snapshot`
+/// of the participants so far. Each text frame the client sends is its new
+/// presence state (e.g. `{"cursor":…,"selection":…}`) and is relayed to the
+/// other participants as an `update`; they get a `leave` when it disconnects.
+async fn presence(
+    State(state): State<AppState>,
+    Path(dataset): Path<String>,
+    Query(params): Query<PresenceParams>,
+    ws: WebSocketUpgrade,
+) -> ApiResult<Response> {
+    let dataset_id = state.dataset_id(&dataset).await?;
+    let session = state.presence().join(dataset_id, &params.document_id);
+    Ok(ws.on_upgrade(move |socket| run(socket, session)))
+}
+
+/// Relay between the socket and the session until either side closes.
+/// Dropping the session on return evicts its state.
+async fn run(mut socket: WebSocket, mut session: PresenceSession) {
+    let welcome = json!({ "type": "welcome", "sessionId": session.session_id() });
+    if send(&mut socket, &welcome).await.is_err()
+        || send(&mut socket, &session.snapshot()).await.is_err()
+    {
+        return;
+    }
+    loop {
+        tokio::select! {
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<Value>(&text) {
+                    Ok(state) => session.update(state),
+                    Err(e) => {
+                        let error = json!({ "type": "error", "message": e.to_string() });
+                        if send(&mut socket, &error).await.is_err() {
+                            return;
+                        }
+                    }
+                },
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+            event = session.recv() => {
+                let Some(event) = event else { return };
+                if send(&mut socket, &event).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn send(socket: &mut WebSocket, frame: &impl serde::Serialize) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(frame).expect("presence frames serialize to JSON");
+    socket.send(Message::Text(text.into())).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::TcpStream;
+    use tokio_tungstenite::{connect_async, tungstenite, MaybeTlsStream, WebSocketStream};
+
+    use super::*;
+    use crate::routes::build_router;
+    use crate::test_support::{create_dataset, test_state};
+
+    type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    async fn next_json(client: &mut Client) -> Value {
+        let frame = tokio::time::timeout(Duration::from_secs(5), client.next())
+            .await
+            .expect("timed out waiting for a frame")
+            .expect("socket closed")
+            .unwrap();
+        serde_json::from_str(frame.to_text().unwrap()).unwrap()
+    }
+
+    /// Connect and consume the welcome and snapshot frames, returning the
+    /// session id and the snapshot's participants.
+    async fn connect(url: &str) -> (Client, String, Value) {
+        let (mut client, _) = connect_async(url).await.unwrap();
+        let welcome = next_json(&mut client).await;
+        assert_eq!(welcome["type"], "welcome");
+        let snapshot = next_json(&mut client).await;
+        assert_eq!(snapshot["type"], "snapshot");
+        let session_id = welcome["sessionId"].as_str().unwrap().to_string();
+        (client, session_id, snapshot["participants"].clone())
+    }
+
+    #[tokio::test]
+    async fn relays_presence_between_clients_on_a_document() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = build_router(state.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let url = format!("ws://{addr}/v1/presence/{dataset}?documentId=post1");
+
+        let (mut alice, alice_id, _) = connect(&url).await;
+        let (mut bob, _, participants) = connect(&url).await;
+        assert_eq!(participants, json!([]));
+
+        let cursor = json!({ "cursor": { "path": "title", "offset": 4 } });
+        alice
+            .send(tungstenite::Message::text(cursor.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_json(&mut bob).await,
+            json!({ "type": "update", "sessionId": alice_id, "state": cursor })
+        );
+
+        let (_carol, _, participants) = connect(&url).await;
+        assert_eq!(
+            participants,
+            json!([{ "sessionId": alice_id, "state": cursor }])
+        );
+
+        alice.close(None).await.unwrap();
+        assert_eq!(
+            next_json(&mut bob).await,
+            json!({ "type": "leave", "sessionId": alice_id })
+        );
+    }
+}
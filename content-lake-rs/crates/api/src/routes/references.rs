@@ -0,0 +1,117 @@
+use axum::{
+    extract::{Path, State},
+    routing::get,
+    Json, Router,
+};
+use content_lake_core::store::DocumentStore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::auth::ReadGrants;
+use crate::error::ApiResult;
+use crate::state::AppState;
+
+/// Reference lookup routes.
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/v1/data/references/{dataset}/{id}", get(get_referencing))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ReferencesResponse {
+    documents: Vec<Value>,
+}
+
+/// Live documents that hold a strong `_ref` to `id`, ordered by id. Answered from
+/// the reference index rather than by scanning document content. Documents
+/// the requester's grants don't cover are left out.
+async fn get_referencing(
+    State(state): State<AppState>,
+    Path((dataset, id)): Path<(String, String)>,
+    grants: ReadGrants,
+) -> ApiResult<Json<ReferencesResponse>> {
+    let dataset_id = state.dataset_id(&dataset).await?;
+    let rows = state.store().referencing(dataset_id, &id).await?;
+    Ok(Json(ReferencesResponse {
+        documents: rows
+            .iter()
+            .map(|row| row.to_document())
+            .filter(|doc| grants.allows(doc))
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Request, StatusCode};
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_support::{create_dataset, seed, send, test_state, token};
+
+    #[tokio::test]
+    async fn finds_documents_referencing_an_id() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        seed(
+            &state,
+            &dataset,
+            json!([
+                {"create": {"_id": "author", "_type": "person"}},
+                {"create": {"_id": "p1", "_type": "post", "author": {"_ref": "author"}}},
+                {"create": {"_id": "p2", "_type": "post"}}
+            ]),
+        )
+        .await;
+
+        let get = |id: &str| {
+            Request::get(format!("/v1/data/references/{dataset}/{id}"))
+                .body(Default::default())
+                .unwrap()
+        };
+        let (status, _, body) = send(&state, get("author")).await;
+        assert_eq!(status, StatusCode::OK);
+        let body: ReferencesResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.documents.len(), 1);
+        assert_eq!(body.documents[0]["_id"], "p1");
+
+        let (_, _, body) = send(&state, get("p2")).await;
+        let body: ReferencesResponse = serde_json::from_slice(&body).unwrap();
+        assert!(body.documents.is_empty());
+    }
+
+    #[tokio::test]
+    async fn grants_hide_referencing_documents() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let dataset = create_dataset(&state).await;
+        seed(
+            &state,
+            &dataset,
+            json!([
+                {"create": {"_id": "author", "_type": "person"}},
+                {"create": {"_id": "p1", "_type": "post", "author": {"_ref": "author"}}},
+                {"create": {"_id": "d1", "_type": "draft", "author": {"_ref": "author"}}}
+            ]),
+        )
+        .await;
+
+        let (status, _, body) = send(
+            &state,
+            Request::get(format!("/v1/data/references/{dataset}/author"))
+                .header(
+                    "authorization",
+                    token(&state, Some(&[r#"_type == "post""#])),
+                )
+                .body(Default::default())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let body: ReferencesResponse = serde_json::from_slice(&body).unwrap();
+        let ids: Vec<_> = body.documents.iter().map(|doc| &doc["_id"]).collect();
+        assert_eq!(ids, vec!["p1"]);
+    }
+}
@@ -1,9 +1,17 @@
 use std::sync::Arc;
+use std::time::Duration;
 
+use content_lake_core::assets::{BlobStore, LocalBlobStore};
 use content_lake_core::events::bus::EventBus;
+use content_lake_core::events::presence::PresenceHub;
+use content_lake_core::grants::Grants;
+use content_lake_core::store::PgDocumentStore;
 use sqlx::PgPool;
+use uuid::Uuid;
 
-use crate::config::AppConfig;
+use crate::cache::{DatasetIdCache, ExprCache, HealthCache, SqlCache};
+use crate::config::{AppConfig, AssetBackend};
+use crate::error::{ApiError, ApiResult};
 
 /// Shared application state, passed to all handlers via Axum's `State` extractor.
 /// Wrapped in `Arc` so cloning is cheap.
@@ -17,12 +25,34 @@ struct InnerState {
     pub pool: PgPool,
     pub config: AppConfig,
     pub event_bus: EventBus,
+    pub presence: PresenceHub,
+    pub store: PgDocumentStore,
+    pub query_cache: ExprCache,
+    pub sql_cache: SqlCache,
+    pub dataset_ids: DatasetIdCache,
+    pub health: HealthCache,
+    pub blob_store: Arc<dyn BlobStore>,
+    pub public_grants: Option<Grants>,
 }
 
 impl AppState {
     pub fn new(pool: PgPool, config: AppConfig, event_bus: EventBus) -> Self {
+        let public_grants = config.public_grants.as_ref().map(|filters| {
+            Grants::compile(filters).unwrap_or_else(|err| panic!("PUBLIC_GRANTS: {err}"))
+        });
         Self {
             inner: Arc::new(InnerState {
+                public_grants,
+                store: PgDocumentStore::new(pool.clone()).with_statement_timeout(
+                    (config.query_timeout_ms > 0)
+                        .then(|| Duration::from_millis(config.query_timeout_ms)),
+                ),
+                query_cache: ExprCache::new(config.query_cache_size),
+                sql_cache: SqlCache::new(config.query_cache_size),
+                dataset_ids: DatasetIdCache::new(Duration::from_millis(config.dataset_cache_ms)),
+                health: HealthCache::new(Duration::from_millis(config.health_cache_ms)),
+                blob_store: blob_store(&config),
+                presence: PresenceHub::new(config.event_bus_capacity),
                 pool,
                 config,
                 event_bus,
@@ -34,7 +64,6 @@ impl AppState {
         &self.inner.pool
     }
 
-    #[allow(dead_code)]
     pub fn config(&self) -> &AppConfig {
         &self.inner.config
     }
@@ -42,4 +71,97 @@ impl AppState {
     pub fn event_bus(&self) -> &EventBus {
         &self.inner.event_bus
     }
+
+    pub fn presence(&self) -> &PresenceHub {
+        &self.inner.presence
+    }
+
+    pub fn store(&self) -> &PgDocumentStore {
+        &self.inner.store
+    }
+
+    pub fn blob_store(&self) -> &dyn BlobStore {
+        self.inner.blob_store.as_ref()
+    }
+
+    pub fn query_cache(&self) -> &ExprCache {
+        &self.inner.query_cache
+    }
+
+    pub fn sql_cache(&self) -> &SqlCache {
+        &self.inner.sql_cache
+    }
+
+    /// Read grants of requests without a bearer token; `None` if they are
+    /// unrestricted.
+    pub fn public_grants(&self) -> Option<&Grants> {
+        self.inner.public_grants.as_ref()
+    }
+
+    pub fn health_cache(&self) -> &HealthCache {
+        &self.inner.health
+    }
+
+    /// Check database connectivity, reusing a recent successful check.
+    pub async fn check_database(&self) -> Result<(), sqlx::Error> {
+        if self.inner.health.fresh() {
+            return Ok(());
+        }
+        sqlx::query("SELECT 1").execute(self.pool()).await?;
+        self.inner.health.record_ok();
+        Ok(())
+    }
+
+    /// Resolve a dataset name from a route path to its id, returning
+    /// `NotFound` if it doesn't exist. Known names are cached.
+    pub async fn dataset_id(&self, name: &str) -> ApiResult<Uuid> {
+        if let Some(id) = self.inner.dataset_ids.get(name) {
+            return Ok(id);
+        }
+        let id = self
+            .store()
+            .dataset_id(name)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("dataset not found: {name}")))?;
+        self.inner.dataset_ids.insert(name, id);
+        Ok(id)
+    }
+
+    /// `err` from writing to dataset `name`, or `NotFound` if the write
+    /// broke a foreign key to the dataset: it was deleted, e.g. through
+    /// another node, while its id was cached here. The stale id is dropped.
+    pub fn dataset_write_error(&self, name: &str, err: impl Into<ApiError>) -> ApiError {
+        let err = err.into();
+        let gone = match &err {
+            ApiError::Database(err) => err.as_database_error().is_some_and(|err| {
+                err.is_foreign_key_violation()
+                    && err
+                        .constraint()
+                        .is_some_and(|constraint| constraint.ends_with("dataset_id_fkey"))
+            }),
+            _ => false,
+        };
+        if !gone {
+            return err;
+        }
+        self.inner.dataset_ids.remove(name);
+        ApiError::NotFound(format!("dataset not found: {name}"))
+    }
+
+    /// Delete a dataset and drop its cached id.
+    pub async fn delete_dataset(&self, name: &str, purge: bool) -> ApiResult<()> {
+        self.store().delete_dataset(name, purge).await?;
+        self.inner.dataset_ids.remove(name);
+        Ok(())
+    }
+}
+
+/// The asset storage backend selected by `config`.
+fn blob_store(config: &AppConfig) -> Arc<dyn BlobStore> {
+    match config.asset_backend {
+        AssetBackend::Local => Arc::new(LocalBlobStore::new(
+            &config.asset_dir,
+            &config.asset_base_url,
+        )),
+    }
 }
@@ -0,0 +1,127 @@
+//! Where uploaded asset files are kept.
+//!
+//! Handlers hold a [`BlobStore`] trait object, so the local filesystem
+//! backend can be swapped for object storage without touching them.
+
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use futures::future::BoxFuture;
+
+/// Storage for asset files, addressed by relative `/`-separated paths.
+pub trait BlobStore: Send + Sync {
+    /// Store `bytes` at `path`, replacing any existing blob.
+    fn put<'a>(&'a self, path: &'a str, bytes: &'a [u8]) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Read the blob at `path`, or `None` if there is none.
+    fn get<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<Option<Vec<u8>>>>;
+
+    /// Remove the blob at `path`; removing a missing blob is not an error.
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Public URL the blob at `path` is served from.
+    fn url<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<String>>;
+}
+
+/// Blobs as files under a directory on the local filesystem.
+#[derive(Debug, Clone)]
+pub struct LocalBlobStore {
+    root: PathBuf,
+    base_url: String,
+}
+
+impl LocalBlobStore {
+    /// Store files under `root`, served from URLs starting with `base_url`.
+    pub fn new(root: impl Into<PathBuf>, base_url: &str) -> Self {
+        Self {
+            root: root.into(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Resolve `path` under the root, refusing anything that would escape it.
+    fn resolve(&self, path: &str) -> io::Result<PathBuf> {
+        let relative = Path::new(path);
+        if !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid asset path: {path}"),
+            ));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+impl BlobStore for LocalBlobStore {
+    fn put<'a>(&'a self, path: &'a str, bytes: &'a [u8]) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let file = self.resolve(path)?;
+            if let Some(dir) = file.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+            tokio::fs::write(file, bytes).await
+        })
+    }
+
+    fn get<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<Option<Vec<u8>>>> {
+        Box::pin(async move {
+            match tokio::fs::read(self.resolve(path)?).await {
+                Ok(bytes) => Ok(Some(bytes)),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err),
+            }
+        })
+    }
+
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self.resolve(path)?).await {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    fn url<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<String>> {
+        Box::pin(async move {
+            self.resolve(path)?;
+            Ok(format!("{}/{path}", self.base_url))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn local_store_put_get_url_delete() {
+        let root = std::env::temp_dir().join(format!("assets-{}", Uuid::new_v4()));
+        let store = LocalBlobStore::new(&root, "https://cdn.example.com/files/");
+
+        store.put("images/ds/a.png", b"png").await.unwrap();
+        assert_eq!(
+            store.get("images/ds/a.png").await.unwrap().as_deref(),
+            Some(&b"png"[..])
+        );
+        assert_eq!(
+            store.url("images/ds/a.png").await.unwrap(),
+            "https://cdn.example.com/files/images/ds/a.png"
+        );
+
+        store.delete("images/ds/a.png").await.unwrap();
+        assert_eq!(store.get("images/ds/a.png").await.unwrap(), None);
+        store.delete("images/ds/a.png").await.unwrap();
+
+        let err = store.put("../escape.png", b"x").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(store.put("/abs.png", b"x").await.is_err());
+        assert!(store.url("a/../../b").await.is_err());
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
@@ -0,0 +1,137 @@
+//! Image assets: content addressing, metadata and the `sanity.imageAsset`
+//! document describing an upload.
+
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ImageError {
+    #[error("unsupported image format")]
+    UnsupportedFormat,
+    #[error("could not read image dimensions")]
+    InvalidImage,
+}
+
+/// What is known about an uploaded image before it is stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInfo {
+    /// Hex SHA-1 of the bytes; identical uploads share it.
+    pub sha1: String,
+    pub width: usize,
+    pub height: usize,
+    pub mime_type: &'static str,
+    pub extension: &'static str,
+    pub size: usize,
+}
+
+impl ImageInfo {
+    /// Sniff the format and dimensions of `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ImageError> {
+        let (mime_type, extension) = match imagesize::image_type(bytes) {
+            Ok(imagesize::ImageType::Png) => ("image/png", "png"),
+            Ok(imagesize::ImageType::Jpeg) => ("image/jpeg", "jpg"),
+            Ok(imagesize::ImageType::Gif) => ("image/gif", "gif"),
+            Ok(imagesize::ImageType::Webp) => ("image/webp", "webp"),
+            _ => return Err(ImageError::UnsupportedFormat),
+        };
+        let dimensions = imagesize::blob_size(bytes).map_err(|_| ImageError::InvalidImage)?;
+        Ok(Self {
+            sha1: format!("{:x}", Sha1::digest(bytes)),
+            width: dimensions.width,
+            height: dimensions.height,
+            mime_type,
+            extension,
+            size: bytes.len(),
+        })
+    }
+
+    /// Document id, following Sanity's `image-{sha1}-{w}x{h}-{ext}` scheme.
+    pub fn document_id(&self) -> String {
+        format!(
+            "image-{}-{}x{}-{}",
+            self.sha1, self.width, self.height, self.extension
+        )
+    }
+
+    /// Storage path of the file within the dataset's assets.
+    pub fn path(&self, dataset: &str) -> String {
+        format!(
+            "images/{dataset}/{}-{}x{}.{}",
+            self.sha1, self.width, self.height, self.extension
+        )
+    }
+
+    /// The `sanity.imageAsset` document for this image, served from `url`.
+    pub fn document(&self, dataset: &str, url: &str, original_filename: Option<&str>) -> Value {
+        let mut doc = json!({
+            "_id": self.document_id(),
+            "_type": "sanity.imageAsset",
+            "assetId": self.sha1,
+            "sha1hash": self.sha1,
+            "extension": self.extension,
+            "mimeType": self.mime_type,
+            "size": self.size,
+            "path": self.path(dataset),
+            "url": url,
+            "metadata": {
+                "_type": "sanity.imageMetadata",
+                "dimensions": {
+                    "_type": "sanity.imageDimensions",
+                    "width": self.width,
+                    "height": self.height,
+                    "aspectRatio": self.width as f64 / self.height.max(1) as f64,
+                },
+            },
+        });
+        if let Some(name) = original_filename {
+            doc["originalFilename"] = Value::String(name.to_string());
+        }
+        doc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x1 RGBA PNG.
+    const TEST_PNG: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0xf4,
+        0x22, 0x7f, 0x8a, 0x00, 0x00, 0x00, 0x0e, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0xf8,
+        0xcf, 0xc0, 0x00, 0x42, 0xff, 0x01, 0x0f, 0xf9, 0x03, 0xfd, 0x98, 0x79, 0xd7, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn reads_png_metadata() {
+        let info = ImageInfo::from_bytes(TEST_PNG).unwrap();
+        assert_eq!((info.width, info.height), (2, 1));
+        assert_eq!(info.mime_type, "image/png");
+        assert_eq!(info.size, TEST_PNG.len());
+        assert_eq!(info.sha1.len(), 40);
+        assert_eq!(info.document_id(), format!("image-{}-2x1-png", info.sha1));
+
+        let doc = info.document("production", "/a.png", Some("dot.png"));
+        assert_eq!(doc["_type"], "sanity.imageAsset");
+        assert_eq!(
+            doc["path"],
+            format!("images/production/{}-2x1.png", info.sha1)
+        );
+        assert_eq!(doc["metadata"]["dimensions"]["aspectRatio"], 2.0);
+        assert_eq!(doc["originalFilename"], "dot.png");
+    }
+
+    #[test]
+    fn rejects_non_images() {
+        assert_eq!(
+            ImageInfo::from_bytes(b"plain text"),
+            Err(ImageError::UnsupportedFormat)
+        );
+        assert_eq!(
+            ImageInfo::from_bytes(&TEST_PNG[..12]),
+            Err(ImageError::InvalidImage)
+        );
+    }
+}
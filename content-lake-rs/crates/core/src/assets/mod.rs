@@ -0,0 +1,5 @@
+pub mod blob;
+pub mod image;
+pub mod transform;
+
+pub use blob::{BlobStore, LocalBlobStore};
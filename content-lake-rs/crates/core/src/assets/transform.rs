@@ -0,0 +1,318 @@
+//! On-the-fly image transforms, driven by Sanity's image URL parameters:
+//! `w` and `h` (pixels), `fit` and `fm` (output format).
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use thiserror::Error;
+
+/// Largest width or height a transform may ask for.
+const MAX_DIMENSION: u32 = 8192;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TransformError {
+    #[error("invalid {param}: {value}")]
+    InvalidParam { param: &'static str, value: String },
+    #[error("cannot decode image: {0}")]
+    Decode(String),
+    #[error("cannot encode image: {0}")]
+    Encode(String),
+}
+
+/// How the image is fitted when both `w` and `h` are given.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fit {
+    /// Fit within the box, keeping the aspect ratio.
+    #[default]
+    Clip,
+    /// Like `clip`, but never scale up.
+    Max,
+    /// Fill the box, cropping whatever overflows it.
+    Crop,
+    /// Stretch to exactly the box.
+    Scale,
+}
+
+impl Fit {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "clip" => Some(Fit::Clip),
+            "max" => Some(Fit::Max),
+            "crop" => Some(Fit::Crop),
+            "scale" => Some(Fit::Scale),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Fit::Clip => "clip",
+            Fit::Max => "max",
+            Fit::Crop => "crop",
+            Fit::Scale => "scale",
+        }
+    }
+}
+
+/// Output formats a transform can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Jpg,
+    Png,
+    Webp,
+    Gif,
+}
+
+impl Format {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "jpg" | "jpeg" => Some(Format::Jpg),
+            "png" => Some(Format::Png),
+            "webp" => Some(Format::Webp),
+            "gif" => Some(Format::Gif),
+            _ => None,
+        }
+    }
+
+    /// The format of a file with `extension`, as stored by uploads.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Self::parse(extension)
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Jpg => "jpg",
+            Format::Png => "png",
+            Format::Webp => "webp",
+            Format::Gif => "gif",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Format::Jpg => "image/jpeg",
+            Format::Png => "image/png",
+            Format::Webp => "image/webp",
+            Format::Gif => "image/gif",
+        }
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            Format::Jpg => ImageFormat::Jpeg,
+            Format::Png => ImageFormat::Png,
+            Format::Webp => ImageFormat::WebP,
+            Format::Gif => ImageFormat::Gif,
+        }
+    }
+}
+
+/// A requested resize and/or format conversion.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transform {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fit: Fit,
+    pub format: Option<Format>,
+}
+
+impl Transform {
+    /// Read `w`, `h`, `fit` and `fm` from query parameters; others are ignored.
+    pub fn from_query(params: &HashMap<String, String>) -> Result<Self, TransformError> {
+        let invalid = |param, value: &str| TransformError::InvalidParam {
+            param,
+            value: value.to_string(),
+        };
+        let dimension = |param| {
+            params
+                .get(param)
+                .map(|v| {
+                    v.parse::<u32>()
+                        .ok()
+                        .filter(|n| (1..=MAX_DIMENSION).contains(n))
+                        .ok_or_else(|| invalid(param, v))
+                })
+                .transpose()
+        };
+        Ok(Self {
+            width: dimension("w")?,
+            height: dimension("h")?,
+            fit: match params.get("fit") {
+                Some(v) => Fit::parse(v).ok_or_else(|| invalid("fit", v))?,
+                None => Fit::default(),
+            },
+            format: params
+                .get("fm")
+                .map(|v| Format::parse(v).ok_or_else(|| invalid("fm", v)))
+                .transpose()?,
+        })
+    }
+
+    /// True if the transform would return the original unchanged.
+    pub fn is_identity(&self) -> bool {
+        self.width.is_none() && self.height.is_none() && self.format.is_none()
+    }
+
+    /// Identifies the transform, for caching its output.
+    pub fn key(&self) -> String {
+        let dimension = |d: Option<u32>| d.map_or("auto".to_string(), |d| d.to_string());
+        format!(
+            "w{}-h{}-{}-{}",
+            dimension(self.width),
+            dimension(self.height),
+            self.fit.name(),
+            self.format.map_or("orig", Format::extension)
+        )
+    }
+
+    /// Apply the transform to encoded image `bytes` in `original` format.
+    pub fn apply(&self, bytes: &[u8], original: Format) -> Result<Vec<u8>, TransformError> {
+        let img =
+            image::load_from_memory(bytes).map_err(|e| TransformError::Decode(e.to_string()))?;
+        let img = self.resize(img);
+        let format = self.format.unwrap_or(original);
+        // JPEG has no alpha channel.
+        let img = match format {
+            Format::Jpg => DynamicImage::ImageRgb8(img.to_rgb8()),
+            _ => img,
+        };
+        let mut out = Cursor::new(Vec::new());
+        img.write_to(&mut out, format.image_format())
+            .map_err(|e| TransformError::Encode(e.to_string()))?;
+        Ok(out.into_inner())
+    }
+
+    fn resize(&self, img: DynamicImage) -> DynamicImage {
+        let (w, h) = (img.width(), img.height());
+        let filter = FilterType::CatmullRom;
+        let (box_w, box_h) = match (self.width, self.height) {
+            (None, None) => return img,
+            // A single dimension scales the other to keep the aspect ratio.
+            (Some(bw), None) => (bw, scaled(h, bw, w)),
+            (None, Some(bh)) => (scaled(w, bh, h), bh),
+            (Some(bw), Some(bh)) => (bw, bh),
+        };
+        match self.fit {
+            Fit::Max if box_w >= w && box_h >= h => img,
+            Fit::Clip | Fit::Max => img.resize(box_w, box_h, filter),
+            Fit::Crop => img.resize_to_fill(box_w, box_h, filter),
+            Fit::Scale => img.resize_exact(box_w, box_h, filter),
+        }
+    }
+}
+
+/// `length` scaled by `to / from`, between 1 and `MAX_DIMENSION`.
+fn scaled(length: u32, to: u32, from: u32) -> u32 {
+    let length = (length as u64 * to as u64 + from as u64 / 2) / from.max(1) as u64;
+    length.clamp(1, MAX_DIMENSION as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, RgbaImage};
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(width, height));
+        let mut out = Cursor::new(Vec::new());
+        img.write_to(&mut out, ImageFormat::Png).unwrap();
+        out.into_inner()
+    }
+
+    fn dimensions(bytes: &[u8]) -> (u32, u32) {
+        image::load_from_memory(bytes).unwrap().dimensions()
+    }
+
+    #[test]
+    fn parses_query_params() {
+        let t = Transform::from_query(&params(&[
+            ("w", "100"),
+            ("h", "50"),
+            ("fit", "crop"),
+            ("fm", "webp"),
+            ("dl", "ignored"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            t,
+            Transform {
+                width: Some(100),
+                height: Some(50),
+                fit: Fit::Crop,
+                format: Some(Format::Webp),
+            }
+        );
+        assert_eq!(t.key(), "w100-h50-crop-webp");
+        assert!(Transform::from_query(&params(&[])).unwrap().is_identity());
+    }
+
+    #[test]
+    fn rejects_invalid_params() {
+        for (param, value) in [
+            ("w", "0"),
+            ("w", "-3"),
+            ("h", "big"),
+            ("h", "9000"),
+            ("fit", "stretch"),
+            ("fm", "bmp"),
+        ] {
+            assert_eq!(
+                Transform::from_query(&params(&[(param, value)])),
+                Err(TransformError::InvalidParam {
+                    param,
+                    value: value.into()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn resizes_by_fit() {
+        let src = png(40, 20);
+        let resize = |pairs: &[(&str, &str)]| {
+            let t = Transform::from_query(&params(pairs)).unwrap();
+            dimensions(&t.apply(&src, Format::Png).unwrap())
+        };
+        assert_eq!(resize(&[("w", "10")]), (10, 5));
+        assert_eq!(resize(&[("h", "10")]), (20, 10));
+        assert_eq!(resize(&[("w", "10"), ("h", "10")]), (10, 5));
+        assert_eq!(
+            resize(&[("w", "10"), ("h", "10"), ("fit", "crop")]),
+            (10, 10)
+        );
+        assert_eq!(
+            resize(&[("w", "10"), ("h", "10"), ("fit", "scale")]),
+            (10, 10)
+        );
+        assert_eq!(resize(&[("w", "80"), ("fit", "max")]), (40, 20));
+        assert_eq!(resize(&[("w", "80")]), (80, 40));
+    }
+
+    #[test]
+    fn derived_dimensions_stay_within_the_limit() {
+        let resize = |src: &[u8], pairs: &[(&str, &str)]| {
+            let t = Transform::from_query(&params(pairs)).unwrap();
+            dimensions(&t.apply(src, Format::Png).unwrap())
+        };
+        assert_eq!(resize(&png(1, 4096), &[("w", "8192")]), (2, 8192));
+        assert_eq!(resize(&png(4096, 1), &[("h", "8192")]), (8192, 2));
+        assert_eq!(scaled(u32::MAX, MAX_DIMENSION, 1), MAX_DIMENSION);
+    }
+
+    #[test]
+    fn converts_format() {
+        let t = Transform::from_query(&params(&[("fm", "jpg")])).unwrap();
+        let out = t.apply(&png(4, 4), Format::Png).unwrap();
+        assert_eq!(image::guess_format(&out).unwrap(), ImageFormat::Jpeg);
+        assert_eq!(dimensions(&out), (4, 4));
+    }
+}
@@ -5,9 +5,24 @@
 //! - Draft: `drafts.{id}`
 //! - Version: `versions.{releaseId}.{id}`
 
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
 const DRAFT_PREFIX: &str = "drafts.";
 const VERSION_PREFIX: &str = "versions.";
 
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum IdParseError {
+    #[error("document id cannot be empty")]
+    Empty,
+    #[error("document id has an empty base id: {0}")]
+    EmptyBaseId(String),
+    #[error("version id has an empty release id: {0}")]
+    EmptyReleaseId(String),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DocumentIdKind {
     Published(String),
@@ -35,6 +50,24 @@ impl DocumentIdKind {
         }
     }
 
+    /// Parse a document ID, rejecting empty base and release ids that
+    /// [`parse`](Self::parse) would accept.
+    pub fn parse_checked(id: &str) -> Result<Self, IdParseError> {
+        if id.is_empty() {
+            return Err(IdParseError::Empty);
+        }
+        let kind = Self::parse(id);
+        if let DocumentIdKind::Version { release_id, .. } = &kind {
+            if release_id.is_empty() {
+                return Err(IdParseError::EmptyReleaseId(id.to_string()));
+            }
+        }
+        if kind.base_id().is_empty() {
+            return Err(IdParseError::EmptyBaseId(id.to_string()));
+        }
+        Ok(kind)
+    }
+
     /// Get the base (published) document ID regardless of prefix.
     pub fn base_id(&self) -> &str {
         match self {
@@ -69,6 +102,22 @@ impl DocumentIdKind {
     }
 }
 
+impl fmt::Display for DocumentIdKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.full_id())
+    }
+}
+
+/// Parses with [`DocumentIdKind::parse_checked`], so `id.parse()?` rejects
+/// empty base and release ids.
+impl FromStr for DocumentIdKind {
+    type Err = IdParseError;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        Self::parse_checked(id)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,4 +154,47 @@ mod tests {
         assert_eq!(kind.full_id(), "versions.release1.abc123");
         assert!(kind.is_version());
     }
+
+    #[test]
+    fn parse_checked_rejects_empty_base() {
+        assert_eq!(
+            DocumentIdKind::parse_checked("drafts."),
+            Err(IdParseError::EmptyBaseId("drafts.".to_string()))
+        );
+        assert_eq!(
+            DocumentIdKind::parse_checked("versions.r1."),
+            Err(IdParseError::EmptyBaseId("versions.r1.".to_string()))
+        );
+        assert_eq!(DocumentIdKind::parse_checked(""), Err(IdParseError::Empty));
+    }
+
+    #[test]
+    fn parse_checked_rejects_empty_release() {
+        assert_eq!(
+            DocumentIdKind::parse_checked("versions..x"),
+            Err(IdParseError::EmptyReleaseId("versions..x".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_checked_accepts_well_formed_version() {
+        assert_eq!(
+            DocumentIdKind::parse_checked("versions.r1.abc"),
+            Ok(DocumentIdKind::Version {
+                release_id: "r1".to_string(),
+                base_id: "abc".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn display_and_from_str_round_trip() {
+        for id in ["abc123", "drafts.abc123", "versions.r1.abc123"] {
+            let kind: DocumentIdKind = id.parse().unwrap();
+            assert_eq!(kind, DocumentIdKind::parse(id));
+            assert_eq!(kind.to_string(), id);
+            assert_eq!(format!("{kind}").parse::<DocumentIdKind>().unwrap(), kind);
+        }
+        assert!("drafts.".parse::<DocumentIdKind>().is_err());
+    }
 }
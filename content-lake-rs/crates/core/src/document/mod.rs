@@ -1,3 +1,4 @@
 pub mod id;
 pub mod model;
+pub mod perspective;
 pub mod validate;
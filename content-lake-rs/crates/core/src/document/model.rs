@@ -1,10 +1,19 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use uuid::Uuid;
 
+use super::validate::{validate_content_keys, ValidationError};
+
+/// System fields managed by the content lake rather than stored in `content`.
+pub const SYSTEM_FIELDS: &[&str] = &["_id", "_type", "_rev", "_createdAt", "_updatedAt"];
+
 /// Core Sanity document stored in the content lake.
 /// Maps to the `documents` PostgreSQL table.
+///
+/// `content` collects every field not matched by name, so call
+/// [`validate`](Self::validate) on deserialized documents to reject unknown
+/// `_`-prefixed fields among them.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SanityDocument {
     pub _id: String,
@@ -20,7 +29,7 @@ pub struct SanityDocument {
 }
 
 /// Database row representation of a document.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, sqlx::FromRow)]
 pub struct DocumentRow {
     pub id: Uuid,
     pub dataset_id: Uuid,
@@ -32,3 +41,184 @@ pub struct DocumentRow {
     pub updated_at: DateTime<Utc>,
     pub deleted: bool,
 }
+
+impl DocumentRow {
+    /// Assemble the full JSON document: stored content plus system fields.
+    pub fn to_document(&self) -> Value {
+        let mut map = match &self.content {
+            Value::Object(map) => map.clone(),
+            _ => serde_json::Map::new(),
+        };
+        map.insert("_id".into(), Value::String(self.document_id.clone()));
+        map.insert("_type".into(), Value::String(self.doc_type.clone()));
+        map.insert("_rev".into(), Value::String(self.revision.clone()));
+        map.insert(
+            "_createdAt".into(),
+            Value::String(self.created_at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        );
+        map.insert(
+            "_updatedAt".into(),
+            Value::String(self.updated_at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        );
+        Value::Object(map)
+    }
+}
+
+impl SanityDocument {
+    /// Check `content` for reserved fields; see [`validate_content_keys`].
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_content_keys(&self.content)
+    }
+
+    /// Build the database row for this document in `dataset_id`.
+    /// The row gets a fresh primary key and is not deleted.
+    pub fn to_row(&self, dataset_id: Uuid) -> DocumentRow {
+        DocumentRow {
+            id: Uuid::new_v4(),
+            dataset_id,
+            document_id: self._id.clone(),
+            doc_type: self._type.clone(),
+            revision: self._rev.clone(),
+            content: content_without_system_fields(&Value::Object(self.content.clone())),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            deleted: false,
+        }
+    }
+}
+
+impl From<DocumentRow> for SanityDocument {
+    fn from(row: DocumentRow) -> Self {
+        let content = match content_without_system_fields(&row.content) {
+            Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        };
+        SanityDocument {
+            _id: row.document_id,
+            _type: row.doc_type,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            _rev: row.revision,
+            content,
+        }
+    }
+}
+
+/// Strip system fields from a document, leaving the JSONB `content` payload.
+pub fn content_without_system_fields(doc: &Value) -> Value {
+    match doc {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(k, _)| !SYSTEM_FIELDS.contains(&k.as_str()))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        ),
+        _ => Value::Object(serde_json::Map::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn row_to_document_merges_system_fields() {
+        let now = Utc::now();
+        let row = DocumentRow {
+            id: Uuid::new_v4(),
+            dataset_id: Uuid::new_v4(),
+            document_id: "post-1".into(),
+            doc_type: "post".into(),
+            revision: "rev1".into(),
+            content: json!({"title": "Hello"}),
+            created_at: now,
+            updated_at: now,
+            deleted: false,
+        };
+        let doc = row.to_document();
+        assert_eq!(doc["_id"], "post-1");
+        assert_eq!(doc["_type"], "post");
+        assert_eq!(doc["_rev"], "rev1");
+        assert_eq!(doc["title"], "Hello");
+        assert_eq!(
+            content_without_system_fields(&doc),
+            json!({"title": "Hello"})
+        );
+    }
+
+    fn sample_row() -> DocumentRow {
+        let now = Utc::now();
+        DocumentRow {
+            id: Uuid::new_v4(),
+            dataset_id: Uuid::new_v4(),
+            document_id: "drafts.post-1".into(),
+            doc_type: "post".into(),
+            revision: "rev1".into(),
+            content: json!({"title": "Hello", "tags": ["a"]}),
+            created_at: now,
+            updated_at: now,
+            deleted: false,
+        }
+    }
+
+    #[test]
+    fn row_and_sanity_document_round_trip() {
+        let row = sample_row();
+        let doc = SanityDocument::from(row.clone());
+        assert_eq!(doc._id, "drafts.post-1");
+        assert_eq!(doc._type, "post");
+        assert_eq!(doc._rev, "rev1");
+        assert_eq!(Value::Object(doc.content.clone()), row.content);
+
+        let back = doc.to_row(row.dataset_id);
+        assert_eq!(back.dataset_id, row.dataset_id);
+        assert_eq!(back.document_id, row.document_id);
+        assert_eq!(back.doc_type, row.doc_type);
+        assert_eq!(back.revision, row.revision);
+        assert_eq!(back.content, row.content);
+        assert_eq!(back.created_at, row.created_at);
+        assert_eq!(back.to_document(), row.to_document());
+        assert_eq!(serde_json::to_value(&doc).unwrap()["title"], "Hello");
+    }
+
+    #[test]
+    fn rejects_unknown_reserved_fields_in_content() {
+        let doc: SanityDocument = serde_json::from_value(json!({
+            "_id": "post-1",
+            "_type": "post",
+            "_rev": "rev1",
+            "_createdAt": "2024-01-01T00:00:00Z",
+            "_updatedAt": "2024-01-01T00:00:00Z",
+            "title": "Hello",
+            "_weird": true,
+        }))
+        .unwrap();
+        assert!(matches!(
+            doc.validate(),
+            Err(ValidationError::ReservedKey(key)) if key == "_weird"
+        ));
+
+        let clean = SanityDocument::from(sample_row());
+        let clean: SanityDocument =
+            serde_json::from_value(serde_json::to_value(&clean).unwrap()).unwrap();
+        assert!(clean.validate().is_ok());
+        assert_eq!(clean.content["title"], "Hello");
+    }
+
+    #[test]
+    fn system_fields_never_leak_into_content() {
+        let mut row = sample_row();
+        row.content = json!({"title": "Hello", "_id": "spoofed", "_rev": "old"});
+        let doc = SanityDocument::from(row);
+        assert_eq!(doc._id, "drafts.post-1");
+        assert_eq!(
+            Value::Object(doc.content.clone()),
+            json!({"title": "Hello"})
+        );
+
+        let mut doc = doc;
+        doc.content.insert("_type".into(), json!("spoofed"));
+        assert_eq!(doc.to_row(Uuid::nil()).content, json!({"title": "Hello"}));
+    }
+}
@@ -0,0 +1,160 @@
+//! Query perspectives: which document variants a query sees.
+//!
+//! - `raw`: every stored document, drafts and versions included.
+//! - `published`: published documents only.
+//! - `previewDrafts`: published documents with their drafts laid over them;
+//!   drafts without a published counterpart are included as well.
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::id::DocumentIdKind;
+use super::model::DocumentRow;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Perspective {
+    #[default]
+    Raw,
+    #[serde(rename = "published")]
+    PublishedOnly,
+    PreviewDrafts,
+}
+
+impl FromStr for Perspective {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(Perspective::Raw),
+            "published" => Ok(Perspective::PublishedOnly),
+            "previewDrafts" => Ok(Perspective::PreviewDrafts),
+            other => Err(format!("unknown perspective: {other}")),
+        }
+    }
+}
+
+/// Apply a perspective to a set of documents, preserving input order.
+///
+/// Under `PreviewDrafts` a draft takes the place of its published document;
+/// version documents are dropped by every perspective except `Raw`.
+pub fn overlay(docs: Vec<DocumentRow>, perspective: Perspective) -> Vec<DocumentRow> {
+    match perspective {
+        Perspective::Raw => docs,
+        Perspective::PublishedOnly => docs
+            .into_iter()
+            .filter(|doc| DocumentIdKind::parse(&doc.document_id).is_published())
+            .collect(),
+        Perspective::PreviewDrafts => preview_drafts(docs),
+    }
+}
+
+fn preview_drafts(docs: Vec<DocumentRow>) -> Vec<DocumentRow> {
+    let kinds: Vec<DocumentIdKind> = docs
+        .iter()
+        .map(|doc| DocumentIdKind::parse(&doc.document_id))
+        .collect();
+    let published: HashSet<&str> = kinds
+        .iter()
+        .filter(|kind| kind.is_published())
+        .map(DocumentIdKind::base_id)
+        .collect();
+
+    let mut drafts: HashMap<String, DocumentRow> = HashMap::new();
+    let mut slots: Vec<DocumentRow> = Vec::with_capacity(docs.len());
+    for (doc, kind) in docs.into_iter().zip(&kinds) {
+        match kind {
+            // Shadowed below, in its published document's slot.
+            DocumentIdKind::Draft(base) if published.contains(&base.as_str()) => {
+                drafts.insert(base.clone(), doc);
+            }
+            DocumentIdKind::Version { .. } => {}
+            _ => slots.push(doc),
+        }
+    }
+
+    slots
+        .into_iter()
+        .map(|doc| drafts.remove(&doc.document_id).unwrap_or(doc))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn row(id: &str, title: &str) -> DocumentRow {
+        let now = Utc::now();
+        DocumentRow {
+            id: Uuid::new_v4(),
+            dataset_id: Uuid::nil(),
+            document_id: id.into(),
+            doc_type: "post".into(),
+            revision: "r".into(),
+            content: json!({"title": title}),
+            created_at: now,
+            updated_at: now,
+            deleted: false,
+        }
+    }
+
+    fn ids_and_titles(docs: &[DocumentRow]) -> Vec<(String, String)> {
+        docs.iter()
+            .map(|d| {
+                (
+                    d.document_id.clone(),
+                    d.content["title"].as_str().unwrap().into(),
+                )
+            })
+            .collect()
+    }
+
+    fn sample() -> Vec<DocumentRow> {
+        vec![
+            row("a", "A published"),
+            row("drafts.a", "A draft"),
+            row("b", "B published"),
+            row("drafts.c", "C draft only"),
+            row("versions.r1.a", "A in release"),
+        ]
+    }
+
+    #[test]
+    fn preview_drafts_shadows_published() {
+        let docs = overlay(sample(), Perspective::PreviewDrafts);
+        assert_eq!(
+            ids_and_titles(&docs),
+            vec![
+                ("drafts.a".into(), "A draft".into()),
+                ("b".into(), "B published".into()),
+                ("drafts.c".into(), "C draft only".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn published_only_hides_drafts() {
+        let docs = overlay(sample(), Perspective::PublishedOnly);
+        assert_eq!(
+            ids_and_titles(&docs),
+            vec![
+                ("a".into(), "A published".into()),
+                ("b".into(), "B published".into()),
+            ]
+        );
+        assert_eq!(overlay(sample(), Perspective::Raw).len(), 5);
+    }
+
+    #[test]
+    fn parses_perspective_names() {
+        assert_eq!("raw".parse(), Ok(Perspective::Raw));
+        assert_eq!("published".parse(), Ok(Perspective::PublishedOnly));
+        assert_eq!("previewDrafts".parse(), Ok(Perspective::PreviewDrafts));
+        assert!("drafts".parse::<Perspective>().is_err());
+    }
+}
@@ -1,7 +1,13 @@
 /// Document validation utilities.
 /// Will be expanded in Phase 1.
+use std::collections::HashSet;
+
+use serde_json::{Map, Value};
 use thiserror::Error;
 
+use super::model::SYSTEM_FIELDS;
+use crate::jsonmatch::PathError;
+
 #[derive(Debug, Error)]
 pub enum ValidationError {
     #[error("document _id is required")]
@@ -12,6 +18,14 @@ pub enum ValidationError {
     EmptyId,
     #[error("document _type cannot be empty")]
     EmptyType,
+    #[error("document must be a JSON object")]
+    NotAnObject,
+    #[error("invalid patch path: {reason}")]
+    InvalidPatchPath { path: String, reason: PathError },
+    #[error("duplicate _key {key:?} in {path}")]
+    DuplicateKey { path: String, key: String },
+    #[error("reserved field {0} is not allowed in document content")]
+    ReservedKey(String),
 }
 
 /// Validate that a document has the minimum required fields.
@@ -31,3 +45,86 @@ pub fn validate_document_fields(
     }
     Ok(())
 }
+
+/// Reject top-level `_`-prefixed fields other than the system fields. The
+/// underscore namespace is the content lake's, so an unknown one is either a
+/// typo or an attempt to smuggle a field in beside the system ones.
+pub fn validate_content_keys(content: &Map<String, Value>) -> Result<(), ValidationError> {
+    match content
+        .keys()
+        .find(|k| k.starts_with('_') && !SYSTEM_FIELDS.contains(&k.as_str()))
+    {
+        Some(key) => Err(ValidationError::ReservedKey(key.clone())),
+        None => Ok(()),
+    }
+}
+
+/// Reject arrays, at any depth, in which two items share a `_key`. Sanity
+/// addresses array items by key, so duplicates make edits ambiguous.
+pub fn validate_unique_keys(doc: &Value) -> Result<(), ValidationError> {
+    unique_keys(doc, &mut String::new())
+}
+
+/// `path` is the JSONMatch path of `value`, extended in place while descending.
+fn unique_keys(value: &Value, path: &mut String) -> Result<(), ValidationError> {
+    let len = path.len();
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(key);
+                unique_keys(child, path)?;
+                path.truncate(len);
+            }
+        }
+        Value::Array(items) => {
+            let mut seen = HashSet::new();
+            for (i, item) in items.iter().enumerate() {
+                if let Some(key) = item.get("_key").and_then(Value::as_str) {
+                    if !seen.insert(key) {
+                        return Err(ValidationError::DuplicateKey {
+                            path: path.clone(),
+                            key: key.to_string(),
+                        });
+                    }
+                }
+                path.push_str(&format!("[{i}]"));
+                unique_keys(item, path)?;
+                path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn accepts_distinct_keys() {
+        let doc = json!({
+            "body": [{"_key": "a"}, {"_key": "b", "children": [{"_key": "a"}]}, "text"],
+            "other": [{"_key": "a"}]
+        });
+        assert!(validate_unique_keys(&doc).is_ok());
+    }
+
+    #[test]
+    fn rejects_duplicate_keys() {
+        let doc = json!({"body": [{"_key": "a"}, {"_key": "b"}, {"_key": "a"}]});
+        let err = validate_unique_keys(&doc).unwrap_err();
+        assert!(matches!(
+            err,
+            ValidationError::DuplicateKey { ref path, ref key } if path == "body" && key == "a"
+        ));
+
+        let doc = json!({"body": [{"_key": "a", "children": [{"_key": "x"}, {"_key": "x"}]}]});
+        let err = validate_unique_keys(&doc).unwrap_err();
+        assert_eq!(err.to_string(), "duplicate _key \"x\" in body[0].children");
+    }
+}
@@ -1,21 +1,96 @@
-use std::sync::Arc;
-use tokio::sync::broadcast;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
 
 use super::types::ContentLakeEvent;
 
+/// An event as delivered by the bus, numbered in publish order.
+#[derive(Debug, Clone)]
+pub struct BusEvent {
+    /// Increases by one per published event, starting at 1. Sent to SSE
+    /// clients as the event id so they can resume with `Last-Event-ID`.
+    pub id: u64,
+    pub event: ContentLakeEvent,
+}
+
+impl BusEvent {
+    /// The dataset a mutation event belongs to.
+    pub fn dataset_id(&self) -> Option<&str> {
+        match &self.event {
+            ContentLakeEvent::Mutation(event) => Some(&event.dataset_id),
+            _ => None,
+        }
+    }
+}
+
+/// A subscription to one dataset's mutation events.
+#[derive(Debug)]
+pub struct DatasetSubscription {
+    dataset_id: String,
+    rx: broadcast::Receiver<BusEvent>,
+    lagged: Arc<AtomicU64>,
+}
+
+impl DatasetSubscription {
+    /// Receive the next event for the dataset, skipping other datasets'
+    /// events without cloning or serializing them further.
+    ///
+    /// A subscriber that falls more than the channel capacity behind gets
+    /// `RecvError::Lagged` once, counted in [`EventBus::lagged_count`], and
+    /// then continues from the oldest event still buffered.
+    pub async fn recv(&mut self) -> Result<BusEvent, RecvError> {
+        loop {
+            match self.rx.recv().await {
+                Ok(event) if event.dataset_id() == Some(self.dataset_id.as_str()) => {
+                    return Ok(event)
+                }
+                Ok(_) => continue,
+                Err(err) => {
+                    if let RecvError::Lagged(_) = err {
+                        self.lagged.fetch_add(1, Ordering::Relaxed);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+/// The most recent events, kept for replay to resuming subscribers.
+#[derive(Debug)]
+struct EventLog {
+    last_id: u64,
+    events: VecDeque<BusEvent>,
+    capacity: usize,
+}
+
 /// In-process event bus backed by `tokio::broadcast`.
 /// Single-node; will be extended to PG LISTEN/NOTIFY for multi-node.
+///
+/// The last `capacity` events are also kept in memory so a subscriber that
+/// dropped off can [`resume`](Self::resume) without missing any.
 #[derive(Debug, Clone)]
 pub struct EventBus {
-    sender: Arc<broadcast::Sender<ContentLakeEvent>>,
+    sender: Arc<broadcast::Sender<BusEvent>>,
+    log: Arc<Mutex<EventLog>>,
+    lagged: Arc<AtomicU64>,
 }
 
 impl EventBus {
-    /// Create a new event bus with the given channel capacity.
+    /// Create a new event bus with the given channel capacity, which is
+    /// also the replay window.
     pub fn new(capacity: usize) -> Self {
         let (sender, _) = broadcast::channel(capacity);
         Self {
             sender: Arc::new(sender),
+            log: Arc::new(Mutex::new(EventLog {
+                last_id: 0,
+                events: VecDeque::with_capacity(capacity),
+                capacity,
+            })),
+            lagged: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -23,19 +98,98 @@ impl EventBus {
     pub fn publish(
         &self,
         event: ContentLakeEvent,
-    ) -> Result<usize, broadcast::error::SendError<ContentLakeEvent>> {
+    ) -> Result<usize, broadcast::error::SendError<BusEvent>> {
+        let mut log = self.log.lock().expect("event log lock poisoned");
+        log.last_id += 1;
+        let event = BusEvent {
+            id: log.last_id,
+            event,
+        };
+        if log.events.len() == log.capacity {
+            log.events.pop_front();
+        }
+        log.events.push_back(event.clone());
+        // Sent under the lock so `resume` sees each event exactly once,
+        // either in the replay or on the receiver.
         self.sender.send(event)
     }
 
+    /// Publish an event, returning how many subscribers it reached. Having
+    /// none is normal for a broadcast channel, so it counts as zero rather
+    /// than an error; the event is still kept for replay.
+    pub fn publish_lossy(&self, event: ContentLakeEvent) -> usize {
+        self.publish(event).unwrap_or(0)
+    }
+
     /// Subscribe to the event stream.
-    pub fn subscribe(&self) -> broadcast::Receiver<ContentLakeEvent> {
+    pub fn subscribe(&self) -> broadcast::Receiver<BusEvent> {
         self.sender.subscribe()
     }
 
+    /// Subscribe from just after `last_event_id`: the missed events still in
+    /// the replay window, then a receiver for everything published later.
+    ///
+    /// Returns `None` if some of the missed events have already left the
+    /// window, or the id was never issued by this bus, in which case the
+    /// subscriber has to start over.
+    pub fn resume(
+        &self,
+        last_event_id: u64,
+    ) -> Option<(Vec<BusEvent>, broadcast::Receiver<BusEvent>)> {
+        let log = self.log.lock().expect("event log lock poisoned");
+        let oldest = log.events.front().map_or(log.last_id + 1, |e| e.id);
+        if last_event_id > log.last_id || last_event_id + 1 < oldest {
+            return None;
+        }
+        let missed = log
+            .events
+            .iter()
+            .filter(|e| e.id > last_event_id)
+            .cloned()
+            .collect();
+        Some((missed, self.sender.subscribe()))
+    }
+
+    /// Subscribe to the mutation events of one dataset.
+    pub fn subscribe_dataset(&self, dataset_id: Uuid) -> DatasetSubscription {
+        DatasetSubscription {
+            dataset_id: dataset_id.to_string(),
+            rx: self.subscribe(),
+            lagged: self.lagged.clone(),
+        }
+    }
+
+    /// [`resume`](Self::resume) scoped to one dataset's mutation events.
+    pub fn resume_dataset(
+        &self,
+        dataset_id: Uuid,
+        last_event_id: u64,
+    ) -> Option<(Vec<BusEvent>, DatasetSubscription)> {
+        let dataset_id = dataset_id.to_string();
+        let (missed, rx) = self.resume(last_event_id)?;
+        let missed = missed
+            .into_iter()
+            .filter(|e| e.dataset_id() == Some(dataset_id.as_str()))
+            .collect();
+        Some((
+            missed,
+            DatasetSubscription {
+                dataset_id,
+                rx,
+                lagged: self.lagged.clone(),
+            },
+        ))
+    }
+
     /// Number of active subscribers.
     pub fn subscriber_count(&self) -> usize {
         self.sender.receiver_count()
     }
+
+    /// How many times a dataset subscriber fell behind and missed events.
+    pub fn lagged_count(&self) -> u64 {
+        self.lagged.load(Ordering::Relaxed)
+    }
 }
 
 impl Default for EventBus {
@@ -47,6 +201,60 @@ impl Default for EventBus {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::events::types::{MutationEvent, Transition};
+    use chrono::Utc;
+    use futures::FutureExt;
+
+    fn mutation(dataset_id: Uuid, document_id: &str) -> ContentLakeEvent {
+        ContentLakeEvent::Mutation(Box::new(MutationEvent {
+            dataset_id: dataset_id.to_string(),
+            document_id: document_id.into(),
+            transaction_id: "tx".into(),
+            previous_rev: None,
+            result_rev: "tx".into(),
+            transition: Transition::Appear,
+            timestamp: Utc::now(),
+            effects: None,
+            result: None,
+            previous: None,
+            transaction_total_events: 1,
+            transaction_current_event: 1,
+        }))
+    }
+
+    #[tokio::test]
+    async fn dataset_subscribers_only_see_their_dataset() {
+        let bus = EventBus::new(16);
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut rx_a = bus.subscribe_dataset(a);
+        let mut rx_b = bus.subscribe_dataset(b);
+
+        for (dataset, doc) in [(a, "a1"), (b, "b1"), (b, "b2"), (a, "a2")] {
+            bus.publish(mutation(dataset, doc)).unwrap();
+        }
+        bus.publish(ContentLakeEvent::Welcome).unwrap();
+        bus.publish(mutation(a, "end")).unwrap();
+        bus.publish(mutation(b, "end")).unwrap();
+
+        // Everything has been published, so only ready events are read.
+        fn received(rx: &mut DatasetSubscription) -> Vec<String> {
+            let mut docs = Vec::new();
+            while let Some(Ok(event)) = rx.recv().now_or_never() {
+                if let ContentLakeEvent::Mutation(m) = event.event {
+                    docs.push(m.document_id);
+                }
+            }
+            docs
+        }
+        assert_eq!(received(&mut rx_a), vec!["a1", "a2", "end"]);
+        assert_eq!(received(&mut rx_b), vec!["b1", "b2", "end"]);
+
+        let (missed, _) = bus.resume_dataset(b, 1).unwrap();
+        assert_eq!(
+            missed.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![2, 3, 7]
+        );
+    }
 
     #[tokio::test]
     async fn publish_and_receive() {
@@ -56,7 +264,8 @@ mod tests {
         bus.publish(ContentLakeEvent::Welcome).unwrap();
 
         let event = rx.recv().await.unwrap();
-        assert!(matches!(event, ContentLakeEvent::Welcome));
+        assert_eq!(event.id, 1);
+        assert!(matches!(event.event, ContentLakeEvent::Welcome));
     }
 
     #[tokio::test]
@@ -70,12 +279,42 @@ mod tests {
         bus.publish(ContentLakeEvent::Reconnect).unwrap();
 
         assert!(matches!(
-            rx1.recv().await.unwrap(),
+            rx1.recv().await.unwrap().event,
             ContentLakeEvent::Reconnect
         ));
         assert!(matches!(
-            rx2.recv().await.unwrap(),
+            rx2.recv().await.unwrap().event,
             ContentLakeEvent::Reconnect
         ));
     }
+
+    #[tokio::test]
+    async fn publish_lossy_counts_missing_subscribers_as_zero() {
+        let bus = EventBus::new(4);
+        assert_eq!(bus.publish_lossy(ContentLakeEvent::Welcome), 0);
+        let mut rx = bus.subscribe();
+        assert_eq!(bus.publish_lossy(ContentLakeEvent::Reconnect), 1);
+        assert_eq!(rx.recv().await.unwrap().id, 2);
+        let (missed, _) = bus.resume(0).unwrap();
+        assert_eq!(missed.len(), 2, "unheard events are still replayable");
+    }
+
+    #[tokio::test]
+    async fn resume_replays_missed_events_then_goes_live() {
+        let bus = EventBus::new(3);
+        assert!(bus.resume(0).is_some());
+        for _ in 0..4 {
+            bus.publish(ContentLakeEvent::Welcome).unwrap_err();
+        }
+
+        // Events 2..=4 are in the window; event 1 has been evicted.
+        assert!(bus.resume(1).is_some());
+        assert!(bus.resume(0).is_none(), "event 1 is gone");
+        assert!(bus.resume(5).is_none(), "never issued");
+
+        let (missed, mut rx) = bus.resume(2).unwrap();
+        assert_eq!(missed.iter().map(|e| e.id).collect::<Vec<_>>(), vec![3, 4]);
+        bus.publish(ContentLakeEvent::Reconnect).unwrap();
+        assert_eq!(rx.recv().await.unwrap().id, 5);
+    }
 }
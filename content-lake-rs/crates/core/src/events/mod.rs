@@ -1,2 +1,3 @@
 pub mod bus;
+pub mod presence;
 pub mod types;
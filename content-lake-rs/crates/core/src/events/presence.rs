@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+/// One participant's presence on a document: whatever cursor and selection
+/// state its client last announced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Participant {
+    pub session_id: String,
+    pub state: Value,
+}
+
+/// Presence updates fanned out to the participants of a document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PresenceEvent {
+    /// Everyone who has announced a state. Sent on joining, and again after a
+    /// participant fell behind and missed updates.
+    Snapshot {
+        participants: Vec<Participant>,
+    },
+    Update(Participant),
+    Leave {
+        #[serde(rename = "sessionId")]
+        session_id: String,
+    },
+}
+
+type RoomKey = (Uuid, String);
+
+/// The participants of one document and their fan-out channel.
+#[derive(Debug)]
+struct Room {
+    sender: broadcast::Sender<PresenceEvent>,
+    states: HashMap<String, Value>,
+    sessions: usize,
+}
+
+/// In-memory presence channels, one per document.
+///
+/// Separate from the [`EventBus`](super::bus::EventBus): presence is
+/// ephemeral, so nothing is numbered, logged or replayed, and a room is
+/// dropped once its last session leaves.
+#[derive(Debug, Clone)]
+pub struct PresenceHub {
+    rooms: Arc<Mutex<HashMap<RoomKey, Room>>>,
+    capacity: usize,
+}
+
+impl PresenceHub {
+    /// Create a hub whose per-document channels buffer `capacity` updates.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            rooms: Arc::new(Mutex::new(HashMap::new())),
+            capacity,
+        }
+    }
+
+    /// Join a document's presence channel under a fresh session id. The
+    /// session's state is evicted when it is dropped.
+    pub fn join(&self, dataset_id: Uuid, document_id: &str) -> PresenceSession {
+        let key = (dataset_id, document_id.to_string());
+        let mut rooms = self.rooms.lock().expect("presence lock poisoned");
+        let room = rooms.entry(key.clone()).or_insert_with(|| Room {
+            sender: broadcast::channel(self.capacity).0,
+            states: HashMap::new(),
+            sessions: 0,
+        });
+        room.sessions += 1;
+        PresenceSession {
+            hub: self.clone(),
+            key,
+            session_id: Uuid::new_v4().to_string(),
+            rx: room.sender.subscribe(),
+        }
+    }
+
+    /// Number of documents that currently have participants.
+    pub fn room_count(&self) -> usize {
+        self.rooms.lock().expect("presence lock poisoned").len()
+    }
+
+    fn snapshot(&self, key: &RoomKey) -> PresenceEvent {
+        let rooms = self.rooms.lock().expect("presence lock poisoned");
+        let mut participants: Vec<_> = rooms
+            .get(key)
+            .map(|room| {
+                room.states
+                    .iter()
+                    .map(|(session_id, state)| Participant {
+                        session_id: session_id.clone(),
+                        state: state.clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        participants.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        PresenceEvent::Snapshot { participants }
+    }
+}
+
+/// A participant's membership of one document's presence channel.
+#[derive(Debug)]
+pub struct PresenceSession {
+    hub: PresenceHub,
+    key: RoomKey,
+    session_id: String,
+    rx: broadcast::Receiver<PresenceEvent>,
+}
+
+impl PresenceSession {
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// The states announced so far by everyone on the document.
+    pub fn snapshot(&self) -> PresenceEvent {
+        self.hub.snapshot(&self.key)
+    }
+
+    /// Record this session's state and send it to the other participants.
+    pub fn update(&self, state: Value) {
+        let mut rooms = self.hub.rooms.lock().expect("presence lock poisoned");
+        let Some(room) = rooms.get_mut(&self.key) else {
+            return;
+        };
+        room.states.insert(self.session_id.clone(), state.clone());
+        let _ = room.sender.send(PresenceEvent::Update(Participant {
+            session_id: self.session_id.clone(),
+            state,
+        }));
+    }
+
+    /// Receive the next update from another participant. A session that
+    /// fell behind gets a fresh [`PresenceEvent::Snapshot`] instead of the
+    /// updates it missed.
+    pub async fn recv(&mut self) -> Option<PresenceEvent> {
+        loop {
+            match self.rx.recv().await {
+                Ok(PresenceEvent::Update(p)) if p.session_id == self.session_id => continue,
+                Ok(event) => return Some(event),
+                Err(RecvError::Lagged(_)) => return Some(self.snapshot()),
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+impl Drop for PresenceSession {
+    fn drop(&mut self) {
+        let mut rooms = self.hub.rooms.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(room) = rooms.get_mut(&self.key) else {
+            return;
+        };
+        room.sessions -= 1;
+        if room.sessions == 0 {
+            rooms.remove(&self.key);
+        } else if room.states.remove(&self.session_id).is_some() {
+            let _ = room.sender.send(PresenceEvent::Leave {
+                session_id: self.session_id.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn fans_out_updates_per_document_and_evicts_on_leave() {
+        let hub = PresenceHub::new(8);
+        let dataset = Uuid::new_v4();
+        let alice = hub.join(dataset, "doc1");
+        let mut bob = hub.join(dataset, "doc1");
+        let mut carol = hub.join(dataset, "doc2");
+
+        alice.update(json!({ "cursor": { "path": "title", "offset": 3 } }));
+        let expected = PresenceEvent::Update(Participant {
+            session_id: alice.session_id().to_string(),
+            state: json!({ "cursor": { "path": "title", "offset": 3 } }),
+        });
+        assert_eq!(bob.recv().await, Some(expected));
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(20), carol.recv())
+                .await
+                .is_err(),
+            "other documents don't see the update"
+        );
+
+        let PresenceEvent::Snapshot { participants } = bob.snapshot() else {
+            panic!("expected a snapshot");
+        };
+        assert_eq!(participants.len(), 1);
+
+        let alice_id = alice.session_id().to_string();
+        drop(alice);
+        assert_eq!(
+            bob.recv().await,
+            Some(PresenceEvent::Leave {
+                session_id: alice_id
+            })
+        );
+        assert_eq!(
+            bob.snapshot(),
+            PresenceEvent::Snapshot {
+                participants: vec![]
+            }
+        );
+
+        assert_eq!(hub.room_count(), 2);
+        drop(bob);
+        drop(carol);
+        assert_eq!(hub.room_count(), 0);
+    }
+
+    #[test]
+    fn serializes_events_with_type_tags() {
+        let event = PresenceEvent::Update(Participant {
+            session_id: "s1".into(),
+            state: json!({ "selection": null }),
+        });
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({ "type": "update", "sessionId": "s1", "state": { "selection": null } })
+        );
+        let leave = PresenceEvent::Leave {
+            session_id: "s1".into(),
+        };
+        assert_eq!(
+            serde_json::to_value(&leave).unwrap(),
+            json!({ "type": "leave", "sessionId": "s1" })
+        );
+    }
+}
@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum ContentLakeEvent {
     Welcome,
-    Mutation(MutationEvent),
+    Mutation(Box<MutationEvent>),
     Reconnect,
 }
 
@@ -18,8 +18,30 @@ pub struct MutationEvent {
     pub transaction_id: String,
     pub previous_rev: Option<String>,
     pub result_rev: String,
+    pub transition: Transition,
     pub timestamp: DateTime<Utc>,
     pub effects: Option<serde_json::Value>,
+    /// The document after the mutation; absent for a deletion.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    /// The document before the mutation. Only used to match listener
+    /// filters against deleted documents; never sent to clients.
+    #[serde(skip)]
+    pub previous: Option<serde_json::Value>,
     pub transaction_total_events: u32,
     pub transaction_current_event: u32,
 }
+
+/// What a mutation did to a document's existence, as in Sanity's listener.
+/// A `disappear` event is a tombstone: the document was deleted at
+/// `result_rev`, and anything that held it (including a query's result
+/// set) should evict it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transition {
+    /// Created, or re-created after being deleted.
+    Appear,
+    Update,
+    /// Deleted.
+    Disappear,
+}
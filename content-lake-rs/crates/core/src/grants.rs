@@ -0,0 +1,80 @@
+//! Read grants: which documents a requester may see.
+//!
+//! A grant is a GROQ filter such as `_type == "post"`. A requester holding
+//! several grants sees a document if any of them matches it; one holding no
+//! grants sees nothing.
+
+use content_lake_groq::ast::Expr;
+use content_lake_groq::eval::eval_filter;
+use content_lake_groq::parser::{parse, ParseError};
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("invalid grant filter {filter:?}: {source}")]
+pub struct GrantError {
+    pub filter: String,
+    #[source]
+    pub source: ParseError,
+}
+
+/// A compiled set of read grants.
+#[derive(Debug, Clone)]
+pub struct Grants {
+    filters: Vec<Expr>,
+}
+
+impl Grants {
+    /// Parse each filter of `filters`.
+    pub fn compile<S: AsRef<str>>(filters: &[S]) -> Result<Self, GrantError> {
+        let filters = filters
+            .iter()
+            .map(|filter| {
+                parse(filter.as_ref()).map_err(|source| GrantError {
+                    filter: filter.as_ref().to_string(),
+                    source,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { filters })
+    }
+
+    /// Whether any grant matches `doc`. A filter that fails to evaluate
+    /// against the document doesn't match it.
+    pub fn allows(&self, doc: &Value) -> bool {
+        let params = Value::Object(Default::default());
+        self.filters
+            .iter()
+            .any(|filter| eval_filter(filter, doc, &params).unwrap_or(false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn ors_the_filters() {
+        let post = json!({"_id": "p1", "_type": "post"});
+        let author = json!({"_id": "a1", "_type": "author", "public": true});
+        let secret = json!({"_id": "s1", "_type": "secret"});
+
+        let posts = Grants::compile(&[r#"_type == "post""#]).unwrap();
+        assert!(posts.allows(&post));
+        assert!(!posts.allows(&author));
+
+        let grants = Grants::compile(&[r#"_type == "post""#, "public == true"]).unwrap();
+        assert!(grants.allows(&post) && grants.allows(&author));
+        assert!(!grants.allows(&secret));
+
+        let none = Grants::compile::<&str>(&[]).unwrap();
+        assert!(!none.allows(&post));
+    }
+
+    #[test]
+    fn rejects_unparseable_filters() {
+        let err = Grants::compile(&["_type ==", r#"_type == "post""#]).unwrap_err();
+        assert_eq!(err.filter, "_type ==");
+    }
+}
@@ -0,0 +1,119 @@
+//! Append-only transaction log backing document revision history.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::mutation::executor::DocumentChange;
+use crate::mutation::types::Mutation;
+
+/// One revision of a document, as recorded by the transaction that produced it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub transaction_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub author: Option<String>,
+    pub previous_rev: Option<String>,
+    pub result_rev: Option<String>,
+}
+
+/// Record a committed transaction and the documents it changed.
+/// Runs on the caller's connection so it commits or rolls back with the mutations.
+pub async fn record_transaction(
+    conn: &mut PgConnection,
+    dataset_id: Uuid,
+    transaction_id: &str,
+    mutations: &[Mutation],
+    changes: &[DocumentChange],
+    timestamp: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let mutations = serde_json::to_value(mutations).expect("mutations serialize to JSON");
+    let id: Uuid = sqlx::query_scalar(
+        "INSERT INTO transactions (dataset_id, transaction_id, mutations, timestamp) \
+         VALUES ($1, $2, $3, $4) RETURNING id",
+    )
+    .bind(dataset_id)
+    .bind(transaction_id)
+    .bind(mutations)
+    .bind(timestamp)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    for change in changes {
+        sqlx::query(
+            "INSERT INTO transaction_documents (transaction_id, document_id, previous_rev, result_rev) \
+             VALUES ($1, $2, $3, $4)",
+        )
+        .bind(id)
+        .bind(&change.document_id)
+        .bind(&change.previous_rev)
+        .bind(&change.result_rev)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// All recorded revisions of a document, oldest first.
+pub async fn document_history(
+    pool: &PgPool,
+    dataset_id: Uuid,
+    document_id: &str,
+) -> Result<Vec<HistoryEntry>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT t.transaction_id, t.timestamp, t.author, d.previous_rev, d.result_rev \
+         FROM transaction_documents d JOIN transactions t ON t.id = d.transaction_id \
+         WHERE t.dataset_id = $1 AND d.document_id = $2 \
+         ORDER BY t.seq",
+    )
+    .bind(dataset_id)
+    .bind(document_id)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mutation::executor::apply_transaction;
+    use crate::store::PgDocumentStore;
+    use crate::test_support::{create_dataset, test_pool};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn records_revisions_in_order() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let dataset_id = create_dataset(&pool).await;
+        let store = PgDocumentStore::new(pool.clone());
+
+        for mutations in [
+            json!([{"create": {"_id": "a", "_type": "post", "title": "v1"}}]),
+            json!([{"patch": {"id": "a", "set": {"title": "v2"}}}]),
+        ] {
+            let mutations: Vec<Mutation> = serde_json::from_value(mutations).unwrap();
+            apply_transaction(&store, dataset_id, &mutations, Default::default())
+                .await
+                .unwrap();
+        }
+
+        let history = document_history(&pool, dataset_id, "a").await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].previous_rev, None);
+        assert_eq!(
+            history[0].result_rev.as_deref(),
+            Some(history[0].transaction_id.as_str())
+        );
+        assert_eq!(history[1].previous_rev, history[0].result_rev);
+        assert!(history[0].timestamp <= history[1].timestamp);
+
+        let other = create_dataset(&pool).await;
+        assert!(document_history(&pool, other, "a")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,372 @@
+//! JSONMatch paths, as used by patch operations.
+//!
+//! A path is a key followed by any number of `.key` and bracket segments:
+//! - `a.b.c` — object keys
+//! - `arr[2]`, `arr[-1]` — array indices, negative counting from the end
+//! - `arr[_key=="x"]` — the array item whose `_key` is `x`
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(i64),
+    KeyedItem(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PathError {
+    #[error("path is empty")]
+    Empty,
+    #[error("unbalanced brackets in {0}")]
+    UnbalancedBrackets(String),
+    #[error("invalid segment {segment:?} in {path}")]
+    InvalidSegment { path: String, segment: String },
+    #[error("type mismatch at {path}: expected {expected}")]
+    TypeMismatch {
+        path: String,
+        expected: &'static str,
+    },
+    #[error("path not found: {0}")]
+    NotFound(String),
+}
+
+/// Parse a JSONMatch path into its segments.
+pub fn parse_path(path: &str) -> Result<Vec<PathSegment>, PathError> {
+    if path.trim().is_empty() {
+        return Err(PathError::Empty);
+    }
+    let unbalanced = || PathError::UnbalancedBrackets(path.to_string());
+    let invalid = |segment: &str| PathError::InvalidSegment {
+        path: path.to_string(),
+        segment: segment.to_string(),
+    };
+
+    let mut segments = Vec::new();
+    let mut rest = path;
+    let mut expect_key = true;
+    loop {
+        if expect_key {
+            let end = rest.find(['.', '[', ']']).unwrap_or(rest.len());
+            let key = &rest[..end];
+            if !is_key(key) {
+                return Err(invalid(key));
+            }
+            segments.push(PathSegment::Key(key.to_string()));
+            rest = &rest[end..];
+            expect_key = false;
+        } else if let Some(after) = rest.strip_prefix('.') {
+            rest = after;
+            expect_key = true;
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let close = closing_bracket(after).ok_or_else(unbalanced)?;
+            let inner = &after[..close];
+            segments.push(bracket(inner).ok_or_else(|| invalid(inner))?);
+            rest = &after[close + 1..];
+        } else if rest.is_empty() {
+            return Ok(segments);
+        } else {
+            return Err(unbalanced());
+        }
+    }
+}
+
+/// Whether `key` can be written as a path segment without quoting.
+pub(crate) fn is_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Offset of the `]` closing a bracket whose contents start `s`, skipping
+/// over quoted strings. `None` if it is unclosed or another `[` opens first.
+fn closing_bracket(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, ']') => return Some(i),
+            (None, '[') => return None,
+            (None, _) => {}
+        }
+    }
+    None
+}
+
+fn bracket(inner: &str) -> Option<PathSegment> {
+    let inner = inner.trim();
+    if let Ok(index) = inner.parse::<i64>() {
+        return Some(PathSegment::Index(index));
+    }
+    let quoted = inner
+        .strip_prefix("_key")?
+        .trim_start()
+        .strip_prefix("==")?
+        .trim_start();
+    let quote = quoted.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+    let key = quoted[1..].strip_suffix(quote)?;
+    (!key.is_empty() && !key.contains(quote)).then(|| PathSegment::KeyedItem(key.to_string()))
+}
+
+/// Render segments back into path syntax, for error messages.
+pub fn render(segments: &[PathSegment]) -> String {
+    Rendered(segments).to_string()
+}
+
+struct Rendered<'a>(&'a [PathSegment]);
+
+impl fmt::Display for Rendered<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Key(key) if i == 0 => write!(f, "{key}")?,
+                PathSegment::Key(key) => write!(f, ".{key}")?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+                PathSegment::KeyedItem(key) => write!(f, "[_key==\"{key}\"]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Position in `array` addressed by an index or keyed-item segment.
+pub fn position(array: &[Value], segment: &PathSegment) -> Option<usize> {
+    match segment {
+        PathSegment::Key(_) => None,
+        PathSegment::Index(index) => {
+            let len = array.len() as i64;
+            let resolved = if *index < 0 { len + index } else { *index };
+            (0..len).contains(&resolved).then_some(resolved as usize)
+        }
+        PathSegment::KeyedItem(key) => array
+            .iter()
+            .position(|item| item.get("_key").and_then(Value::as_str) == Some(key)),
+    }
+}
+
+/// The value at `segments`, if every step exists.
+pub fn resolve<'a>(doc: &'a Value, segments: &[PathSegment]) -> Option<&'a Value> {
+    segments
+        .iter()
+        .try_fold(doc, |current, segment| match (segment, current) {
+            (PathSegment::Key(key), _) => current.get(key),
+            (_, Value::Array(arr)) => arr.get(position(arr, segment)?),
+            _ => None,
+        })
+}
+
+/// Mutable counterpart of [`resolve`].
+pub fn resolve_mut<'a>(doc: &'a mut Value, segments: &[PathSegment]) -> Option<&'a mut Value> {
+    segments
+        .iter()
+        .try_fold(doc, |current, segment| match (segment, current) {
+            (PathSegment::Key(key), current) => current.get_mut(key),
+            (_, Value::Array(arr)) => {
+                let idx = position(arr, segment)?;
+                arr.get_mut(idx)
+            }
+            _ => None,
+        })
+}
+
+/// Write `value` at `segments`, creating objects for missing keys along the
+/// way. Array items must already exist.
+pub fn apply(doc: &mut Value, segments: &[PathSegment], value: Value) -> Result<(), PathError> {
+    let mismatch = |expected| PathError::TypeMismatch {
+        path: render(segments),
+        expected,
+    };
+    let mut current = doc;
+    for segment in segments {
+        current = match segment {
+            PathSegment::Key(key) => {
+                if current.is_null() {
+                    *current = Value::Object(Map::new());
+                }
+                let map = current.as_object_mut().ok_or_else(|| mismatch("object"))?;
+                map.entry(key.clone()).or_insert(Value::Null)
+            }
+            _ => {
+                let arr = current.as_array_mut().ok_or_else(|| mismatch("array"))?;
+                let idx =
+                    position(arr, segment).ok_or_else(|| PathError::NotFound(render(segments)))?;
+                &mut arr[idx]
+            }
+        };
+    }
+    *current = value;
+    Ok(())
+}
+
+/// Remove and return the value at `segments`; missing paths are a no-op.
+pub fn remove(doc: &mut Value, segments: &[PathSegment]) -> Option<Value> {
+    let (last, parent_path) = segments.split_last()?;
+    match (last, resolve_mut(doc, parent_path)?) {
+        (PathSegment::Key(key), Value::Object(map)) => map.remove(key),
+        (PathSegment::Key(_), _) => None,
+        (_, Value::Array(arr)) => {
+            let idx = position(arr, last)?;
+            Some(arr.remove(idx))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn key(k: &str) -> PathSegment {
+        PathSegment::Key(k.into())
+    }
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!(parse_path("title").unwrap(), vec![key("title")]);
+        assert_eq!(
+            parse_path("a.b_c.d-e").unwrap(),
+            vec![key("a"), key("b_c"), key("d-e")]
+        );
+    }
+
+    #[test]
+    fn parses_indices() {
+        assert_eq!(
+            parse_path("arr[2]").unwrap(),
+            vec![key("arr"), PathSegment::Index(2)]
+        );
+        assert_eq!(
+            parse_path("arr[ -1 ][0].x").unwrap(),
+            vec![
+                key("arr"),
+                PathSegment::Index(-1),
+                PathSegment::Index(0),
+                key("x")
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_keyed_items() {
+        assert_eq!(
+            parse_path(r#"body[_key=="a1"].text"#).unwrap(),
+            vec![
+                key("body"),
+                PathSegment::KeyedItem("a1".into()),
+                key("text")
+            ]
+        );
+        assert_eq!(
+            parse_path("body[_key == 'x]y']").unwrap(),
+            vec![key("body"), PathSegment::KeyedItem("x]y".into())]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        assert_eq!(parse_path(""), Err(PathError::Empty));
+        for path in ["a[", "a[0", "a]", "a[0]]", "a[[0]]", r#"a[_key=="x]"#] {
+            assert_eq!(
+                parse_path(path),
+                Err(PathError::UnbalancedBrackets(path.into())),
+                "{path}"
+            );
+        }
+        for (path, segment) in [
+            ("a.", ""),
+            (".a", ""),
+            ("a..b", ""),
+            ("[0]", ""),
+            ("a b", "a b"),
+            ("a[x]", "x"),
+            ("a[]", ""),
+            ("a[_id==\"x\"]", "_id==\"x\""),
+            ("a[_key==x]", "_key==x"),
+            ("a[_key==\"\"]", "_key==\"\""),
+        ] {
+            assert_eq!(
+                parse_path(path),
+                Err(PathError::InvalidSegment {
+                    path: path.into(),
+                    segment: segment.into()
+                }),
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn renders_round_trip() {
+        let path = r#"a.b[-1][_key=="k"].c"#;
+        assert_eq!(render(&parse_path(path).unwrap()), path);
+    }
+
+    #[test]
+    fn resolves_each_segment_type() {
+        let doc = json!({"a": {"b": [1, {"_key": "k", "c": 2}, 3]}});
+        let at = |path| resolve(&doc, &parse_path(path).unwrap()).cloned();
+        assert_eq!(at("a.b[0]"), Some(json!(1)));
+        assert_eq!(at("a.b[-1]"), Some(json!(3)));
+        assert_eq!(at(r#"a.b[_key=="k"].c"#), Some(json!(2)));
+        assert_eq!(at("a.b[3]"), None);
+        assert_eq!(at("a.b[-4]"), None);
+        assert_eq!(at(r#"a.b[_key=="missing"]"#), None);
+        assert_eq!(at("a[0]"), None);
+        assert_eq!(at("a.b.c"), None);
+    }
+
+    #[test]
+    fn apply_creates_objects_and_writes_items() {
+        let mut doc = json!({"items": [{"_key": "k", "n": 1}]});
+        apply(&mut doc, &parse_path("meta.views").unwrap(), json!(3)).unwrap();
+        apply(
+            &mut doc,
+            &parse_path(r#"items[_key=="k"].n"#).unwrap(),
+            json!(2),
+        )
+        .unwrap();
+        apply(&mut doc, &parse_path("items[-1].m").unwrap(), json!(true)).unwrap();
+        assert_eq!(
+            doc,
+            json!({"meta": {"views": 3}, "items": [{"_key": "k", "n": 2, "m": true}]})
+        );
+
+        let err = apply(&mut doc, &parse_path("items[1]").unwrap(), json!(0)).unwrap_err();
+        assert_eq!(err, PathError::NotFound("items[1]".into()));
+        let err = apply(&mut doc, &parse_path("meta.views.x").unwrap(), json!(0)).unwrap_err();
+        assert_eq!(
+            err,
+            PathError::TypeMismatch {
+                path: "meta.views.x".into(),
+                expected: "object"
+            }
+        );
+        let err = apply(&mut doc, &parse_path("meta[0]").unwrap(), json!(0)).unwrap_err();
+        assert!(matches!(
+            err,
+            PathError::TypeMismatch {
+                expected: "array",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn remove_by_key_index_and_keyed_item() {
+        let mut doc = json!({"a": 1, "list": [{"_key": "x"}, {"_key": "y"}, 3]});
+        let mut remove_at = |path| remove(&mut doc, &parse_path(path).unwrap());
+        assert_eq!(remove_at("a"), Some(json!(1)));
+        assert_eq!(remove_at(r#"list[_key=="y"]"#), Some(json!({"_key": "y"})));
+        assert_eq!(remove_at("list[-1]"), Some(json!(3)));
+        assert_eq!(remove_at("list[5]"), None);
+        assert_eq!(remove_at("missing.b"), None);
+        assert_eq!(doc, json!({"list": [{"_key": "x"}]}));
+    }
+}
@@ -1,3 +1,11 @@
+pub mod assets;
 pub mod document;
 pub mod events;
+pub mod grants;
+pub mod history;
+pub mod jsonmatch;
 pub mod mutation;
+pub mod store;
+
+#[cfg(test)]
+mod test_support;
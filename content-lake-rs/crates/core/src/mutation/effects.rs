@@ -0,0 +1,162 @@
+/// Effects of a mutation: the patches between a document's previous and
+/// resulting content, sent with its mutation event.
+///
+/// `apply` turns the previous content into the result and `revert` turns it
+/// back, both as [`PatchOperations`] so clients can feed them through the
+/// same `set`/`unset` handling as any other patch. Objects are diffed key by
+/// key; a changed array or scalar is set whole.
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::types::PatchOperations;
+use crate::document::model::content_without_system_fields;
+use crate::jsonmatch::is_key;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Effects {
+    pub apply: PatchOperations,
+    pub revert: PatchOperations,
+}
+
+/// The effects of changing `previous` into `result`, either of which is
+/// `None` if the document didn't exist. System fields are ignored. `None` if
+/// the change can't be expressed as patches, i.e. it touches a top-level
+/// field whose name can't be written as a path.
+pub fn effects(previous: Option<&Value>, result: Option<&Value>) -> Option<Effects> {
+    let content = |doc: Option<&Value>| match doc.map(content_without_system_fields) {
+        Some(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+    let (previous, result) = (content(previous), content(result));
+    Some(Effects {
+        apply: diff(&previous, &result)?,
+        revert: diff(&result, &previous)?,
+    })
+}
+
+/// The `set` and `unset` operations turning `from` into `to`, if there are
+/// any that do.
+fn diff(from: &Map<String, Value>, to: &Map<String, Value>) -> Option<PatchOperations> {
+    let mut set = Map::new();
+    let mut unset = Vec::new();
+    diff_objects(None, from, to, &mut set, &mut unset).then(|| PatchOperations {
+        set: (!set.is_empty()).then_some(Value::Object(set)),
+        unset: (!unset.is_empty()).then_some(unset),
+        ..Default::default()
+    })
+}
+
+/// Diff the objects at `path`, or the document itself if `None`. Fields
+/// whose names can't be written as a path are changed by setting the whole
+/// object; at the top level there is no such object, so this returns false.
+fn diff_objects(
+    path: Option<&str>,
+    from: &Map<String, Value>,
+    to: &Map<String, Value>,
+    set: &mut Map<String, Value>,
+    unset: &mut Vec<String>,
+) -> bool {
+    let changed = |key: &String| from.get(key) != to.get(key);
+    if from
+        .keys()
+        .chain(to.keys())
+        .any(|k| !is_key(k) && changed(k))
+    {
+        let Some(path) = path else {
+            return false;
+        };
+        set.insert(path.to_string(), Value::Object(to.clone()));
+        return true;
+    }
+    let child = |key: &str| match path {
+        Some(path) => format!("{path}.{key}"),
+        None => key.to_string(),
+    };
+    for key in from.keys().filter(|k| !to.contains_key(*k)) {
+        unset.push(child(key));
+    }
+    for (key, value) in to.iter().filter(|(k, _)| changed(k)) {
+        match (from.get(key), value) {
+            (Some(Value::Object(before)), Value::Object(after)) => {
+                diff_objects(Some(&child(key)), before, after, set, unset);
+            }
+            _ => {
+                set.insert(child(key), value.clone());
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mutation::patch::apply_patch;
+    use serde_json::json;
+
+    #[test]
+    fn describes_changed_added_and_removed_fields() {
+        let before = json!({
+            "_id": "p1", "_rev": "r1", "title": "A", "meta": {"views": 1, "old": true},
+            "tags": ["a"],
+        });
+        let after = json!({
+            "_id": "p1", "_rev": "r2", "title": "B", "meta": {"views": 2},
+            "tags": ["a", "b"], "extra": null,
+        });
+        let effects = effects(Some(&before), Some(&after)).unwrap();
+        assert_eq!(
+            serde_json::to_value(&effects).unwrap(),
+            json!({
+                "apply": {
+                    "set": {"title": "B", "meta.views": 2, "tags": ["a", "b"], "extra": null},
+                    "unset": ["meta.old"],
+                },
+                "revert": {
+                    "set": {"title": "A", "meta.views": 1, "meta.old": true, "tags": ["a"]},
+                    "unset": ["extra"],
+                },
+            })
+        );
+
+        let content = |doc: &Value| content_without_system_fields(doc);
+        let mut doc = content(&before);
+        apply_patch(&mut doc, &effects.apply).unwrap();
+        assert_eq!(doc, content(&after));
+        apply_patch(&mut doc, &effects.revert).unwrap();
+        assert_eq!(doc, content(&before));
+    }
+
+    #[test]
+    fn creation_and_deletion_diff_against_nothing() {
+        let doc = json!({"_id": "p1", "_type": "post", "title": "A"});
+        let created = effects(None, Some(&doc)).unwrap();
+        assert_eq!(created.apply.set, Some(json!({"title": "A"})));
+        assert_eq!(created.revert.unset, Some(vec!["title".to_string()]));
+        assert_eq!(effects(Some(&doc), None).unwrap().apply, created.revert);
+    }
+
+    #[test]
+    fn sets_objects_with_unpathable_keys_whole() {
+        let before = json!({"labels": {"en-US": "Hi", "fr FR": "Salut"}});
+        let after = json!({"labels": {"en-US": "Hi", "fr FR": "Bonjour"}});
+        let effects = effects(Some(&before), Some(&after)).unwrap();
+        assert_eq!(
+            effects.apply.set,
+            Some(json!({"labels": {"en-US": "Hi", "fr FR": "Bonjour"}}))
+        );
+    }
+
+    #[test]
+    fn unpathable_top_level_changes_have_no_effects() {
+        let before = json!({"title": "A", "fr FR": "Salut"});
+        let after = json!({"title": "B", "fr FR": "Bonjour"});
+        assert_eq!(effects(Some(&before), Some(&after)), None);
+        assert_eq!(effects(None, Some(&after)), None);
+
+        // Left untouched, such a field doesn't get in the way.
+        let after = json!({"title": "B", "fr FR": "Salut"});
+        let effects = effects(Some(&before), Some(&after)).unwrap();
+        assert_eq!(effects.apply.set, Some(json!({"title": "B"})));
+    }
+}
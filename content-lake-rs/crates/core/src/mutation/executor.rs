@@ -0,0 +1,1061 @@
+//! Mutation executor.
+//!
+//! Applies a transaction of mutations to a dataset inside a single SQL
+//! transaction and returns one `MutationEvent` per touched document, to be
+//! published once the transaction has committed.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use content_lake_groq::ast::Expr;
+use content_lake_groq::eval::eval_filter;
+use content_lake_groq::functions::referenced_ids;
+use content_lake_groq::params::bind;
+use content_lake_groq::parser::parse;
+use serde_json::Value;
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::effects::effects;
+use super::patch::{apply_patch, PatchError};
+use super::types::{DeleteTarget, Mutation, MutationResponse, MutationResult};
+use crate::document::model::{content_without_system_fields, DocumentRow};
+use crate::document::validate::{validate_document_fields, validate_unique_keys, ValidationError};
+use crate::events::bus::EventBus;
+use crate::events::types::{ContentLakeEvent, MutationEvent, Transition};
+use crate::store::{DocumentStore, DocumentTransaction};
+
+#[derive(Debug, Error)]
+pub enum MutationError {
+    #[error("document already exists: {0}")]
+    AlreadyExists(String),
+    #[error("transaction id already used: {0}")]
+    DuplicateTransaction(String),
+    #[error("document not found: {0}")]
+    NotFound(String),
+    #[error("revision mismatch for {id}: expected {expected}, found {found}")]
+    RevisionMismatch {
+        id: String,
+        expected: String,
+        found: String,
+    },
+    #[error("invalid document: {0}")]
+    ValidationFailed(#[from] ValidationError),
+    #[error("patch failed: {0}")]
+    PatchFailed(#[from] PatchError),
+    #[error("invalid query: {0}")]
+    InvalidQuery(String),
+    #[error("document {id} is referenced by {}", by.join(", "))]
+    StillReferenced { id: String, by: Vec<String> },
+    #[error("document {id} is {size} bytes, over the limit of {limit}")]
+    DocumentTooLarge {
+        id: String,
+        size: usize,
+        limit: usize,
+    },
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// A document written by a committed transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentChange {
+    pub document_id: String,
+    pub previous_rev: Option<String>,
+    pub result_rev: String,
+    pub transition: Transition,
+    /// The document before the transaction, if it existed.
+    pub previous: Option<Value>,
+    /// The document as written, or `None` if it was deleted.
+    pub result: Option<Value>,
+}
+
+/// A committed transaction and the events it produced.
+///
+/// The events are only built once the SQL transaction has committed, so a
+/// rolled-back transaction never has anything to publish.
+#[derive(Debug)]
+pub struct CommittedTransaction {
+    pub response: MutationResponse,
+    pub events: Vec<MutationEvent>,
+}
+
+impl CommittedTransaction {
+    /// Publish the transaction's events and return the response.
+    pub fn publish(self, events: &EventBus) -> MutationResponse {
+        publish_events(self.events, events);
+        self.response
+    }
+}
+
+/// Publish a committed transaction's events in order.
+pub fn publish_events(events: Vec<MutationEvent>, bus: &EventBus) {
+    for event in events {
+        bus.publish_lossy(ContentLakeEvent::Mutation(Box::new(event)));
+    }
+}
+
+/// Settings for a single transaction.
+#[derive(Debug, Clone, Default)]
+pub struct TransactionOptions {
+    /// Id to record the transaction under; generated when `None`.
+    pub transaction_id: Option<String>,
+    /// Largest serialized size, in bytes, of any document the transaction
+    /// writes, excluding system fields. Checked against the final documents,
+    /// after patches apply.
+    pub max_document_bytes: Option<usize>,
+    /// Delete documents even when other documents still reference them.
+    pub purge: bool,
+}
+
+/// Generate a random identifier for transactions and revisions.
+pub fn new_transaction_id() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// Apply `mutations` atomically to the dataset and record the transaction in
+/// the history log. Callers publish the returned events with
+/// [`CommittedTransaction::publish`].
+///
+/// Every document written by the transaction receives the transaction id as
+/// its new `_rev`.
+pub async fn apply_transaction<S: DocumentStore>(
+    store: &S,
+    dataset_id: Uuid,
+    mutations: &[Mutation],
+    options: TransactionOptions,
+) -> Result<CommittedTransaction, MutationError> {
+    let transaction_id = options
+        .transaction_id
+        .clone()
+        .unwrap_or_else(new_transaction_id);
+    let now = Utc::now();
+
+    let mut tx = store.begin().await?;
+    let (results, changes) = run(
+        &mut tx,
+        dataset_id,
+        mutations,
+        &transaction_id,
+        &options,
+        now,
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(CommittedTransaction {
+        events: transaction_events(dataset_id, &transaction_id, &changes, now),
+        response: MutationResponse {
+            transaction_id,
+            results,
+        },
+    })
+}
+
+/// Run `mutations` exactly as [`apply_transaction`] would, then roll the
+/// SQL transaction back. The response shows what the transaction would
+/// return; nothing is written and there are no events to publish.
+pub async fn dry_run_transaction<S: DocumentStore>(
+    store: &S,
+    dataset_id: Uuid,
+    mutations: &[Mutation],
+    options: TransactionOptions,
+) -> Result<MutationResponse, MutationError> {
+    let transaction_id = options
+        .transaction_id
+        .clone()
+        .unwrap_or_else(new_transaction_id);
+    let now = Utc::now();
+
+    let mut tx = store.begin().await?;
+    let (results, _) = run(
+        &mut tx,
+        dataset_id,
+        mutations,
+        &transaction_id,
+        &options,
+        now,
+    )
+    .await?;
+    tx.rollback().await?;
+
+    Ok(MutationResponse {
+        transaction_id,
+        results,
+    })
+}
+
+/// Apply `mutations` and record them in the history log within `tx`,
+/// leaving the caller to commit or roll back.
+async fn run<T: DocumentTransaction>(
+    tx: &mut T,
+    dataset_id: Uuid,
+    mutations: &[Mutation],
+    transaction_id: &str,
+    options: &TransactionOptions,
+    now: DateTime<Utc>,
+) -> Result<(Vec<MutationResult>, Vec<DocumentChange>), MutationError> {
+    let mut state = TransactionState::default();
+    let mut results = Vec::new();
+    for mutation in mutations {
+        apply_mutation(tx, dataset_id, mutation, &mut state, &mut results).await?;
+    }
+    if let Some(limit) = options.max_document_bytes {
+        state.check_sizes(limit)?;
+    }
+    if !options.purge {
+        state.check_references(tx, dataset_id).await?;
+    }
+    let changes = state.write(tx, dataset_id, transaction_id, now).await?;
+    tx.record(dataset_id, transaction_id, mutations, &changes, now)
+        .await
+        .map_err(|err| match err.as_database_error() {
+            Some(db) if db.is_unique_violation() => {
+                MutationError::DuplicateTransaction(transaction_id.to_string())
+            }
+            _ => MutationError::Database(err),
+        })?;
+    Ok((results, changes))
+}
+
+/// Build one event per changed document, numbered `1..=N` within the transaction.
+pub fn transaction_events(
+    dataset_id: Uuid,
+    transaction_id: &str,
+    changes: &[DocumentChange],
+    timestamp: DateTime<Utc>,
+) -> Vec<MutationEvent> {
+    let total = changes.len() as u32;
+    changes
+        .iter()
+        .enumerate()
+        .map(|(i, change)| MutationEvent {
+            dataset_id: dataset_id.to_string(),
+            document_id: change.document_id.clone(),
+            transaction_id: transaction_id.to_string(),
+            previous_rev: change.previous_rev.clone(),
+            result_rev: change.result_rev.clone(),
+            transition: change.transition,
+            timestamp,
+            effects: effects(change.previous.as_ref(), change.result.as_ref())
+                .map(|e| serde_json::to_value(e).expect("effects serialize to JSON")),
+            result: change.result.clone(),
+            previous: change.previous.clone(),
+            transaction_total_events: total,
+            transaction_current_event: i as u32 + 1,
+        })
+        .collect()
+}
+
+/// Working copy of a document within the transaction.
+struct Entry {
+    /// Row as loaded from the database, including soft-deleted rows.
+    previous: Option<DocumentRow>,
+    /// Current document, or `None` if absent or deleted.
+    current: Option<Value>,
+    dirty: bool,
+}
+
+#[derive(Default)]
+struct TransactionState {
+    /// Document ids in the order they were first touched.
+    order: Vec<String>,
+    docs: HashMap<String, Entry>,
+}
+
+impl TransactionState {
+    async fn load<T: DocumentTransaction>(
+        &mut self,
+        tx: &mut T,
+        dataset_id: Uuid,
+        id: &str,
+    ) -> Result<&mut Entry, MutationError> {
+        if !self.docs.contains_key(id) {
+            let row = tx.get(dataset_id, id).await?;
+            let current = row
+                .as_ref()
+                .filter(|r| !r.deleted)
+                .map(DocumentRow::to_document);
+            self.order.push(id.to_string());
+            self.docs.insert(
+                id.to_string(),
+                Entry {
+                    previous: row,
+                    current,
+                    dirty: false,
+                },
+            );
+        }
+        Ok(self.docs.get_mut(id).expect("entry loaded above"))
+    }
+
+    /// Reject the transaction if the stored content of any document it writes,
+    /// i.e. without system fields, serializes to more than `limit` bytes.
+    fn check_sizes(&self, limit: usize) -> Result<(), MutationError> {
+        for (id, entry) in &self.docs {
+            let Some(doc) = entry.current.as_ref().filter(|_| entry.dirty) else {
+                continue;
+            };
+            let content = content_without_system_fields(doc);
+            let size = serde_json::to_vec(&content).map_or(0, |bytes| bytes.len());
+            if size > limit {
+                return Err(MutationError::DocumentTooLarge {
+                    id: id.clone(),
+                    size,
+                    limit,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Reject deleting a document that is still strongly referenced once the
+    /// transaction is done, i.e. by a stored document it leaves untouched or
+    /// by a document it writes.
+    async fn check_references<T: DocumentTransaction>(
+        &self,
+        tx: &mut T,
+        dataset_id: Uuid,
+    ) -> Result<(), MutationError> {
+        for id in &self.order {
+            let entry = &self.docs[id];
+            let live_before = entry.previous.as_ref().is_some_and(|r| !r.deleted);
+            if !entry.dirty || entry.current.is_some() || !live_before {
+                continue;
+            }
+            let stored = tx.referencing(dataset_id, id).await?;
+            let written = self.docs.iter().filter(|(_, e)| {
+                e.current
+                    .as_ref()
+                    .is_some_and(|doc| referenced_ids(doc).contains(id))
+            });
+            let mut by: Vec<String> = stored
+                .into_iter()
+                .filter(|other| !self.docs.contains_key(other))
+                .chain(written.map(|(other, _)| other.clone()))
+                .collect();
+            if !by.is_empty() {
+                by.sort();
+                return Err(MutationError::StillReferenced { id: id.clone(), by });
+            }
+        }
+        Ok(())
+    }
+
+    async fn write<T: DocumentTransaction>(
+        self,
+        tx: &mut T,
+        dataset_id: Uuid,
+        transaction_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<DocumentChange>, MutationError> {
+        let mut docs = self.docs;
+        let mut changes = Vec::new();
+
+        for id in self.order {
+            let entry = docs.remove(&id).expect("ordered id has an entry");
+            if !entry.dirty {
+                continue;
+            }
+            let previous = entry.previous.as_ref().filter(|r| !r.deleted);
+
+            let (transition, result) = match &entry.current {
+                Some(doc) => {
+                    // The row as the upsert leaves it; a re-created
+                    // document keeps its original creation time.
+                    let row = DocumentRow {
+                        id: entry.previous.as_ref().map_or_else(Uuid::new_v4, |r| r.id),
+                        dataset_id,
+                        document_id: id.clone(),
+                        doc_type: doc
+                            .get("_type")
+                            .and_then(Value::as_str)
+                            .map(str::to_string)
+                            .or_else(|| previous.map(|r| r.doc_type.clone()))
+                            .unwrap_or_default(),
+                        revision: transaction_id.to_string(),
+                        content: content_without_system_fields(doc),
+                        created_at: entry.previous.as_ref().map_or(now, |r| r.created_at),
+                        updated_at: now,
+                        deleted: false,
+                    };
+                    tx.upsert(
+                        dataset_id,
+                        &id,
+                        &row.doc_type,
+                        transaction_id,
+                        &row.content,
+                        now,
+                    )
+                    .await?;
+                    let transition = if previous.is_some() {
+                        Transition::Update
+                    } else {
+                        Transition::Appear
+                    };
+                    (transition, Some(row.to_document()))
+                }
+                None if previous.is_some() => {
+                    tx.soft_delete(dataset_id, &id, transaction_id, now).await?;
+                    (Transition::Disappear, None)
+                }
+                None => continue,
+            };
+
+            changes.push(DocumentChange {
+                document_id: id,
+                previous_rev: previous.map(|r| r.revision.clone()),
+                result_rev: transaction_id.to_string(),
+                transition,
+                previous: previous.map(DocumentRow::to_document),
+                result,
+            });
+        }
+
+        Ok(changes)
+    }
+}
+
+async fn apply_mutation<T: DocumentTransaction>(
+    tx: &mut T,
+    dataset_id: Uuid,
+    mutation: &Mutation,
+    state: &mut TransactionState,
+    results: &mut Vec<MutationResult>,
+) -> Result<(), MutationError> {
+    match mutation {
+        Mutation::Create(m) => {
+            let (id, doc) = prepare_document(&m.document)?;
+            let entry = state.load(tx, dataset_id, &id).await?;
+            if entry.current.is_some() {
+                return Err(MutationError::AlreadyExists(id));
+            }
+            entry.current = Some(doc);
+            entry.dirty = true;
+            results.push(result(id, "create"));
+        }
+        Mutation::CreateOrReplace(m) => {
+            let (id, doc) = prepare_document(&m.document)?;
+            let entry = state.load(tx, dataset_id, &id).await?;
+            let operation = if entry.current.is_some() {
+                "update"
+            } else {
+                "create"
+            };
+            entry.current = Some(doc);
+            entry.dirty = true;
+            results.push(result(id, operation));
+        }
+        Mutation::CreateIfNotExists(m) => {
+            let (id, doc) = prepare_document(&m.document)?;
+            let entry = state.load(tx, dataset_id, &id).await?;
+            if entry.current.is_none() {
+                entry.current = Some(doc);
+                entry.dirty = true;
+                results.push(result(id, "create"));
+            } else {
+                results.push(result(id, "none"));
+            }
+        }
+        Mutation::Delete(m) => {
+            let ids = match &m.target {
+                DeleteTarget::ById { id } => vec![id.clone()],
+                DeleteTarget::ByQuery { query, params } => {
+                    matching_ids(tx, dataset_id, query, params.as_ref()).await?
+                }
+            };
+            for id in ids {
+                let entry = state.load(tx, dataset_id, &id).await?;
+                if entry.current.take().is_some() {
+                    entry.dirty = true;
+                    results.push(result(id, "delete"));
+                }
+            }
+        }
+        Mutation::Patch(m) => {
+            let entry = state.load(tx, dataset_id, &m.id).await?;
+            let doc = entry
+                .current
+                .as_mut()
+                .ok_or_else(|| MutationError::NotFound(m.id.clone()))?;
+            if let Some(expected) = &m.if_revision_id {
+                let found = doc.get("_rev").and_then(Value::as_str).unwrap_or_default();
+                if found != expected {
+                    return Err(MutationError::RevisionMismatch {
+                        id: m.id.clone(),
+                        expected: expected.clone(),
+                        found: found.to_string(),
+                    });
+                }
+            }
+            apply_patch(doc, &m.operations)?;
+            // Inserts and sets can introduce a `_key` the array already has.
+            validate_unique_keys(doc)?;
+            entry.dirty = true;
+            results.push(result(m.id.clone(), "update"));
+        }
+    }
+    Ok(())
+}
+
+/// Validate a document payload and assign an `_id` if it has none.
+/// An `_id` ending in `.` (e.g. `drafts.`) is used as a prefix for a generated id.
+fn prepare_document(document: &Value) -> Result<(String, Value), MutationError> {
+    let mut doc = document.clone();
+    let map = doc.as_object_mut().ok_or(ValidationError::NotAnObject)?;
+
+    let id = match map.get("_id").and_then(Value::as_str) {
+        None => Uuid::new_v4().to_string(),
+        Some(prefix) if prefix.ends_with('.') => format!("{prefix}{}", Uuid::new_v4()),
+        Some(id) => id.to_string(),
+    };
+    validate_document_fields(Some(&id), map.get("_type").and_then(Value::as_str))?;
+    map.insert("_id".into(), Value::String(id.clone()));
+    validate_unique_keys(&doc)?;
+
+    Ok((id, doc))
+}
+
+/// Ids of live documents in the dataset matching a `*[filter]` delete query.
+async fn matching_ids<T: DocumentTransaction>(
+    tx: &mut T,
+    dataset_id: Uuid,
+    query: &str,
+    params: Option<&Value>,
+) -> Result<Vec<String>, MutationError> {
+    let invalid = |e: &dyn std::fmt::Display| MutationError::InvalidQuery(e.to_string());
+    let empty = Value::Object(serde_json::Map::new());
+    let params = params.unwrap_or(&empty);
+    let expr = parse(query).map_err(|e| invalid(&e))?;
+    let filter = match bind(&expr, params).map_err(|e| invalid(&e))? {
+        Expr::Pipeline(stages) => match stages.as_slice() {
+            [Expr::Everything, Expr::Filter(filter)] => filter.as_ref().clone(),
+            _ => return Err(invalid(&"delete query must be of the form *[filter]")),
+        },
+        _ => return Err(invalid(&"delete query must be of the form *[filter]")),
+    };
+
+    let rows = tx.list_by_type(dataset_id, None).await?;
+    let mut ids = Vec::new();
+    for row in rows {
+        if eval_filter(&filter, &row.to_document(), params).map_err(|e| invalid(&e))? {
+            ids.push(row.document_id);
+        }
+    }
+    Ok(ids)
+}
+
+fn result(id: String, operation: &str) -> MutationResult {
+    MutationResult {
+        id,
+        operation: operation.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::bus::BusEvent;
+    use crate::store::{InMemoryDocumentStore, PgDocumentStore};
+    use crate::test_support::{create_dataset, test_pool};
+    use serde_json::json;
+
+    fn mutations(value: Value) -> Vec<Mutation> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn events_are_numbered_within_transaction() {
+        let changes: Vec<DocumentChange> = ["a", "b", "c"]
+            .iter()
+            .map(|id| DocumentChange {
+                document_id: id.to_string(),
+                previous_rev: None,
+                result_rev: "tx1".into(),
+                transition: Transition::Appear,
+                previous: None,
+                result: None,
+            })
+            .collect();
+        let events = transaction_events(Uuid::new_v4(), "tx1", &changes, Utc::now());
+        let counters: Vec<(u32, u32)> = events
+            .iter()
+            .map(|e| (e.transaction_current_event, e.transaction_total_events))
+            .collect();
+        assert_eq!(counters, vec![(1, 3), (2, 3), (3, 3)]);
+    }
+
+    #[test]
+    fn prepare_document_requires_type() {
+        let err = prepare_document(&json!({"_id": "a"})).unwrap_err();
+        assert!(matches!(
+            err,
+            MutationError::ValidationFailed(ValidationError::MissingType)
+        ));
+    }
+
+    #[test]
+    fn prepare_document_generates_prefixed_id() {
+        let (id, doc) = prepare_document(&json!({"_id": "drafts.", "_type": "post"})).unwrap();
+        assert!(id.starts_with("drafts.") && id.len() > "drafts.".len());
+        assert_eq!(doc["_id"], json!(id));
+    }
+
+    async fn recv_mutation(rx: &mut tokio::sync::broadcast::Receiver<BusEvent>) -> MutationEvent {
+        match rx.recv().await.unwrap().event {
+            ContentLakeEvent::Mutation(event) => *event,
+            other => panic!("expected mutation event, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn publishing_without_listeners_still_returns_the_response() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let dataset_id = create_dataset(&pool).await;
+        let bus = EventBus::new(16);
+        let store = PgDocumentStore::new(pool.clone());
+
+        let response = apply_transaction(
+            &store,
+            dataset_id,
+            &mutations(json!([{"create": {"_id": "a", "_type": "post"}}])),
+            Default::default(),
+        )
+        .await
+        .unwrap()
+        .publish(&bus);
+        assert_eq!(response.results.len(), 1);
+        let (missed, _) = bus.resume(0).unwrap();
+        assert_eq!(missed.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn three_document_transaction_emits_counted_events() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let dataset_id = create_dataset(&pool).await;
+        let bus = EventBus::new(16);
+        let mut rx = bus.subscribe();
+
+        let store = PgDocumentStore::new(pool.clone());
+        let first = apply_transaction(
+            &store,
+            dataset_id,
+            &mutations(json!([
+                {"create": {"_id": "a", "_type": "post", "title": "A"}},
+                {"create": {"_id": "b", "_type": "post", "title": "B"}},
+                {"create": {"_id": "c", "_type": "post", "title": "C"}},
+            ])),
+            Default::default(),
+        )
+        .await
+        .unwrap()
+        .publish(&bus);
+
+        for (i, id) in ["a", "b", "c"].iter().enumerate() {
+            let event = recv_mutation(&mut rx).await;
+            assert_eq!(event.document_id, *id);
+            assert_eq!(event.transaction_id, first.transaction_id);
+            assert_eq!(event.transaction_total_events, 3);
+            assert_eq!(event.transaction_current_event, i as u32 + 1);
+            assert_eq!(event.previous_rev, None);
+            assert_eq!(event.result_rev, first.transaction_id);
+        }
+
+        let second_id = format!("tx-{}", new_transaction_id());
+        let second = apply_transaction(
+            &store,
+            dataset_id,
+            &mutations(json!([
+                {"patch": {"id": "c", "set": {"title": "C2"}}},
+                {"delete": {"id": "b"}},
+                {"patch": {"id": "a", "inc": {"views": 1}}},
+                {"patch": {"id": "c", "set": {"subtitle": "twice"}}},
+            ])),
+            TransactionOptions {
+                transaction_id: Some(second_id.clone()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap()
+        .publish(&bus);
+        assert_eq!(second.transaction_id, second_id);
+
+        // One event per document, in first-touched order, despite `c` being patched twice.
+        for (i, id) in ["c", "b", "a"].iter().enumerate() {
+            let event = recv_mutation(&mut rx).await;
+            assert_eq!(event.document_id, *id);
+            assert_eq!(event.transaction_total_events, 3);
+            assert_eq!(event.transaction_current_event, i as u32 + 1);
+            assert_eq!(
+                event.previous_rev.as_deref(),
+                Some(first.transaction_id.as_str())
+            );
+            assert_eq!(event.result_rev, second_id);
+        }
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn create_on_existing_document_conflicts() {
+        let store = InMemoryDocumentStore::new();
+        let dataset_id = Uuid::new_v4();
+        let create = mutations(json!([{"create": {"_id": "a", "_type": "post"}}]));
+
+        apply_transaction(&store, dataset_id, &create, Default::default())
+            .await
+            .unwrap();
+        let err = apply_transaction(&store, dataset_id, &create, Default::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MutationError::AlreadyExists(id) if id == "a"));
+    }
+
+    #[tokio::test]
+    async fn events_carry_the_document_transition() {
+        let store = InMemoryDocumentStore::new();
+        let dataset_id = Uuid::new_v4();
+        let apply = |value: Value| {
+            let store = store.clone();
+            async move {
+                let events =
+                    apply_transaction(&store, dataset_id, &mutations(value), Default::default())
+                        .await
+                        .unwrap()
+                        .events;
+                events
+                    .into_iter()
+                    .map(|e| (e.transition, e.previous_rev.is_some()))
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        let created = apply(json!([{"create": {"_id": "a", "_type": "post"}}])).await;
+        assert_eq!(created, [(Transition::Appear, false)]);
+        let patched = apply(json!([{"patch": {"id": "a", "set": {"n": 1}}}])).await;
+        assert_eq!(patched, [(Transition::Update, true)]);
+        let deleted = apply(json!([{"delete": {"id": "a"}}])).await;
+        assert_eq!(deleted, [(Transition::Disappear, true)]);
+        let revived = apply(json!([{"create": {"_id": "a", "_type": "post"}}])).await;
+        assert_eq!(revived, [(Transition::Appear, false)]);
+    }
+
+    #[tokio::test]
+    async fn events_carry_the_effects_of_a_patch() {
+        let store = InMemoryDocumentStore::new();
+        let dataset_id = Uuid::new_v4();
+        let create = mutations(json!([{"create": {"_id": "a", "_type": "post", "title": "A"}}]));
+        apply_transaction(&store, dataset_id, &create, Default::default())
+            .await
+            .unwrap();
+
+        let patch = mutations(json!([{"patch": {"id": "a", "set": {"title": "B"}}}]));
+        let events = apply_transaction(&store, dataset_id, &patch, Default::default())
+            .await
+            .unwrap()
+            .events;
+        assert_eq!(
+            events[0].effects,
+            Some(json!({
+                "apply": {"set": {"title": "B"}},
+                "revert": {"set": {"title": "A"}},
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn create_revives_soft_deleted_document() {
+        let store = InMemoryDocumentStore::new();
+        let dataset_id = Uuid::new_v4();
+        let apply = |value: Value| {
+            let store = store.clone();
+            async move {
+                apply_transaction(&store, dataset_id, &mutations(value), Default::default()).await
+            }
+        };
+        apply(json!([{"create": {"_id": "a", "_type": "post", "title": "old"}}]))
+            .await
+            .unwrap();
+        apply(json!([{"delete": {"id": "a"}}])).await.unwrap();
+        assert!(store.get(dataset_id, "a").await.unwrap().is_none());
+        let deleted = store.get_including_deleted(dataset_id, "a").await.unwrap();
+        assert!(deleted.is_some_and(|row| row.deleted));
+
+        let revived = apply(json!([{"create": {"_id": "a", "_type": "post", "title": "new"}}]))
+            .await
+            .unwrap();
+        assert_eq!(revived.response.results[0].operation, "create");
+        let row = store.get(dataset_id, "a").await.unwrap().unwrap();
+        assert!(!row.deleted);
+        assert_eq!(row.to_document()["title"], "new");
+    }
+
+    #[tokio::test]
+    async fn create_or_replace_overwrites_existing_document() {
+        let store = InMemoryDocumentStore::new();
+        let dataset_id = Uuid::new_v4();
+        let create = mutations(json!([{"create": {"_id": "a", "_type": "post", "title": "x"}}]));
+        apply_transaction(&store, dataset_id, &create, Default::default())
+            .await
+            .unwrap();
+
+        let replace = mutations(json!([{"createOrReplace": {"_id": "a", "_type": "page"}}]));
+        let applied = apply_transaction(&store, dataset_id, &replace, Default::default())
+            .await
+            .unwrap();
+        assert_eq!(applied.response.results[0].operation, "update");
+        let doc = store
+            .get(dataset_id, "a")
+            .await
+            .unwrap()
+            .unwrap()
+            .to_document();
+        assert_eq!(doc["_type"], "page");
+        assert!(doc.get("title").is_none());
+    }
+
+    #[tokio::test]
+    async fn patch_introducing_duplicate_key_is_rejected() {
+        let store = InMemoryDocumentStore::new();
+        let dataset_id = Uuid::new_v4();
+        let create = mutations(json!([{"create": {
+            "_id": "a", "_type": "post", "body": [{"_key": "k1"}]
+        }}]));
+        apply_transaction(&store, dataset_id, &create, Default::default())
+            .await
+            .unwrap();
+
+        let insert = mutations(json!([{"patch": {
+            "id": "a",
+            "insert": {"after": "body[-1]", "items": [{"_key": "k1"}]}
+        }}]));
+        let err = apply_transaction(&store, dataset_id, &insert, Default::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            MutationError::ValidationFailed(ValidationError::DuplicateKey { .. })
+        ));
+        let row = store.get(dataset_id, "a").await.unwrap().unwrap();
+        assert_eq!(row.to_document()["body"], json!([{"_key": "k1"}]));
+    }
+
+    #[tokio::test]
+    async fn document_size_limit_applies_after_patches() {
+        let store = InMemoryDocumentStore::new();
+        let dataset_id = Uuid::new_v4();
+        let doc = json!({"_id": "a", "_type": "post", "title": "x"});
+        let limit = serde_json::to_vec(&json!({"title": "x"})).unwrap().len();
+        let options = || TransactionOptions {
+            max_document_bytes: Some(limit),
+            ..Default::default()
+        };
+
+        apply_transaction(
+            &store,
+            dataset_id,
+            &mutations(json!([{"create": doc}])),
+            options(),
+        )
+        .await
+        .unwrap();
+
+        // A small patch payload that grows the document past the limit.
+        let grow = mutations(json!([{"patch": {"id": "a", "set": {"title": "xy"}}}]));
+        let err = apply_transaction(&store, dataset_id, &grow, options())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            MutationError::DocumentTooLarge { ref id, size, limit: l }
+                if id == "a" && size == limit + 1 && l == limit
+        ));
+        let row = store.get(dataset_id, "a").await.unwrap().unwrap();
+        assert_eq!(row.to_document()["title"], "x");
+
+        // Shrinking back within the same transaction is fine.
+        let net_same = mutations(json!([
+            {"patch": {"id": "a", "set": {"title": "xyz"}}},
+            {"patch": {"id": "a", "set": {"title": "z"}}}
+        ]));
+        apply_transaction(&store, dataset_id, &net_same, options())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn referenced_documents_are_only_deleted_with_purge() {
+        let store = InMemoryDocumentStore::new();
+        let dataset_id = Uuid::new_v4();
+        let seed = mutations(json!([
+            {"create": {"_id": "author", "_type": "person"}},
+            {"create": {"_id": "lonely", "_type": "person"}},
+            {"create": {"_id": "p1", "_type": "post", "author": {"_ref": "author"}}}
+        ]));
+        apply_transaction(&store, dataset_id, &seed, Default::default())
+            .await
+            .unwrap();
+
+        let delete = mutations(json!([{"delete": {"id": "author"}}]));
+        let err = apply_transaction(&store, dataset_id, &delete, Default::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            MutationError::StillReferenced { ref id, ref by } if id == "author" && by == &["p1"]
+        ));
+
+        // A reference added in the same transaction blocks too.
+        let refer = mutations(json!([
+            {"patch": {"id": "lonely", "set": {"friend": {"_ref": "author"}}}},
+            {"patch": {"id": "p1", "unset": ["author"]}},
+            {"delete": {"id": "author"}}
+        ]));
+        let err = apply_transaction(&store, dataset_id, &refer, Default::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MutationError::StillReferenced { ref by, .. } if by == &["lonely"]));
+
+        // Removing the referrer in the same transaction unblocks it.
+        let both = mutations(json!([{"delete": {"id": "p1"}}, {"delete": {"id": "author"}}]));
+        let dry = dry_run_transaction(&store, dataset_id, &both, Default::default()).await;
+        assert!(dry.is_ok());
+
+        let purge = TransactionOptions {
+            purge: true,
+            ..Default::default()
+        };
+        apply_transaction(&store, dataset_id, &delete, purge)
+            .await
+            .unwrap();
+        assert!(store.get(dataset_id, "author").await.unwrap().is_none());
+
+        let unreferenced = mutations(json!([{"delete": {"id": "lonely"}}]));
+        apply_transaction(&store, dataset_id, &unreferenced, Default::default())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn deletion_waits_for_a_concurrent_referrer() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let store = PgDocumentStore::new(pool.clone());
+        let dataset_id = create_dataset(&pool).await;
+        let seed = mutations(json!([{"create": {"_id": "author", "_type": "person"}}]));
+        apply_transaction(&store, dataset_id, &seed, Default::default())
+            .await
+            .unwrap();
+
+        // The referrer is written before the deletion starts but commits
+        // after it has begun checking for references.
+        let mut referrer = store.begin().await.unwrap();
+        let content = json!({"_id": "p1", "_type": "post", "author": {"_ref": "author"}});
+        referrer
+            .upsert(dataset_id, "p1", "post", "r1", &content, Utc::now())
+            .await
+            .unwrap();
+        let delete = mutations(json!([{"delete": {"id": "author"}}]));
+        let deleting = tokio::spawn({
+            let store = store.clone();
+            async move { apply_transaction(&store, dataset_id, &delete, Default::default()).await }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(!deleting.is_finished());
+
+        referrer.commit().await.unwrap();
+        let err = deleting.await.unwrap().unwrap_err();
+        assert!(matches!(err, MutationError::StillReferenced { ref by, .. } if by == &["p1"]));
+    }
+
+    #[tokio::test]
+    async fn create_get_delete_in_memory() {
+        let store = InMemoryDocumentStore::new();
+        let dataset_id = Uuid::new_v4();
+
+        let created = apply_transaction(
+            &store,
+            dataset_id,
+            &mutations(json!([{"create": {"_id": "a", "_type": "post", "title": "A"}}])),
+            Default::default(),
+        )
+        .await
+        .unwrap()
+        .response;
+        let row = store.get(dataset_id, "a").await.unwrap().unwrap();
+        assert_eq!(row.revision, created.transaction_id);
+        assert_eq!(row.to_document()["title"], "A");
+
+        let deleted = apply_transaction(
+            &store,
+            dataset_id,
+            &mutations(json!([{"delete": {"id": "a"}}])),
+            Default::default(),
+        )
+        .await
+        .unwrap()
+        .response;
+        assert_eq!(deleted.results[0].operation, "delete");
+        assert!(store.get(dataset_id, "a").await.unwrap().is_none());
+        assert!(store
+            .list_by_type(dataset_id, None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_transaction_writes_nothing() {
+        let store = InMemoryDocumentStore::new();
+        let dataset_id = Uuid::new_v4();
+        let err = apply_transaction(
+            &store,
+            dataset_id,
+            &mutations(json!([
+                {"create": {"_id": "a", "_type": "post"}},
+                {"patch": {"id": "missing", "set": {"x": 1}}}
+            ])),
+            Default::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, MutationError::NotFound(_)));
+        assert!(store.get(dataset_id, "a").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn dry_run_reports_results_without_writing() {
+        let store = InMemoryDocumentStore::new();
+        let dataset_id = Uuid::new_v4();
+        let response = dry_run_transaction(
+            &store,
+            dataset_id,
+            &mutations(json!([{"create": {"_id": "a", "_type": "post"}}])),
+            TransactionOptions {
+                transaction_id: Some("tx1".into()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.transaction_id, "tx1");
+        assert_eq!(response.results[0].id, "a");
+        assert_eq!(response.results[0].operation, "create");
+        assert!(store.get(dataset_id, "a").await.unwrap().is_none());
+
+        let err = dry_run_transaction(
+            &store,
+            dataset_id,
+            &mutations(json!([{"create": {"_id": "b"}}])),
+            Default::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, MutationError::ValidationFailed(_)));
+    }
+}
@@ -1 +1,7 @@
+pub mod effects;
+pub mod executor;
+pub mod patch;
 pub mod types;
+mod validate;
+
+pub use validate::validate;
@@ -0,0 +1,477 @@
+/// Patch application for `PatchMutation` operations.
+/// Paths use the JSONMatch grammar of [`crate::jsonmatch`].
+use serde_json::Value;
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::types::{InsertOperation, PatchOperations};
+use crate::jsonmatch::{self, parse_path, resolve, resolve_mut, PathError, PathSegment};
+
+/// Length of generated array item `_key`s.
+const KEY_LENGTH: usize = 12;
+
+#[derive(Debug, Error)]
+pub enum PatchError {
+    #[error("invalid path: {0}")]
+    InvalidPath(String),
+    #[error("type mismatch at {path}: expected {expected}")]
+    TypeMismatch { path: String, expected: String },
+    #[error("path not found: {0}")]
+    NotFound(String),
+    #[error("invalid diffMatchPatch at {path}: {reason}")]
+    InvalidTextPatch { path: String, reason: String },
+    #[error("diffMatchPatch does not apply cleanly at {0}")]
+    TextPatchConflict(String),
+}
+
+impl From<PathError> for PatchError {
+    fn from(err: PathError) -> Self {
+        match err {
+            PathError::TypeMismatch { path, expected } => PatchError::TypeMismatch {
+                path,
+                expected: expected.into(),
+            },
+            PathError::NotFound(path) => PatchError::NotFound(path),
+            other => PatchError::InvalidPath(other.to_string()),
+        }
+    }
+}
+
+/// Apply all operations of a patch to `doc`, in Sanity's order:
+/// set, setIfMissing, merge, unset, inc, dec, insert, diffMatchPatch.
+pub fn apply_patch(doc: &mut Value, ops: &PatchOperations) -> Result<(), PatchError> {
+    if let Some(set) = &ops.set {
+        for (path, value) in as_object(set, "set")? {
+            jsonmatch::apply(doc, &parse_path(path)?, value.clone())?;
+        }
+    }
+    if let Some(set_if_missing) = &ops.set_if_missing {
+        for (path, value) in as_object(set_if_missing, "setIfMissing")? {
+            let segments = parse_path(path)?;
+            if resolve(doc, &segments).is_none() {
+                jsonmatch::apply(doc, &segments, value.clone())?;
+            }
+        }
+    }
+    if let Some(merge) = &ops.merge {
+        for (path, value) in as_object(merge, "merge")? {
+            let segments = parse_path(path)?;
+            match resolve_mut(doc, &segments) {
+                Some(target) => merge_into(target, value),
+                None => jsonmatch::apply(doc, &segments, without_nulls(value.clone()))?,
+            }
+        }
+    }
+    if let Some(unset) = &ops.unset {
+        for path in unset {
+            jsonmatch::remove(doc, &parse_path(path)?);
+        }
+    }
+    if let Some(inc) = &ops.inc {
+        for (path, amount) in as_object(inc, "inc")? {
+            add_number(doc, path, amount, false)?;
+        }
+    }
+    if let Some(dec) = &ops.dec {
+        for (path, amount) in as_object(dec, "dec")? {
+            add_number(doc, path, amount, true)?;
+        }
+    }
+    if let Some(insert) = &ops.insert {
+        apply_insert(doc, insert)?;
+    }
+    if let Some(patches) = &ops.diff_match_patch {
+        for (path, patch) in as_object(patches, "diffMatchPatch")? {
+            apply_text_patch(doc, path, patch)?;
+        }
+    }
+    Ok(())
+}
+
+fn as_object<'a>(
+    value: &'a Value,
+    op: &str,
+) -> Result<&'a serde_json::Map<String, Value>, PatchError> {
+    value.as_object().ok_or_else(|| PatchError::TypeMismatch {
+        path: op.to_string(),
+        expected: "object of path → value".into(),
+    })
+}
+
+/// Deep-merge `patch` into `target`: objects merge key by key, anything else
+/// replaces the target wholesale. A `null` in an object deletes that key
+/// rather than storing it.
+fn merge_into(target: &mut Value, patch: &Value) {
+    let (Value::Object(target), Value::Object(patch)) = (&mut *target, patch) else {
+        *target = without_nulls(patch.clone());
+        return;
+    };
+    for (key, value) in patch {
+        match (target.get_mut(key), value) {
+            (_, Value::Null) => {
+                target.remove(key);
+            }
+            (Some(existing), _) => merge_into(existing, value),
+            (None, _) => {
+                target.insert(key.clone(), without_nulls(value.clone()));
+            }
+        }
+    }
+}
+
+/// Drop `null` object members recursively, as merging into an absent value
+/// would have.
+fn without_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k, without_nulls(v)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+fn add_number(doc: &mut Value, path: &str, amount: &Value, negate: bool) -> Result<(), PatchError> {
+    let segments = parse_path(path)?;
+    let Some(target) = resolve_mut(doc, &segments) else {
+        // Incrementing a missing field is a no-op, matching Sanity.
+        return Ok(());
+    };
+    let mismatch = || PatchError::TypeMismatch {
+        path: path.to_string(),
+        expected: "number".into(),
+    };
+
+    let (Value::Number(current), Value::Number(delta)) = (&*target, amount) else {
+        return Err(mismatch());
+    };
+
+    let next = match (current.as_i64(), delta.as_i64()) {
+        (Some(a), Some(b)) => {
+            let b = if negate { b.checked_neg() } else { Some(b) };
+            b.and_then(|b| a.checked_add(b)).map(Value::from)
+        }
+        _ => None,
+    };
+    *target = match next {
+        Some(v) => v,
+        None => {
+            let a = current.as_f64().ok_or_else(mismatch)?;
+            let b = delta.as_f64().ok_or_else(mismatch)?;
+            let sum = if negate { a - b } else { a + b };
+            serde_json::Number::from_f64(sum)
+                .map(Value::Number)
+                .ok_or_else(mismatch)?
+        }
+    };
+    Ok(())
+}
+
+fn apply_insert(doc: &mut Value, insert: &InsertOperation) -> Result<(), PatchError> {
+    let (path, position) = match (&insert.before, &insert.after, &insert.replace) {
+        (Some(p), None, None) => (p, InsertPosition::Before),
+        (None, Some(p), None) => (p, InsertPosition::After),
+        (None, None, Some(p)) => (p, InsertPosition::Replace),
+        _ => {
+            return Err(PatchError::InvalidPath(
+                "insert requires exactly one of before, after or replace".into(),
+            ))
+        }
+    };
+
+    let segments = parse_path(path)?;
+    let Some((item, array_path)) = segments.split_last() else {
+        return Err(PatchError::InvalidPath(path.clone()));
+    };
+    let arr = resolve_mut(doc, array_path)
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| PatchError::NotFound(path.clone()))?;
+
+    // Indices past either end clamp for before/after; keyed items must exist.
+    let len = arr.len() as i64;
+    let resolved = match item {
+        PathSegment::Index(index) if *index < 0 => len + index,
+        PathSegment::Index(index) => *index,
+        PathSegment::KeyedItem(_) => {
+            jsonmatch::position(arr, item).ok_or_else(|| PatchError::NotFound(path.clone()))? as i64
+        }
+        PathSegment::Key(_) => return Err(PatchError::InvalidPath(path.clone())),
+    };
+    let items = insert.items.iter().cloned().map(with_key);
+    match position {
+        InsertPosition::Before => {
+            let at = resolved.clamp(0, len) as usize;
+            arr.splice(at..at, items);
+        }
+        InsertPosition::After => {
+            let at = (resolved + 1).clamp(0, len) as usize;
+            arr.splice(at..at, items);
+        }
+        InsertPosition::Replace => {
+            let idx =
+                jsonmatch::position(arr, item).ok_or_else(|| PatchError::NotFound(path.clone()))?;
+            arr.splice(idx..=idx, items);
+        }
+    }
+    Ok(())
+}
+
+/// Apply a diff-match-patch patch text to the string at `path`. Fails
+/// unless every hunk applies, so edits made against stale text are rejected.
+fn apply_text_patch(doc: &mut Value, path: &str, patch: &Value) -> Result<(), PatchError> {
+    let invalid = |reason: String| PatchError::InvalidTextPatch {
+        path: path.to_string(),
+        reason,
+    };
+    let patch = patch
+        .as_str()
+        .ok_or_else(|| invalid("patch must be a string".into()))?;
+    let target = resolve_mut(doc, &parse_path(path)?)
+        .ok_or_else(|| PatchError::NotFound(path.to_string()))?;
+    let Value::String(text) = target else {
+        return Err(PatchError::TypeMismatch {
+            path: path.to_string(),
+            expected: "string".into(),
+        });
+    };
+
+    let dmp = dmp::new();
+    let patches = dmp
+        .patch_from_text(patch.to_string())
+        .map_err(|e| invalid(format!("{e:?}")))?;
+    let (patched, applied) = dmp
+        .patch_apply(&patches, text)
+        .map_err(|e| invalid(format!("{e:?}")))?;
+    if applied.contains(&false) {
+        return Err(PatchError::TextPatchConflict(path.to_string()));
+    }
+    *text = patched.into_iter().collect();
+    Ok(())
+}
+
+/// Give an inserted object a `_key` if it has none, so the array item can be
+/// addressed by later patches. Non-object items are left as they are.
+fn with_key(mut item: Value) -> Value {
+    if let Value::Object(map) = &mut item {
+        map.entry("_key")
+            .or_insert_with(|| Value::String(new_key()));
+    }
+    item
+}
+
+fn new_key() -> String {
+    let mut key = Uuid::new_v4().simple().to_string();
+    key.truncate(KEY_LENGTH);
+    key
+}
+
+enum InsertPosition {
+    Before,
+    After,
+    Replace,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ops(value: Value) -> PatchOperations {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn set_creates_nested_fields() {
+        let mut doc = json!({"title": "a"});
+        apply_patch(
+            &mut doc,
+            &ops(json!({"set": {"title": "b", "meta.views": 1}})),
+        )
+        .unwrap();
+        assert_eq!(doc, json!({"title": "b", "meta": {"views": 1}}));
+    }
+
+    #[test]
+    fn set_if_missing_keeps_existing() {
+        let mut doc = json!({"title": "a"});
+        apply_patch(
+            &mut doc,
+            &ops(json!({"setIfMissing": {"title": "b", "tags": []}})),
+        )
+        .unwrap();
+        assert_eq!(doc, json!({"title": "a", "tags": []}));
+    }
+
+    #[test]
+    fn merge_combines_nested_objects() {
+        let mut doc = json!({"meta": {"seo": {"title": "a", "noindex": true}, "views": 1}});
+        apply_patch(
+            &mut doc,
+            &ops(json!({"merge": {"meta": {"seo": {"title": "b", "image": {"alt": "x"}}}}})),
+        )
+        .unwrap();
+        assert_eq!(
+            doc,
+            json!({"meta": {
+                "seo": {"title": "b", "noindex": true, "image": {"alt": "x"}},
+                "views": 1
+            }})
+        );
+    }
+
+    #[test]
+    fn merge_replaces_scalars_and_arrays() {
+        let mut doc = json!({"a": {"n": 1, "tags": [1, 2, 3], "o": {"x": 1}}, "s": "str"});
+        apply_patch(
+            &mut doc,
+            &ops(json!({"merge": {
+                "a": {"n": {"nested": true}, "tags": [9], "o": 5},
+                "s": {"now": "object"}
+            }})),
+        )
+        .unwrap();
+        assert_eq!(
+            doc,
+            json!({"a": {"n": {"nested": true}, "tags": [9], "o": 5}, "s": {"now": "object"}})
+        );
+    }
+
+    #[test]
+    fn merge_null_deletes_keys() {
+        let mut doc = json!({"meta": {"a": 1, "b": 2}});
+        apply_patch(
+            &mut doc,
+            &ops(json!({"merge": {
+                "meta": {"a": null, "missing": null},
+                "fresh": {"x": 1, "y": null}
+            }})),
+        )
+        .unwrap();
+        assert_eq!(doc, json!({"meta": {"b": 2}, "fresh": {"x": 1}}));
+    }
+
+    #[test]
+    fn unset_and_inc_dec() {
+        let mut doc = json!({"a": 1, "b": 2.5, "c": [1, 2, 3]});
+        apply_patch(
+            &mut doc,
+            &ops(json!({"unset": ["c[0]"], "inc": {"a": 2}, "dec": {"b": 0.5}})),
+        )
+        .unwrap();
+        assert_eq!(doc, json!({"a": 3, "b": 2.0, "c": [2, 3]}));
+    }
+
+    #[test]
+    fn inc_on_string_is_type_mismatch() {
+        let mut doc = json!({"a": "x"});
+        let err = apply_patch(&mut doc, &ops(json!({"inc": {"a": 1}}))).unwrap_err();
+        assert!(matches!(err, PatchError::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn insert_before_after_replace() {
+        let mut doc = json!({"items": [1, 2]});
+        apply_patch(
+            &mut doc,
+            &ops(json!({"insert": {"after": "items[-1]", "items": [3]}})),
+        )
+        .unwrap();
+        apply_patch(
+            &mut doc,
+            &ops(json!({"insert": {"before": "items[0]", "items": [0]}})),
+        )
+        .unwrap();
+        apply_patch(
+            &mut doc,
+            &ops(json!({"insert": {"replace": "items[1]", "items": [9, 9]}})),
+        )
+        .unwrap();
+        assert_eq!(doc, json!({"items": [0, 9, 9, 2, 3]}));
+    }
+
+    #[test]
+    fn insert_generates_missing_keys() {
+        let mut doc = json!({"items": [{"_key": "first"}]});
+        apply_patch(
+            &mut doc,
+            &ops(json!({"insert": {"after": "items[-1]", "items": [
+                {"title": "a"},
+                {"title": "b"},
+                {"_key": "mine", "title": "c"}
+            ]}})),
+        )
+        .unwrap();
+
+        let items = doc["items"].as_array().unwrap();
+        let keys: Vec<&str> = items.iter().map(|i| i["_key"].as_str().unwrap()).collect();
+        assert_eq!(keys[0], "first");
+        assert_eq!(keys[1].len(), KEY_LENGTH);
+        assert_eq!(keys[2].len(), KEY_LENGTH);
+        assert_ne!(keys[1], keys[2]);
+        assert_eq!(items[3], json!({"_key": "mine", "title": "c"}));
+    }
+
+    fn text_patch(old: &str, new: &str) -> String {
+        let dmp = dmp::new();
+        dmp.patch_to_text(&dmp.patch_make1(old, new))
+    }
+
+    #[test]
+    fn diff_match_patch_edits_string() {
+        let mut doc = json!({"body": {"text": "The quick brown fox"}});
+        let patch = text_patch("The quick brown fox", "The quick red fox jumps");
+        apply_patch(
+            &mut doc,
+            &ops(json!({"diffMatchPatch": {"body.text": patch}})),
+        )
+        .unwrap();
+        assert_eq!(doc, json!({"body": {"text": "The quick red fox jumps"}}));
+    }
+
+    #[test]
+    fn diff_match_patch_rejects_changed_text() {
+        let mut doc = json!({"title": "Completely unrelated sentence here"});
+        let patch = text_patch("The quick brown fox", "The quick red fox");
+        let err =
+            apply_patch(&mut doc, &ops(json!({"diffMatchPatch": {"title": patch}}))).unwrap_err();
+        assert!(matches!(err, PatchError::TextPatchConflict(path) if path == "title"));
+        assert_eq!(doc["title"], "Completely unrelated sentence here");
+
+        let mut doc = json!({"title": 1});
+        let err =
+            apply_patch(&mut doc, &ops(json!({"diffMatchPatch": {"title": patch}}))).unwrap_err();
+        assert!(matches!(err, PatchError::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn invalid_path_is_rejected() {
+        let mut doc = json!({});
+        let err = apply_patch(&mut doc, &ops(json!({"set": {"a[": 1}}))).unwrap_err();
+        assert_eq!(err.to_string(), "invalid path: unbalanced brackets in a[");
+    }
+
+    #[test]
+    fn operations_address_keyed_items() {
+        let mut doc = json!({"body": [{"_key": "a", "n": 1}, {"_key": "b", "n": 5}]});
+        apply_patch(
+            &mut doc,
+            &ops(json!({
+                "set": {"body[_key==\"a\"].text": "hi"},
+                "inc": {"body[_key==\"b\"].n": 1},
+                "insert": {"before": "body[_key==\"b\"]", "items": [{"_key": "c"}]}
+            })),
+        )
+        .unwrap();
+        apply_patch(&mut doc, &ops(json!({"unset": ["body[_key==\"a\"]"]}))).unwrap();
+        assert_eq!(doc, json!({"body": [{"_key": "c"}, {"_key": "b", "n": 6}]}));
+
+        let err = apply_patch(
+            &mut doc,
+            &ops(json!({"insert": {"after": "body[_key==\"zz\"]", "items": [1]}})),
+        )
+        .unwrap_err();
+        assert!(matches!(err, PatchError::NotFound(_)));
+    }
+}
@@ -14,16 +14,19 @@ pub enum Mutation {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct CreateMutation {
     pub document: Value,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct CreateOrReplaceMutation {
     pub document: Value,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct CreateIfNotExistsMutation {
     pub document: Value,
 }
@@ -56,7 +59,7 @@ pub struct PatchMutation {
     pub operations: PatchOperations,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PatchOperations {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -77,7 +80,7 @@ pub struct PatchOperations {
     pub diff_match_patch: Option<Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InsertOperation {
     #[serde(skip_serializing_if = "Option::is_none")]
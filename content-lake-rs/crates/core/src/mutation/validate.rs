@@ -0,0 +1,128 @@
+//! Up-front checks on mutation payloads, so malformed mutations are rejected
+//! before a transaction is opened.
+
+use serde_json::Value;
+
+use super::types::{Mutation, PatchOperations};
+use crate::document::validate::{
+    validate_content_keys, validate_document_fields, validate_unique_keys, ValidationError,
+};
+use crate::jsonmatch::parse_path;
+
+/// Check that `mutation` is well-formed: documents being written carry the
+/// required fields, no other top-level `_` fields and unique array `_key`s,
+/// and every patch path parses.
+pub fn validate(mutation: &Mutation) -> Result<(), ValidationError> {
+    match mutation {
+        Mutation::Create(m) => validate_document(&m.document),
+        Mutation::CreateOrReplace(m) => validate_document(&m.document),
+        Mutation::CreateIfNotExists(m) => validate_document(&m.document),
+        Mutation::Delete(_) => Ok(()),
+        Mutation::Patch(m) => patch_paths(&m.operations).try_for_each(|path| {
+            parse_path(path)
+                .map(drop)
+                .map_err(|reason| ValidationError::InvalidPatchPath {
+                    path: path.to_string(),
+                    reason,
+                })
+        }),
+    }
+}
+
+fn validate_document(document: &Value) -> Result<(), ValidationError> {
+    let map = document.as_object().ok_or(ValidationError::NotAnObject)?;
+    // A missing `_id` is generated by the executor.
+    let id = map
+        .get("_id")
+        .and_then(Value::as_str)
+        .unwrap_or("generated");
+    validate_document_fields(Some(id), map.get("_type").and_then(Value::as_str))?;
+    validate_content_keys(map)?;
+    validate_unique_keys(document)
+}
+
+/// Every path a patch addresses. Operations whose payload has the wrong shape
+/// are left for the executor to report.
+fn patch_paths(ops: &PatchOperations) -> impl Iterator<Item = &str> {
+    let keyed = [
+        &ops.set,
+        &ops.set_if_missing,
+        &ops.merge,
+        &ops.inc,
+        &ops.dec,
+        &ops.diff_match_patch,
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(Value::as_object)
+    .flat_map(|map| map.keys().map(String::as_str));
+    let unset = ops.unset.iter().flatten().map(String::as_str);
+    let insert = ops
+        .insert
+        .iter()
+        .flat_map(|i| [&i.before, &i.after, &i.replace])
+        .flatten()
+        .map(String::as_str);
+    keyed.chain(unset).chain(insert)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonmatch::PathError;
+    use serde_json::json;
+
+    fn mutation(value: Value) -> Mutation {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn create_requires_type() {
+        let err = validate(&mutation(json!({"create": {"_id": "a"}}))).unwrap_err();
+        assert!(matches!(err, ValidationError::MissingType));
+        let err = validate(&mutation(
+            json!({"createOrReplace": {"_id": "", "_type": "post"}}),
+        ))
+        .unwrap_err();
+        assert!(matches!(err, ValidationError::EmptyId));
+        assert!(validate(&mutation(json!({"createIfNotExists": {"_type": "post"}}))).is_ok());
+        let err = validate(&mutation(json!({"create": {
+            "_type": "post",
+            "tags": [{"_key": "t"}, {"_key": "t"}]
+        }})))
+        .unwrap_err();
+        assert!(matches!(err, ValidationError::DuplicateKey { .. }));
+        let err = validate(&mutation(json!({"createIfNotExists": {
+            "_type": "post",
+            "_rev": "r1",
+            "_weird": true
+        }})))
+        .unwrap_err();
+        assert!(matches!(err, ValidationError::ReservedKey(key) if key == "_weird"));
+    }
+
+    #[test]
+    fn patch_paths_must_parse() {
+        let err = validate(&mutation(json!({"patch": {
+            "id": "a",
+            "set": {"title": "x"},
+            "insert": {"after": "items[-1", "items": [1]}
+        }})))
+        .unwrap_err();
+        let ValidationError::InvalidPatchPath { path, reason } = err else {
+            panic!("expected an invalid path, got {err:?}");
+        };
+        assert_eq!(path, "items[-1");
+        assert_eq!(reason, PathError::UnbalancedBrackets("items[-1".into()));
+
+        let err = validate(&mutation(json!({"patch": {"id": "a", "unset": ["a..b"]}})));
+        assert!(matches!(err, Err(ValidationError::InvalidPatchPath { .. })));
+        assert!(validate(&mutation(json!({"patch": {
+            "id": "a",
+            "set": {"body[_key==\"k\"].text": "x"},
+            "merge": {"meta": {}},
+            "unset": ["tags[0]"]
+        }})))
+        .is_ok());
+    }
+}
@@ -0,0 +1,166 @@
+//! Datasets: the named containers documents, transactions and references
+//! are scoped to. The API sees only the datasets of the default project,
+//! where names are unique.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::PgDocumentStore;
+
+/// Project that datasets created through the API belong to.
+const DEFAULT_PROJECT: &str = "default";
+
+/// Longest accepted dataset name.
+const MAX_NAME_LEN: usize = 64;
+
+/// A dataset and how many live documents it holds.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Dataset {
+    #[serde(skip)]
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub document_count: i64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DatasetError {
+    #[error(
+        "invalid dataset name {0:?}: use 1-64 lowercase letters, digits, '_' and '-', \
+         starting with a letter or digit"
+    )]
+    InvalidName(String),
+
+    #[error("dataset already exists: {0}")]
+    AlreadyExists(String),
+
+    #[error("dataset not found: {0}")]
+    NotFound(String),
+
+    #[error("dataset {name} still holds {documents} documents; pass purge=true to delete them")]
+    NotEmpty { name: String, documents: i64 },
+
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Check `name` against Sanity's dataset naming rules.
+pub fn validate_name(name: &str) -> Result<(), DatasetError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_' || b == b'-')
+        && !name.starts_with(['_', '-']);
+    if valid {
+        Ok(())
+    } else {
+        Err(DatasetError::InvalidName(name.to_string()))
+    }
+}
+
+/// The default project's datasets; `$1` binds the project name.
+const SELECT_DATASET: &str = "SELECT d.id, d.name, d.created_at, \
+     (SELECT count(*) FROM documents WHERE dataset_id = d.id AND deleted = false) \
+     AS document_count FROM datasets d JOIN projects p ON p.id = d.project_id \
+     WHERE p.name = $1";
+
+impl PgDocumentStore {
+    /// Resolve a dataset name to its id.
+    pub async fn dataset_id(&self, name: &str) -> Result<Option<Uuid>, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT d.id FROM datasets d JOIN projects p ON p.id = d.project_id \
+             WHERE p.name = $1 AND d.name = $2",
+        )
+        .bind(DEFAULT_PROJECT)
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Look up a dataset by name.
+    pub async fn dataset(&self, name: &str) -> Result<Option<Dataset>, sqlx::Error> {
+        sqlx::query_as(&format!("{SELECT_DATASET} AND d.name = $2"))
+            .bind(DEFAULT_PROJECT)
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// All datasets, ordered by name.
+    pub async fn list_datasets(&self) -> Result<Vec<Dataset>, sqlx::Error> {
+        sqlx::query_as(&format!("{SELECT_DATASET} ORDER BY d.name"))
+            .bind(DEFAULT_PROJECT)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Create an empty dataset in the default project.
+    pub async fn create_dataset(&self, name: &str) -> Result<Dataset, DatasetError> {
+        validate_name(name)?;
+        let created = sqlx::query_as(
+            "INSERT INTO datasets (project_id, name) \
+             SELECT id, $2 FROM projects WHERE name = $1 \
+             ON CONFLICT DO NOTHING \
+             RETURNING id, name, created_at, 0::bigint AS document_count",
+        )
+        .bind(DEFAULT_PROJECT)
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        created.ok_or_else(|| DatasetError::AlreadyExists(name.to_string()))
+    }
+
+    /// Delete a dataset and, through the schema's cascades, everything in
+    /// it. A dataset that still holds live documents is only deleted when
+    /// `purge` is set.
+    pub async fn delete_dataset(&self, name: &str, purge: bool) -> Result<(), DatasetError> {
+        let mut tx = self.pool.begin().await?;
+        let found: Option<(Uuid, i64)> = sqlx::query_as(
+            "SELECT d.id, (SELECT count(*) FROM documents \
+             WHERE dataset_id = d.id AND deleted = false) \
+             FROM datasets d JOIN projects p ON p.id = d.project_id \
+             WHERE p.name = $1 AND d.name = $2 FOR UPDATE OF d",
+        )
+        .bind(DEFAULT_PROJECT)
+        .bind(name)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some((id, documents)) = found else {
+            return Err(DatasetError::NotFound(name.to_string()));
+        };
+        if documents > 0 && !purge {
+            return Err(DatasetError::NotEmpty {
+                name: name.to_string(),
+                documents,
+            });
+        }
+        sqlx::query("DELETE FROM datasets WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_names() {
+        for name in ["production", "staging-2", "a_b", "0day"] {
+            assert!(validate_name(name).is_ok(), "{name}");
+        }
+        let long = "a".repeat(MAX_NAME_LEN + 1);
+        for name in ["", "Prod", "-x", "_x", "a b", "a/b", long.as_str()] {
+            assert!(
+                matches!(validate_name(name), Err(DatasetError::InvalidName(_))),
+                "{name}"
+            );
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use content_lake_groq::functions::referenced_ids;
+use serde_json::Value;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use uuid::Uuid;
+
+use super::{DocumentStore, DocumentTransaction};
+use crate::document::model::DocumentRow;
+use crate::mutation::executor::DocumentChange;
+use crate::mutation::types::Mutation;
+
+/// Rows keyed by dataset and document id, so iteration is in id order.
+type Documents = BTreeMap<(Uuid, String), DocumentRow>;
+
+/// Document store held in memory, for tests that don't need PostgreSQL.
+///
+/// Transactions are serialized: one holds the store until it commits or is
+/// dropped. The history log is not kept.
+#[derive(Clone, Default)]
+pub struct InMemoryDocumentStore {
+    documents: Arc<Mutex<Documents>>,
+}
+
+impl InMemoryDocumentStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn live(documents: &Documents, dataset_id: Uuid, types: Option<&[String]>) -> Vec<DocumentRow> {
+    list(documents, dataset_id, types, false)
+}
+
+fn list(
+    documents: &Documents,
+    dataset_id: Uuid,
+    types: Option<&[String]>,
+    include_deleted: bool,
+) -> Vec<DocumentRow> {
+    documents
+        .range((dataset_id, String::new())..)
+        .take_while(|((dataset, _), _)| *dataset == dataset_id)
+        .map(|(_, row)| row)
+        .filter(|row| include_deleted || !row.deleted)
+        .filter(|row| types.is_none_or(|types| types.contains(&row.doc_type)))
+        .cloned()
+        .collect()
+}
+
+impl DocumentStore for InMemoryDocumentStore {
+    type Transaction = InMemoryTransaction;
+
+    async fn get(
+        &self,
+        dataset_id: Uuid,
+        document_id: &str,
+    ) -> Result<Option<DocumentRow>, sqlx::Error> {
+        let documents = self.documents.lock().await;
+        Ok(documents
+            .get(&(dataset_id, document_id.to_string()))
+            .filter(|row| !row.deleted)
+            .cloned())
+    }
+
+    async fn list_by_type(
+        &self,
+        dataset_id: Uuid,
+        types: Option<&[String]>,
+    ) -> Result<Vec<DocumentRow>, sqlx::Error> {
+        Ok(live(&*self.documents.lock().await, dataset_id, types))
+    }
+
+    async fn get_including_deleted(
+        &self,
+        dataset_id: Uuid,
+        document_id: &str,
+    ) -> Result<Option<DocumentRow>, sqlx::Error> {
+        let documents = self.documents.lock().await;
+        Ok(documents
+            .get(&(dataset_id, document_id.to_string()))
+            .cloned())
+    }
+
+    async fn list_including_deleted(
+        &self,
+        dataset_id: Uuid,
+        types: Option<&[String]>,
+    ) -> Result<Vec<DocumentRow>, sqlx::Error> {
+        Ok(list(&*self.documents.lock().await, dataset_id, types, true))
+    }
+
+    async fn referencing(
+        &self,
+        dataset_id: Uuid,
+        target_id: &str,
+    ) -> Result<Vec<DocumentRow>, sqlx::Error> {
+        let documents = self.documents.lock().await;
+        Ok(live(&documents, dataset_id, None)
+            .into_iter()
+            .filter(|row| referenced_ids(&row.content).contains(target_id))
+            .collect())
+    }
+
+    async fn begin(&self) -> Result<InMemoryTransaction, sqlx::Error> {
+        let guard = self.documents.clone().lock_owned().await;
+        let staged = guard.clone();
+        Ok(InMemoryTransaction { guard, staged })
+    }
+}
+
+/// Writes go to a copy of the store that replaces it on commit.
+pub struct InMemoryTransaction {
+    guard: OwnedMutexGuard<Documents>,
+    staged: Documents,
+}
+
+impl DocumentTransaction for InMemoryTransaction {
+    async fn get(
+        &mut self,
+        dataset_id: Uuid,
+        document_id: &str,
+    ) -> Result<Option<DocumentRow>, sqlx::Error> {
+        Ok(self
+            .staged
+            .get(&(dataset_id, document_id.to_string()))
+            .cloned())
+    }
+
+    async fn list_by_type(
+        &mut self,
+        dataset_id: Uuid,
+        types: Option<&[String]>,
+    ) -> Result<Vec<DocumentRow>, sqlx::Error> {
+        Ok(live(&self.staged, dataset_id, types))
+    }
+
+    async fn referencing(
+        &mut self,
+        dataset_id: Uuid,
+        target_id: &str,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        Ok(live(&self.staged, dataset_id, None)
+            .into_iter()
+            .filter(|row| referenced_ids(&row.content).contains(target_id))
+            .map(|row| row.document_id)
+            .collect())
+    }
+
+    async fn upsert(
+        &mut self,
+        dataset_id: Uuid,
+        document_id: &str,
+        doc_type: &str,
+        revision: &str,
+        content: &Value,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let row = self
+            .staged
+            .entry((dataset_id, document_id.to_string()))
+            .or_insert_with(|| DocumentRow {
+                id: Uuid::new_v4(),
+                dataset_id,
+                document_id: document_id.to_string(),
+                doc_type: String::new(),
+                revision: String::new(),
+                content: Value::Null,
+                created_at: now,
+                updated_at: now,
+                deleted: false,
+            });
+        row.doc_type = doc_type.to_string();
+        row.revision = revision.to_string();
+        row.content = content.clone();
+        row.updated_at = now;
+        row.deleted = false;
+        Ok(())
+    }
+
+    async fn soft_delete(
+        &mut self,
+        dataset_id: Uuid,
+        document_id: &str,
+        revision: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        if let Some(row) = self.staged.get_mut(&(dataset_id, document_id.to_string())) {
+            row.deleted = true;
+            row.revision = revision.to_string();
+            row.updated_at = now;
+        }
+        Ok(())
+    }
+
+    async fn record(
+        &mut self,
+        _dataset_id: Uuid,
+        _transaction_id: &str,
+        _mutations: &[Mutation],
+        _changes: &[DocumentChange],
+        _timestamp: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        Ok(())
+    }
+
+    async fn commit(mut self) -> Result<(), sqlx::Error> {
+        *self.guard = self.staged;
+        Ok(())
+    }
+
+    async fn rollback(self) -> Result<(), sqlx::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn uncommitted_writes_are_discarded() {
+        let store = InMemoryDocumentStore::new();
+        let dataset_id = Uuid::new_v4();
+
+        let mut tx = store.begin().await.unwrap();
+        tx.upsert(dataset_id, "a", "post", "r1", &json!({}), Utc::now())
+            .await
+            .unwrap();
+        drop(tx);
+        assert!(store.get(dataset_id, "a").await.unwrap().is_none());
+
+        let mut tx = store.begin().await.unwrap();
+        tx.upsert(dataset_id, "a", "post", "r1", &json!({}), Utc::now())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        assert!(store.get(dataset_id, "a").await.unwrap().is_some());
+        assert!(store.get(Uuid::new_v4(), "a").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,129 @@
+//! Dataset-scoped access to stored documents.
+//!
+//! Every read and write takes the dataset id explicitly, so no query can
+//! reach documents outside the dataset it was asked about. Mutation and
+//! query code is written against [`DocumentStore`]; [`PgDocumentStore`]
+//! backs the server and [`InMemoryDocumentStore`] backs database-free tests.
+
+pub mod datasets;
+pub mod memory;
+pub mod postgres;
+
+use std::future::Future;
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::document::model::DocumentRow;
+use crate::mutation::executor::DocumentChange;
+use crate::mutation::types::Mutation;
+
+pub use memory::InMemoryDocumentStore;
+pub use postgres::PgDocumentStore;
+
+/// Read access to documents plus the ability to start a write transaction.
+pub trait DocumentStore: Send + Sync {
+    type Transaction: DocumentTransaction;
+
+    /// Fetch a live document by id.
+    fn get(
+        &self,
+        dataset_id: Uuid,
+        document_id: &str,
+    ) -> impl Future<Output = Result<Option<DocumentRow>, sqlx::Error>> + Send;
+
+    /// All live documents ordered by id, optionally restricted to `types`.
+    fn list_by_type(
+        &self,
+        dataset_id: Uuid,
+        types: Option<&[String]>,
+    ) -> impl Future<Output = Result<Vec<DocumentRow>, sqlx::Error>> + Send;
+
+    /// Like [`get`](Self::get), but also finds a soft-deleted document.
+    fn get_including_deleted(
+        &self,
+        dataset_id: Uuid,
+        document_id: &str,
+    ) -> impl Future<Output = Result<Option<DocumentRow>, sqlx::Error>> + Send;
+
+    /// Like [`list_by_type`](Self::list_by_type), but also lists soft-deleted
+    /// documents.
+    fn list_including_deleted(
+        &self,
+        dataset_id: Uuid,
+        types: Option<&[String]>,
+    ) -> impl Future<Output = Result<Vec<DocumentRow>, sqlx::Error>> + Send;
+
+    /// Live documents whose content holds a strong `_ref` to `target_id`,
+    /// ordered by id.
+    fn referencing(
+        &self,
+        dataset_id: Uuid,
+        target_id: &str,
+    ) -> impl Future<Output = Result<Vec<DocumentRow>, sqlx::Error>> + Send;
+
+    fn begin(&self) -> impl Future<Output = Result<Self::Transaction, sqlx::Error>> + Send;
+}
+
+/// A unit of work: documents read through it stay locked until it ends, and
+/// its writes become visible together on [`commit`](Self::commit).
+/// Dropping it without committing discards the writes.
+pub trait DocumentTransaction: Send {
+    /// Load a document, soft-deleted or not.
+    fn get(
+        &mut self,
+        dataset_id: Uuid,
+        document_id: &str,
+    ) -> impl Future<Output = Result<Option<DocumentRow>, sqlx::Error>> + Send;
+
+    /// All live documents ordered by id, optionally restricted to `types`.
+    fn list_by_type(
+        &mut self,
+        dataset_id: Uuid,
+        types: Option<&[String]>,
+    ) -> impl Future<Output = Result<Vec<DocumentRow>, sqlx::Error>> + Send;
+
+    /// Ids of live documents whose stored content holds a strong `_ref` to
+    /// `target_id`, locking them until the transaction ends.
+    fn referencing(
+        &mut self,
+        dataset_id: Uuid,
+        target_id: &str,
+    ) -> impl Future<Output = Result<Vec<String>, sqlx::Error>> + Send;
+
+    /// Insert or overwrite a document, reviving it if it was soft-deleted.
+    fn upsert(
+        &mut self,
+        dataset_id: Uuid,
+        document_id: &str,
+        doc_type: &str,
+        revision: &str,
+        content: &Value,
+        now: DateTime<Utc>,
+    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send;
+
+    /// Soft-delete a document, stamping it with the deleting revision.
+    fn soft_delete(
+        &mut self,
+        dataset_id: Uuid,
+        document_id: &str,
+        revision: &str,
+        now: DateTime<Utc>,
+    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send;
+
+    /// Append the transaction to the dataset's history log.
+    fn record(
+        &mut self,
+        dataset_id: Uuid,
+        transaction_id: &str,
+        mutations: &[Mutation],
+        changes: &[DocumentChange],
+        timestamp: DateTime<Utc>,
+    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send;
+
+    fn commit(self) -> impl Future<Output = Result<(), sqlx::Error>> + Send;
+
+    /// Discard the transaction's writes.
+    fn rollback(self) -> impl Future<Output = Result<(), sqlx::Error>> + Send;
+}
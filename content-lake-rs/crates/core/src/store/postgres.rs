@@ -0,0 +1,563 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use content_lake_groq::functions::referenced_ids;
+use content_lake_groq::sql_gen::{SqlBind, SqlFilter};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use serde_json::Value;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::{DocumentStore, DocumentTransaction};
+use crate::document::model::DocumentRow;
+use crate::history::log::record_transaction;
+use crate::mutation::executor::DocumentChange;
+use crate::mutation::types::Mutation;
+
+const SELECT_DOCUMENT: &str = "SELECT id, dataset_id, document_id, doc_type, revision, content, \
+     created_at, updated_at, deleted FROM documents";
+
+const LIST_DOCUMENTS: &str = "SELECT id, dataset_id, document_id, doc_type, revision, content, \
+     created_at, updated_at, deleted FROM documents \
+     WHERE dataset_id = $1 AND (deleted = false OR $3) \
+     AND ($2::text[] IS NULL OR doc_type = ANY($2)) \
+     ORDER BY document_id";
+
+/// Rows fetched per statement by a [`stream`](PgDocumentStore::stream)
+/// under a statement timeout.
+const STREAM_BATCH: usize = 500;
+
+/// Document store over the `documents` table.
+#[derive(Clone)]
+pub struct PgDocumentStore {
+    pub(super) pool: PgPool,
+    statement_timeout: Option<Duration>,
+}
+
+impl PgDocumentStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            statement_timeout: None,
+        }
+    }
+
+    /// Cancel each statement of a dataset scan (listing, streaming or
+    /// counting documents) that runs longer than `timeout`, failing it with
+    /// the `query_canceled` error. `None` lets scans run indefinitely.
+    pub fn with_statement_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.statement_timeout = timeout;
+        self
+    }
+
+    /// Like [`list_by_type`](DocumentStore::list_by_type), but yields rows
+    /// as the cursor reads them. Soft-deleted rows are included if
+    /// `include_deleted` is set.
+    pub fn stream(
+        &self,
+        dataset_id: Uuid,
+        types: Option<Vec<String>>,
+        include_deleted: bool,
+    ) -> BoxStream<'_, Result<DocumentRow, sqlx::Error>> {
+        let Some(timeout) = self.statement_timeout else {
+            return sqlx::query_as(LIST_DOCUMENTS)
+                .bind(dataset_id)
+                .bind(types)
+                .bind(include_deleted)
+                .fetch(&self.pool);
+        };
+        // Read through a cursor a batch per statement, so the timeout bounds
+        // each fetch rather than the whole stream, however slowly it is
+        // consumed.
+        let batches = stream::try_unfold(None, move |tx: Option<Transaction<'_, Postgres>>| {
+            let types = types.clone();
+            async move {
+                let mut tx = match tx {
+                    Some(tx) => tx,
+                    None => {
+                        let mut tx = self.timed_transaction(timeout).await?;
+                        sqlx::query(&format!(
+                            "DECLARE scan NO SCROLL CURSOR FOR {LIST_DOCUMENTS}"
+                        ))
+                        .bind(dataset_id)
+                        .bind(types)
+                        .bind(include_deleted)
+                        .execute(&mut *tx)
+                        .await?;
+                        tx
+                    }
+                };
+                let rows: Vec<DocumentRow> =
+                    sqlx::query_as(&format!("FETCH {STREAM_BATCH} FROM scan"))
+                        .fetch_all(&mut *tx)
+                        .await?;
+                if rows.is_empty() {
+                    tx.commit().await?;
+                    return Ok::<_, sqlx::Error>(None);
+                }
+                Ok(Some((rows, Some(tx))))
+            }
+        });
+        batches
+            .map_ok(|rows| stream::iter(rows.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+
+    /// Documents of a dataset, optionally of the given types, in id order.
+    async fn list(
+        &self,
+        dataset_id: Uuid,
+        types: Option<&[String]>,
+        include_deleted: bool,
+    ) -> Result<Vec<DocumentRow>, sqlx::Error> {
+        let query = sqlx::query_as(LIST_DOCUMENTS)
+            .bind(dataset_id)
+            .bind(types)
+            .bind(include_deleted);
+        let Some(timeout) = self.statement_timeout else {
+            return query.fetch_all(&self.pool).await;
+        };
+        let mut tx = self.timed_transaction(timeout).await?;
+        let rows = query.fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// A transaction whose statements are cancelled after `timeout`.
+    async fn timed_transaction(
+        &self,
+        timeout: Duration,
+    ) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(&format!(
+            "SET LOCAL statement_timeout = {}",
+            timeout.as_millis().max(1)
+        ))
+        .execute(&mut *tx)
+        .await?;
+        Ok(tx)
+    }
+
+    /// Count the documents matching `filter`, whose placeholders must start
+    /// at `$3`. Soft-deleted rows are counted if `include_deleted` is set.
+    pub async fn count_matching(
+        &self,
+        dataset_id: Uuid,
+        filter: &SqlFilter,
+        include_deleted: bool,
+    ) -> Result<i64, sqlx::Error> {
+        let sql = Self::count_sql(filter);
+        let mut query = sqlx::query_scalar(&sql)
+            .bind(dataset_id)
+            .bind(include_deleted);
+        for value in &filter.binds {
+            query = match value {
+                SqlBind::Text(text) => query.bind(text),
+                SqlBind::TextArray(path) => query.bind(path),
+            };
+        }
+        let Some(timeout) = self.statement_timeout else {
+            return query.fetch_one(&self.pool).await;
+        };
+        let mut tx = self.timed_transaction(timeout).await?;
+        let count = query.fetch_one(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(count)
+    }
+
+    /// The statement [`count_matching`](Self::count_matching) runs.
+    pub fn count_sql(filter: &SqlFilter) -> String {
+        format!(
+            "SELECT count(*) FROM documents \
+             WHERE dataset_id = $1 AND (deleted = false OR $2) AND {}",
+            filter.sql
+        )
+    }
+}
+
+impl DocumentStore for PgDocumentStore {
+    type Transaction = PgDocumentTransaction;
+
+    async fn get(
+        &self,
+        dataset_id: Uuid,
+        document_id: &str,
+    ) -> Result<Option<DocumentRow>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            "{SELECT_DOCUMENT} WHERE dataset_id = $1 AND document_id = $2 AND deleted = false"
+        ))
+        .bind(dataset_id)
+        .bind(document_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn list_by_type(
+        &self,
+        dataset_id: Uuid,
+        types: Option<&[String]>,
+    ) -> Result<Vec<DocumentRow>, sqlx::Error> {
+        self.list(dataset_id, types, false).await
+    }
+
+    async fn get_including_deleted(
+        &self,
+        dataset_id: Uuid,
+        document_id: &str,
+    ) -> Result<Option<DocumentRow>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            "{SELECT_DOCUMENT} WHERE dataset_id = $1 AND document_id = $2"
+        ))
+        .bind(dataset_id)
+        .bind(document_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn list_including_deleted(
+        &self,
+        dataset_id: Uuid,
+        types: Option<&[String]>,
+    ) -> Result<Vec<DocumentRow>, sqlx::Error> {
+        self.list(dataset_id, types, true).await
+    }
+
+    async fn referencing(
+        &self,
+        dataset_id: Uuid,
+        target_id: &str,
+    ) -> Result<Vec<DocumentRow>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            "{SELECT_DOCUMENT} WHERE dataset_id = $1 AND deleted = false AND document_id IN \
+             (SELECT document_id FROM document_references WHERE dataset_id = $1 AND target_id = $2) \
+             ORDER BY document_id"
+        ))
+        .bind(dataset_id)
+        .bind(target_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn begin(&self) -> Result<PgDocumentTransaction, sqlx::Error> {
+        Ok(PgDocumentTransaction {
+            tx: self.pool.begin().await?,
+        })
+    }
+}
+
+/// An SQL transaction; rows it reads are locked with `FOR UPDATE`.
+pub struct PgDocumentTransaction {
+    tx: sqlx::Transaction<'static, Postgres>,
+}
+
+impl PgDocumentTransaction {
+    /// Drop a document's rows from the reference index.
+    async fn clear_references(
+        &mut self,
+        dataset_id: Uuid,
+        document_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM document_references WHERE dataset_id = $1 AND document_id = $2")
+            .bind(dataset_id)
+            .bind(document_id)
+            .execute(&mut *self.tx)
+            .await?;
+        Ok(())
+    }
+}
+
+impl DocumentTransaction for PgDocumentTransaction {
+    async fn get(
+        &mut self,
+        dataset_id: Uuid,
+        document_id: &str,
+    ) -> Result<Option<DocumentRow>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            "{SELECT_DOCUMENT} WHERE dataset_id = $1 AND document_id = $2 FOR UPDATE"
+        ))
+        .bind(dataset_id)
+        .bind(document_id)
+        .fetch_optional(&mut *self.tx)
+        .await
+    }
+
+    async fn list_by_type(
+        &mut self,
+        dataset_id: Uuid,
+        types: Option<&[String]>,
+    ) -> Result<Vec<DocumentRow>, sqlx::Error> {
+        sqlx::query_as(&format!("{LIST_DOCUMENTS} FOR UPDATE"))
+            .bind(dataset_id)
+            .bind(types)
+            .bind(false)
+            .fetch_all(&mut *self.tx)
+            .await
+    }
+
+    async fn referencing(
+        &mut self,
+        dataset_id: Uuid,
+        target_id: &str,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT d.document_id FROM documents d \
+             JOIN document_references r \
+             ON r.dataset_id = d.dataset_id AND r.document_id = d.document_id \
+             WHERE d.dataset_id = $1 AND r.target_id = $2 AND d.deleted = false \
+             ORDER BY d.document_id FOR UPDATE OF d",
+        )
+        .bind(dataset_id)
+        .bind(target_id)
+        .fetch_all(&mut *self.tx)
+        .await
+    }
+
+    async fn upsert(
+        &mut self,
+        dataset_id: Uuid,
+        document_id: &str,
+        doc_type: &str,
+        revision: &str,
+        content: &Value,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO documents \
+             (dataset_id, document_id, doc_type, revision, content, created_at, updated_at, deleted) \
+             VALUES ($1, $2, $3, $4, $5, $6, $6, false) \
+             ON CONFLICT (dataset_id, document_id) DO UPDATE SET \
+             doc_type = EXCLUDED.doc_type, revision = EXCLUDED.revision, \
+             content = EXCLUDED.content, updated_at = EXCLUDED.updated_at, deleted = false",
+        )
+        .bind(dataset_id)
+        .bind(document_id)
+        .bind(doc_type)
+        .bind(revision)
+        .bind(content)
+        .bind(now)
+        .execute(&mut *self.tx)
+        .await?;
+
+        self.clear_references(dataset_id, document_id).await?;
+        let targets: Vec<String> = referenced_ids(content).into_iter().collect();
+        // Share-lock the targets: a transaction deleting one holds it `FOR
+        // UPDATE`, so whichever comes second waits and then sees the other's
+        // reference or deletion.
+        sqlx::query(
+            "SELECT 1 FROM documents WHERE dataset_id = $1 AND document_id = ANY($2) \
+             FOR SHARE",
+        )
+        .bind(dataset_id)
+        .bind(&targets)
+        .execute(&mut *self.tx)
+        .await?;
+        sqlx::query(
+            "INSERT INTO document_references (dataset_id, document_id, target_id) \
+             SELECT $1, $2, unnest($3::text[])",
+        )
+        .bind(dataset_id)
+        .bind(document_id)
+        .bind(targets)
+        .execute(&mut *self.tx)
+        .await?;
+        Ok(())
+    }
+
+    async fn soft_delete(
+        &mut self,
+        dataset_id: Uuid,
+        document_id: &str,
+        revision: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE documents SET deleted = true, revision = $3, updated_at = $4 \
+             WHERE dataset_id = $1 AND document_id = $2",
+        )
+        .bind(dataset_id)
+        .bind(document_id)
+        .bind(revision)
+        .bind(now)
+        .execute(&mut *self.tx)
+        .await?;
+        self.clear_references(dataset_id, document_id).await
+    }
+
+    async fn record(
+        &mut self,
+        dataset_id: Uuid,
+        transaction_id: &str,
+        mutations: &[Mutation],
+        changes: &[DocumentChange],
+        timestamp: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        record_transaction(
+            &mut self.tx,
+            dataset_id,
+            transaction_id,
+            mutations,
+            changes,
+            timestamp,
+        )
+        .await
+    }
+
+    async fn commit(self) -> Result<(), sqlx::Error> {
+        self.tx.commit().await
+    }
+
+    async fn rollback(self) -> Result<(), sqlx::Error> {
+        self.tx.rollback().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_dataset, test_pool};
+    use serde_json::json;
+
+    async fn put(store: &PgDocumentStore, dataset_id: Uuid, id: &str, title: &str) {
+        let mut tx = store.begin().await.unwrap();
+        tx.upsert(
+            dataset_id,
+            id,
+            "post",
+            "r1",
+            &json!({"title": title}),
+            Utc::now(),
+        )
+        .await
+        .unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reads_never_cross_datasets() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let store = PgDocumentStore::new(pool.clone());
+        let a = create_dataset(&pool).await;
+        let b = create_dataset(&pool).await;
+        put(&store, a, "shared", "in A").await;
+        put(&store, b, "shared", "in B").await;
+        put(&store, b, "only-b", "in B").await;
+
+        let rows = store.list_by_type(a, None).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].dataset_id, a);
+        assert_eq!(rows[0].content["title"], "in A");
+
+        assert!(store.get(a, "only-b").await.unwrap().is_none());
+        let shared = store.get(b, "shared").await.unwrap().unwrap();
+        assert_eq!(shared.content["title"], "in B");
+    }
+
+    #[tokio::test]
+    async fn timed_scans_read_in_batches_and_cancel_slow_statements() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let store = PgDocumentStore::new(pool.clone())
+            .with_statement_timeout(Some(Duration::from_millis(50)));
+        let dataset_id = create_dataset(&pool).await;
+        for n in 0..STREAM_BATCH + 3 {
+            put(&store, dataset_id, &format!("doc-{n:04}"), "t").await;
+        }
+
+        let streamed: Vec<_> = store
+            .stream(dataset_id, None, false)
+            .try_collect()
+            .await
+            .unwrap();
+        let listed = store.list_by_type(dataset_id, None).await.unwrap();
+        assert_eq!(streamed.len(), STREAM_BATCH + 3);
+        assert_eq!(
+            streamed.iter().map(|r| &r.document_id).collect::<Vec<_>>(),
+            listed.iter().map(|r| &r.document_id).collect::<Vec<_>>()
+        );
+
+        let slow = SqlFilter {
+            sql: "(pg_sleep(0.5) IS NOT NULL)".into(),
+            binds: vec![],
+        };
+        let err = store
+            .count_matching(dataset_id, &slow, false)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.as_database_error().and_then(|e| e.code()).as_deref(),
+            Some("57014")
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_is_scoped_to_dataset() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let store = PgDocumentStore::new(pool.clone());
+        let a = create_dataset(&pool).await;
+        let b = create_dataset(&pool).await;
+        put(&store, a, "doc", "in A").await;
+        put(&store, b, "doc", "in B").await;
+
+        let mut tx = store.begin().await.unwrap();
+        tx.soft_delete(a, "doc", "r2", Utc::now()).await.unwrap();
+        tx.commit().await.unwrap();
+
+        assert!(store.get(a, "doc").await.unwrap().is_none());
+        assert!(store.get(b, "doc").await.unwrap().is_some());
+        assert_eq!(
+            store
+                .list_by_type(b, Some(&["post".to_string()]))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn reference_index_follows_writes() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let store = PgDocumentStore::new(pool.clone());
+        let dataset_id = create_dataset(&pool).await;
+        let write = |id: &'static str, content: Value| {
+            let store = store.clone();
+            async move {
+                let mut tx = store.begin().await.unwrap();
+                tx.upsert(dataset_id, id, "post", "r1", &content, Utc::now())
+                    .await
+                    .unwrap();
+                tx.commit().await.unwrap();
+            }
+        };
+        let referencing = |target: &'static str| {
+            let store = store.clone();
+            async move {
+                let rows = store.referencing(dataset_id, target).await.unwrap();
+                rows.into_iter().map(|r| r.document_id).collect::<Vec<_>>()
+            }
+        };
+
+        write("a", json!({"author": {"_ref": "person"}})).await;
+        write("b", json!({"body": [{"_ref": "person"}, {"_ref": "tag"}]})).await;
+        assert_eq!(referencing("person").await, ["a", "b"]);
+        assert_eq!(referencing("tag").await, ["b"]);
+
+        write("b", json!({"body": [{"_ref": "tag"}]})).await;
+        assert_eq!(referencing("person").await, ["a"]);
+
+        let mut tx = store.begin().await.unwrap();
+        tx.soft_delete(dataset_id, "a", "r2", Utc::now())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        assert!(referencing("person").await.is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+//! Shared helpers for database-backed tests.
+//!
+//! Tests that need PostgreSQL call [`test_pool`] and return early when
+//! `DATABASE_URL` is unset, so `cargo test` stays green without a database.
+
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Connect to `DATABASE_URL` and apply migrations, or `None` to skip the test.
+pub async fn test_pool() -> Option<PgPool> {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL not set; skipping database test");
+        return None;
+    };
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect(&url)
+        .await
+        .expect("failed to connect to test database");
+    sqlx::migrate!("../../migrations")
+        .run(&pool)
+        .await
+        .expect("failed to run migrations");
+    Some(pool)
+}
+
+/// Create a fresh project and dataset so each test is isolated.
+pub async fn create_dataset(pool: &PgPool) -> Uuid {
+    let project_id: Uuid =
+        sqlx::query_scalar("INSERT INTO projects (name) VALUES ($1) RETURNING id")
+            .bind(format!("test-{}", Uuid::new_v4()))
+            .fetch_one(pool)
+            .await
+            .expect("failed to create project");
+    sqlx::query_scalar("INSERT INTO datasets (project_id, name) VALUES ($1, $2) RETURNING id")
+        .bind(project_id)
+        .bind(format!("ds-{}", Uuid::new_v4()))
+        .fetch_one(pool)
+        .await
+        .expect("failed to create dataset")
+}
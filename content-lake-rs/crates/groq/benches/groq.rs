@@ -0,0 +1,125 @@
+use content_lake_groq::eval::{eval_filter, eval_query};
+use content_lake_groq::lexer::tokenize;
+use content_lake_groq::parser::parse;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use serde_json::{json, Value};
+
+const QUERIES: &[(&str, &str)] = &[
+    ("simple", r#"*[_type == "post"]"#),
+    (
+        "projection",
+        r#"*[_type == "post" && published == true]{title, "slug": slug.current, author}"#,
+    ),
+    (
+        "ordered",
+        r#"*[_type == "post" && meta.author._ref == "user-1"] | order(publishedAt desc)"#,
+    ),
+];
+
+/// Build `count` documents whose `meta` object carries `body_len` paragraphs.
+fn documents(count: usize, body_len: usize) -> Vec<Value> {
+    (0..count)
+        .map(|i| {
+            let body: Vec<Value> = (0..body_len)
+                .map(|j| json!({"_key": format!("k{j}"), "text": "Lorem ipsum dolor sit amet"}))
+                .collect();
+            json!({
+                "_id": format!("doc-{i}"),
+                "_type": if i % 2 == 0 { "post" } else { "page" },
+                "title": format!("Document {i}"),
+                "published": i % 3 == 0,
+                "meta": {
+                    "author": {"_ref": format!("user-{}", i % 10)},
+                    "body": body,
+                },
+            })
+        })
+        .collect()
+}
+
+fn bench_tokenize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tokenize");
+    for (name, query) in QUERIES {
+        group.bench_with_input(BenchmarkId::from_parameter(name), query, |b, q| {
+            b.iter(|| tokenize(black_box(q)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for (name, query) in QUERIES {
+        group.bench_with_input(BenchmarkId::from_parameter(name), query, |b, q| {
+            b.iter(|| parse(black_box(q)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_eval_filter(c: &mut Criterion) {
+    let filter = match parse(r#"*[_type == "post" && meta.author._ref == "user-2"]"#).unwrap() {
+        content_lake_groq::ast::Expr::Pipeline(mut stages) => match stages.remove(1) {
+            content_lake_groq::ast::Expr::Filter(inner) => *inner,
+            other => panic!("expected filter, got {other:?}"),
+        },
+        other => panic!("expected pipeline, got {other:?}"),
+    };
+    let params = json!({});
+
+    let mut group = c.benchmark_group("eval_filter");
+    for (docs, body_len) in [(1_000, 0), (1_000, 50)] {
+        let documents = documents(docs, body_len);
+        group.bench_with_input(
+            BenchmarkId::new(format!("{docs}_docs"), format!("body_{body_len}")),
+            &documents,
+            |b, documents| {
+                b.iter(|| {
+                    documents
+                        .iter()
+                        .filter(|doc| eval_filter(&filter, doc, &params).unwrap())
+                        .count()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_eval_query(c: &mut Criterion) {
+    let queries = [
+        (
+            "filter_projection",
+            r#"*[_type == "post" && published == true]{_id, title, "author": meta.author._ref}"#,
+        ),
+        (
+            "references",
+            r#"*[_type == "post" && references("user-2")]{_id, "paragraphs": count(meta.body)}"#,
+        ),
+        (
+            "ordered",
+            r#"*[_type == "post"] | order(title desc)[0...10]{_id}"#,
+        ),
+    ];
+    let params = json!({});
+    let documents = documents(10_000, 20);
+
+    let mut group = c.benchmark_group("eval_query");
+    group.sample_size(20);
+    for (name, query) in queries {
+        let expr = parse(query).unwrap();
+        group.bench_with_input(BenchmarkId::new("10000_docs", name), &expr, |b, expr| {
+            b.iter(|| eval_query(expr, black_box(&documents), &params).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_tokenize,
+    bench_parse,
+    bench_eval_filter,
+    bench_eval_query
+);
+criterion_main!(benches);
@@ -0,0 +1,12 @@
+#![no_main]
+
+use content_lake_groq::parser::parse;
+use libfuzzer_sys::fuzz_target;
+
+// Any input must parse or fail with a `ParseError`, never panic, and the
+// error must render against the input it came from.
+fuzz_target!(|input: &str| {
+    if let Err(err) = parse(input) {
+        let _ = err.render(input);
+    }
+});
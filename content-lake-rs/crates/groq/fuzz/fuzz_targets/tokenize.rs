@@ -0,0 +1,9 @@
+#![no_main]
+
+use content_lake_groq::lexer::tokenize;
+use libfuzzer_sys::fuzz_target;
+
+// Any input must tokenize or fail with a `LexError`, never panic.
+fuzz_target!(|input: &str| {
+    let _ = tokenize(input);
+});
@@ -0,0 +1,125 @@
+//! Static analysis of GROQ expressions, used to reject pathological queries
+//! before they are evaluated.
+
+use crate::ast::Expr;
+
+/// Structural cost of a query.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Complexity {
+    /// Total number of AST nodes.
+    pub nodes: u32,
+    /// Deepest nesting of projections and object literals.
+    pub projection_depth: u32,
+    /// Number of `->` dereferences, each of which is a document lookup.
+    pub dereferences: u32,
+    /// Longest pipeline, in stages.
+    pub pipeline_length: u32,
+}
+
+impl Complexity {
+    /// Single score compared against the configured budget. Nesting and
+    /// dereferences are weighted above plain node count since they multiply
+    /// the work done per document.
+    pub fn score(&self) -> u32 {
+        self.nodes
+            .saturating_add(self.projection_depth.saturating_mul(10))
+            .saturating_add(self.dereferences.saturating_mul(5))
+            .saturating_add(self.pipeline_length.saturating_mul(2))
+    }
+}
+
+/// Score the complexity of an expression by recursing its AST.
+pub fn complexity(expr: &Expr) -> Complexity {
+    let mut acc = Complexity::default();
+    visit(expr, 0, &mut acc);
+    acc
+}
+
+fn visit(expr: &Expr, depth: u32, acc: &mut Complexity) {
+    acc.nodes = acc.nodes.saturating_add(1);
+    match expr {
+        Expr::StringLiteral(_)
+        | Expr::IntLiteral(_)
+        | Expr::FloatLiteral(_)
+        | Expr::BoolLiteral(_)
+        | Expr::Null
+        | Expr::Ident(_)
+        | Expr::This
+        | Expr::Parent(_)
+        | Expr::Everything
+        | Expr::Param(_) => {}
+        Expr::Array(items) => items.iter().for_each(|item| visit(item, depth, acc)),
+        Expr::Object(fields) | Expr::Projection(fields) => {
+            let depth = depth + 1;
+            acc.projection_depth = acc.projection_depth.max(depth);
+            fields
+                .iter()
+                .for_each(|(_, value)| visit(value, depth, acc));
+        }
+        Expr::Deref(base, _) | Expr::DerefDocument(base) => {
+            acc.dereferences = acc.dereferences.saturating_add(1);
+            visit(base, depth, acc);
+        }
+        Expr::DotAccess(base, _)
+        | Expr::Iterate(base)
+        | Expr::Not(base)
+        | Expr::Filter(base)
+        | Expr::Order(base, _)
+        | Expr::Slice(base, _, _)
+        | Expr::Index(base, _)
+        | Expr::ParamSlice(base, _, _, _) => visit(base, depth, acc),
+        Expr::Eq(l, r)
+        | Expr::Neq(l, r)
+        | Expr::Lt(l, r)
+        | Expr::Gt(l, r)
+        | Expr::Lte(l, r)
+        | Expr::Gte(l, r)
+        | Expr::In(l, r)
+        | Expr::Match(l, r)
+        | Expr::And(l, r)
+        | Expr::Or(l, r) => {
+            visit(l, depth, acc);
+            visit(r, depth, acc);
+        }
+        Expr::Pipeline(stages) => {
+            acc.pipeline_length = acc.pipeline_length.max(stages.len() as u32);
+            stages.iter().for_each(|stage| visit(stage, depth, acc));
+        }
+        Expr::FuncCall(_, args) => args.iter().for_each(|arg| visit(arg, depth, acc)),
+        Expr::Select(branches) => {
+            for (cond, value) in branches {
+                if let Some(cond) = cond {
+                    visit(cond, depth, acc);
+                }
+                visit(value, depth, acc);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn simple_query_is_cheap() {
+        let c = complexity(&parse(r#"*[_type == "post"]{title, "author": author->name}"#).unwrap());
+        assert_eq!(c.projection_depth, 1);
+        assert_eq!(c.dereferences, 1);
+        assert_eq!(c.pipeline_length, 3);
+        assert!(c.score() < 50, "score {}", c.score());
+    }
+
+    #[test]
+    fn deep_nesting_scores_high() {
+        let mut query = String::from("x");
+        for i in 0..12 {
+            query = format!(r#"{{"l{i}": {query}, "r": a->b}}"#);
+        }
+        let c = complexity(&parse(&format!("*[_type == \"post\"]{query}")).unwrap());
+        assert_eq!(c.projection_depth, 12);
+        assert_eq!(c.dereferences, 12);
+        assert!(c.score() > 150, "score {}", c.score());
+    }
+}
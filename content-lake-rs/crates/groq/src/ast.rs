@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 
 /// GROQ Abstract Syntax Tree types.
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Expr {
     // Literals
     StringLiteral(String),
@@ -11,13 +11,20 @@ pub enum Expr {
     BoolLiteral(bool),
     Null,
     Array(Vec<Expr>),
+    /// Object literal `{ "a": 1, "b": field }` used as a value.
+    Object(Vec<(String, Expr)>),
 
     // Identifiers & access
     Ident(String),
     DotAccess(Box<Expr>, String),
     Deref(Box<Expr>, String),
+    /// `expr->`: the whole referenced document.
+    DerefDocument(Box<Expr>),
+    /// `expr[]`: iterate over the elements of an array.
+    Iterate(Box<Expr>),
     This,
-    Parent,
+    /// `^`, `^.^`, ...: the document this many scopes out from `@`.
+    Parent(usize),
 
     // Comparison operators
     Eq(Box<Expr>, Box<Expr>),
@@ -27,6 +34,9 @@ pub enum Expr {
     Lte(Box<Expr>, Box<Expr>),
     Gte(Box<Expr>, Box<Expr>),
     In(Box<Expr>, Box<Expr>),
+    /// `text match pattern`: full-text match of words, `*` matching any
+    /// word suffix.
+    Match(Box<Expr>, Box<Expr>),
 
     // Logical operators
     And(Box<Expr>, Box<Expr>),
@@ -39,11 +49,26 @@ pub enum Expr {
     Projection(Vec<(String, Expr)>),
     Pipeline(Vec<Expr>),
     Order(Box<Expr>, bool),
+    /// `[start...end]` over the base's results, with an exclusive end.
+    /// Negative bounds count from the end; [`SLICE_TO_END`] as the end means
+    /// the slice runs to the last element.
     Slice(Box<Expr>, i64, i64),
+    /// `[n]`: the single element at index `n`, counting from the end when
+    /// negative.
+    Index(Box<Expr>, i64),
+    /// Slice with a `$param` bound, e.g. `[0...$limit]`: base, start, end,
+    /// and whether the end is inclusive. Lowered to `Slice` by
+    /// [`params::bind`](crate::params::bind) once parameter values are known.
+    ParamSlice(Box<Expr>, Box<Expr>, Box<Expr>, bool),
 
     // Function call
     FuncCall(String, Vec<Expr>),
+    /// `select(cond => value, ..., default)`; a `None` condition is the default.
+    Select(Vec<(Option<Expr>, Expr)>),
 
     // Parameter reference ($param)
     Param(String),
 }
+
+/// Exclusive end of a slice with no end bound, e.g. `[2...]`.
+pub const SLICE_TO_END: i64 = i64::MAX;
@@ -1,7 +1,12 @@
 // GROQ in-memory evaluator (for grant filter evaluation).
 // Will be fully implemented in Phase 2.
 
+use std::borrow::{Borrow, Cow};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
 use crate::ast::Expr;
+use crate::functions::{call_builtin_ref, text_match};
 use serde_json::Value;
 
 #[derive(Debug, thiserror::Error)]
@@ -12,48 +17,628 @@ pub enum EvalError {
     Unsupported,
 }
 
-pub fn eval_filter(expr: &Expr, doc: &Value, params: &Value) -> Result<bool, EvalError> {
-    match eval_expr(expr, doc, params)? {
-        Value::Bool(b) => Ok(b),
-        _ => Ok(false),
+/// Shared `null` returned by reference for missing fields.
+static NULL: Value = Value::Null;
+
+/// Options controlling how projections build their result objects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProjectionOptions {
+    /// Drop keys whose value is undefined (`null`) instead of emitting them.
+    pub omit_undefined: bool,
+    /// Drop unresolvable references from `refs[]->` results instead of
+    /// mapping them to `null`.
+    pub drop_missing_references: bool,
+    /// Carry the projected document's system fields (keys starting with
+    /// `_`) into the result even when the projection doesn't name them. A
+    /// key the projection does name takes its projected value instead.
+    pub include_system_fields: bool,
+}
+
+/// Loads referenced documents by id so `->` can be evaluated in memory.
+pub trait DocumentResolver {
+    fn resolve(&self, ref_id: &str) -> Option<Value>;
+}
+
+/// Resolver that knows no documents; every dereference yields `null`.
+pub struct NoResolver;
+
+impl DocumentResolver for NoResolver {
+    fn resolve(&self, _ref_id: &str) -> Option<Value> {
+        None
+    }
+}
+
+impl DocumentResolver for HashMap<String, Value> {
+    fn resolve(&self, ref_id: &str) -> Option<Value> {
+        self.get(ref_id).cloned()
     }
 }
 
+/// A document enclosing the current one, and the scopes enclosing it.
+#[derive(Clone, Copy)]
+struct Scope<'a> {
+    doc: &'a Value,
+    parent: Option<&'a Scope<'a>>,
+}
+
+/// Everything an evaluation needs besides the expression and current document.
+#[derive(Clone, Copy)]
+pub struct EvalContext<'a> {
+    pub params: &'a Value,
+    pub options: ProjectionOptions,
+    pub resolver: &'a dyn DocumentResolver,
+    /// What `^` refers to; `None` at the top level of a query.
+    scope: Option<&'a Scope<'a>>,
+}
+
+impl<'a> EvalContext<'a> {
+    /// Context with default options and no reference resolution.
+    pub fn new(params: &'a Value) -> Self {
+        Self {
+            params,
+            options: ProjectionOptions::default(),
+            resolver: &NoResolver,
+            scope: None,
+        }
+    }
+
+    pub fn with_options(mut self, options: ProjectionOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_resolver(mut self, resolver: &'a dyn DocumentResolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Context for evaluating inside a value of `scope.doc`, where `^`
+    /// refers to `scope.doc`.
+    fn nested<'b>(&self, scope: &'b Scope<'b>) -> EvalContext<'b>
+    where
+        'a: 'b,
+    {
+        EvalContext {
+            params: self.params,
+            options: self.options,
+            resolver: self.resolver,
+            scope: Some(scope),
+        }
+    }
+
+    /// The document `levels` scopes out, or `null` past the outermost.
+    fn parent(&self, levels: usize) -> &'a Value {
+        let mut scope = self.scope;
+        for _ in 1..levels {
+            scope = scope.and_then(|s| s.parent);
+        }
+        scope.map_or(&NULL, |s| s.doc)
+    }
+}
+
+pub fn eval_filter(expr: &Expr, doc: &Value, params: &Value) -> Result<bool, EvalError> {
+    eval_bool(expr, doc, &EvalContext::new(params))
+}
+
 pub fn eval_expr(expr: &Expr, doc: &Value, params: &Value) -> Result<Value, EvalError> {
+    eval_expr_in(expr, doc, &EvalContext::new(params))
+}
+
+/// [`eval_expr`] with an explicit evaluation context.
+pub fn eval_expr_in(expr: &Expr, doc: &Value, ctx: &EvalContext<'_>) -> Result<Value, EvalError> {
+    eval_ref(expr, doc, ctx).map(Cow::into_owned)
+}
+
+/// Evaluate a whole query against a document set.
+///
+/// `*` and pipelines produce the matching documents as an array, or a single
+/// document (`null` if there is none) when indexed with `[n]`; `count(...)`
+/// over such a query returns the number of matches. Any other expression is
+/// evaluated once with no current document.
+pub fn eval_query(expr: &Expr, docs: &[Value], params: &Value) -> Result<Value, EvalError> {
+    eval_query_in(expr, docs, &EvalContext::new(params))
+}
+
+/// [`eval_query`] with an explicit evaluation context.
+pub fn eval_query_in(
+    expr: &Expr,
+    docs: &[Value],
+    ctx: &EvalContext<'_>,
+) -> Result<Value, EvalError> {
     match expr {
-        Expr::Everything => Ok(Value::Bool(true)),
-        Expr::BoolLiteral(b) => Ok(Value::Bool(*b)),
-        Expr::IntLiteral(n) => Ok(Value::Number((*n).into())),
-        Expr::StringLiteral(s) => Ok(Value::String(s.clone())),
-        Expr::Null => Ok(Value::Null),
-        Expr::Ident(name) => Ok(doc.get(name).cloned().unwrap_or(Value::Null)),
-        Expr::DotAccess(base, field) => {
-            let v = eval_expr(base, doc, params)?;
-            Ok(v.get(field).cloned().unwrap_or(Value::Null))
-        }
-        Expr::Param(name) => Ok(params.get(name).cloned().unwrap_or(Value::Null)),
-        Expr::This => Ok(doc.clone()),
-        Expr::Eq(l, r) => {
-            let lv = eval_expr(l, doc, params)?;
-            let rv = eval_expr(r, doc, params)?;
-            Ok(Value::Bool(lv == rv))
+        _ if is_query(expr) => Ok(single_or_array(expr, eval_pipeline(expr, docs, ctx)?)),
+        Expr::FuncCall(name, args) if name == "count" && args.len() == 1 && is_query(&args[0]) => {
+            let matched = eval_pipeline(&args[0], docs, ctx)?;
+            Ok(Value::Number(matched.len().into()))
         }
-        Expr::Neq(l, r) => {
-            let lv = eval_expr(l, doc, params)?;
-            let rv = eval_expr(r, doc, params)?;
-            Ok(Value::Bool(lv != rv))
+        _ => eval_expr_in(expr, &NULL, ctx),
+    }
+}
+
+/// Whether a pipeline ends up picking one element with an index stage.
+fn picks_element(expr: &Expr) -> bool {
+    match expr {
+        Expr::Pipeline(stages) => stages.iter().any(|stage| match stage {
+            Expr::Index(..) => true,
+            Expr::Pipeline(_) => picks_element(stage),
+            _ => false,
+        }),
+        _ => false,
+    }
+}
+
+/// The results of `pipeline`: the element it picked, or the whole array.
+fn single_or_array(pipeline: &Expr, mut results: Vec<Value>) -> Value {
+    if picks_element(pipeline) {
+        results.pop().unwrap_or(Value::Null)
+    } else {
+        Value::Array(results)
+    }
+}
+
+/// Resolve a possibly negative position against `len`, clamped to `0..=len`.
+fn position(index: i64, len: usize) -> usize {
+    let len = len as i64;
+    let index = if index < 0 {
+        index.saturating_add(len)
+    } else {
+        index
+    };
+    index.clamp(0, len) as usize
+}
+
+/// Whether `expr` selects from the document set rather than the current document.
+fn is_query(expr: &Expr) -> bool {
+    match expr {
+        Expr::Everything => true,
+        Expr::Pipeline(stages) => stages.first().is_some_and(is_query),
+        _ => false,
+    }
+}
+
+fn eval_pipeline(
+    expr: &Expr,
+    docs: &[Value],
+    ctx: &EvalContext<'_>,
+) -> Result<Vec<Value>, EvalError> {
+    match expr {
+        Expr::Everything => Ok(docs.to_vec()),
+        Expr::Pipeline(stages) => match stages.split_first() {
+            // `*[...] | order(...)[...]{...}`: the leading stages select from
+            // the borrowed documents, and only what they keep is cloned or
+            // projected.
+            Some((Expr::Everything, rest)) => {
+                let split = rest
+                    .iter()
+                    .position(|stage| !is_selection(stage))
+                    .unwrap_or(rest.len());
+                let (selection, rest) = rest.split_at(split);
+                let mut matched: Vec<&Value> = docs.iter().collect();
+                for stage in selection {
+                    matched = select(matched, stage, ctx)?;
+                }
+                match rest.split_first() {
+                    Some((Expr::Projection(fields), rest)) => {
+                        apply_stages(project(&matched, fields, ctx)?, rest, ctx)
+                    }
+                    _ => apply_stages(matched.into_iter().cloned().collect(), rest, ctx),
+                }
+            }
+            // `(*[...])[0...2]`: a grouped query followed by more stages.
+            Some((first, rest)) if is_query(first) => {
+                apply_stages(eval_pipeline(first, docs, ctx)?, rest, ctx)
+            }
+            _ => Err(EvalError::Unsupported),
+        },
+        _ => Err(EvalError::Unsupported),
+    }
+}
+
+/// Run filter, projection, ordering, slice and index stages over a list of
+/// values. An index stage leaves at most one value.
+fn apply_stages(
+    mut results: Vec<Value>,
+    stages: &[Expr],
+    ctx: &EvalContext<'_>,
+) -> Result<Vec<Value>, EvalError> {
+    for stage in stages {
+        match stage {
+            _ if is_selection(stage) => results = select(results, stage, ctx)?,
+            Expr::Projection(fields) => results = project(&results, fields, ctx)?,
+            // Element-wise dereference in `refs[]->` / `refs[]->field`.
+            Expr::Deref(base, _) | Expr::DerefDocument(base) if matches!(**base, Expr::This) => {
+                let mut resolved = Vec::with_capacity(results.len());
+                for item in &results {
+                    let value = eval_expr_in(stage, item, ctx)?;
+                    if !(value.is_null() && ctx.options.drop_missing_references) {
+                        resolved.push(value);
+                    }
+                }
+                results = resolved;
+            }
+            Expr::FuncCall(name, args) if name == "score" => {
+                for item in &mut results {
+                    let mut score = 0.0;
+                    for arg in args {
+                        score += relevance(arg, item, ctx)?;
+                    }
+                    if let (Value::Object(map), Some(score)) =
+                        (item, serde_json::Number::from_f64(score))
+                    {
+                        map.insert("_score".into(), Value::Number(score));
+                    }
+                }
+            }
+            _ => return Err(EvalError::Unsupported),
         }
-        Expr::And(l, r) => Ok(Value::Bool(
-            eval_filter(l, doc, params)? && eval_filter(r, doc, params)?,
+    }
+    Ok(results)
+}
+
+/// How well `doc` matches one argument of `score()`. Scores add up: a
+/// `match` counts the document's words it matched, `&&` and `||` sum their
+/// operands, `boost(expr, n)` multiplies by `n`, and any other condition
+/// counts one when true.
+fn relevance(expr: &Expr, doc: &Value, ctx: &EvalContext<'_>) -> Result<f64, EvalError> {
+    Ok(match expr {
+        Expr::Match(l, r) => {
+            let (text, pattern) = (eval_ref(l, doc, ctx)?, eval_ref(r, doc, ctx)?);
+            text_match(&text, &pattern).1 as f64
+        }
+        Expr::And(l, r) | Expr::Or(l, r) => relevance(l, doc, ctx)? + relevance(r, doc, ctx)?,
+        Expr::FuncCall(name, args) if name == "boost" => {
+            let [inner, factor] = args.as_slice() else {
+                return Err(EvalError::TypeError("boost() needs 2 args".into()));
+            };
+            let factor = eval_ref(factor, doc, ctx)?
+                .as_f64()
+                .ok_or_else(|| EvalError::TypeError("boost() expects a numeric factor".into()))?;
+            relevance(inner, doc, ctx)? * factor
+        }
+        _ if eval_bool(expr, doc, ctx)? => 1.0,
+        _ => 0.0,
+    })
+}
+
+/// Whether `stage` only drops or reorders values, so it can run over
+/// borrowed documents.
+fn is_selection(stage: &Expr) -> bool {
+    matches!(
+        stage,
+        Expr::Filter(_) | Expr::Order(..) | Expr::Slice(..) | Expr::Index(..)
+    )
+}
+
+/// Apply a filter, order, slice or index stage to `items`.
+fn select<T: Borrow<Value>>(
+    mut items: Vec<T>,
+    stage: &Expr,
+    ctx: &EvalContext<'_>,
+) -> Result<Vec<T>, EvalError> {
+    Ok(match stage {
+        Expr::Filter(filter) => {
+            let mut kept = Vec::with_capacity(items.len());
+            for item in items {
+                if eval_bool(filter, item.borrow(), ctx)? {
+                    kept.push(item);
+                }
+            }
+            kept
+        }
+        // Sort positions by borrowed keys, then move the items into place.
+        Expr::Order(field, ascending) => {
+            let mut order: Vec<usize> = (0..items.len()).collect();
+            let keys = items
+                .iter()
+                .map(|item| eval_ref(field, item.borrow(), ctx))
+                .collect::<Result<Vec<_>, _>>()?;
+            order.sort_by(|&a, &b| {
+                let ord = compare_values(&keys[a], &keys[b]);
+                if *ascending {
+                    ord
+                } else {
+                    ord.reverse()
+                }
+            });
+            drop(keys);
+            let mut slots: Vec<Option<T>> = items.into_iter().map(Some).collect();
+            order.into_iter().filter_map(|i| slots[i].take()).collect()
+        }
+        Expr::Slice(_, start, end) => {
+            let start = position(*start, items.len());
+            let end = position(*end, items.len()).max(start);
+            items.drain(start..end).collect()
+        }
+        Expr::Index(_, index) => {
+            let len = items.len() as i64;
+            let index = if *index < 0 { index + len } else { *index };
+            match usize::try_from(index) {
+                Ok(index) if index < items.len() => vec![items.swap_remove(index)],
+                _ => Vec::new(),
+            }
+        }
+        _ => return Err(EvalError::Unsupported),
+    })
+}
+
+/// Project each of `items`; projecting a non-object (e.g. an unresolved
+/// reference) yields null.
+fn project<T: Borrow<Value>>(
+    items: &[T],
+    fields: &[(String, Expr)],
+    ctx: &EvalContext<'_>,
+) -> Result<Vec<Value>, EvalError> {
+    items
+        .iter()
+        .map(|item| match item.borrow() {
+            doc @ Value::Object(_) => eval_projection(fields, doc, ctx),
+            _ => Ok(Value::Null),
+        })
+        .collect()
+}
+
+/// Elements of an iterated value: arrays yield their items, anything else
+/// yields nothing.
+fn iterate(value: Value) -> Option<Vec<Value>> {
+    match value {
+        Value::Array(items) => Some(items),
+        _ => None,
+    }
+}
+
+/// Resolve a reference object (`{"_ref": id}`) to its document.
+fn resolve(reference: &Value, ctx: &EvalContext<'_>) -> Option<Value> {
+    let id = reference.get("_ref")?.as_str()?;
+    ctx.resolver.resolve(id)
+}
+
+/// Equality used by `==`, `!=` and `in`: numbers compare by value, so `5`
+/// equals `5.0`, while values of different types, such as `true` and `1`,
+/// never match. Arrays and objects compare element by element.
+pub fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => match (x.as_i64(), y.as_i64()) {
+            (Some(x), Some(y)) => x == y,
+            _ => x.as_f64() == y.as_f64(),
+        },
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(x, y)| values_equal(x, y))
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(k, v)| y.get(k).is_some_and(|w| values_equal(v, w)))
+        }
+        _ => a == b,
+    }
+}
+
+/// Ordering used by `<`, `>`, `<=` and `>=`: numbers by value and strings
+/// lexically. Other values, and mixed types, don't compare, making the
+/// comparison null.
+fn compare_ordered(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => match (x.as_i64(), y.as_i64()) {
+            (Some(x), Some(y)) => Some(x.cmp(&y)),
+            _ => x.as_f64()?.partial_cmp(&y.as_f64()?),
+        },
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        _ => None,
+    }
+}
+
+/// Ordering used by `order()`: numbers, strings and booleans compare
+/// naturally; nulls and mixed types sort last.
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x
+            .as_f64()
+            .partial_cmp(&y.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Bool(x), Value::Bool(y)) => x.cmp(y),
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Greater,
+        (_, Value::Null) => Ordering::Less,
+        _ => Ordering::Equal,
+    }
+}
+
+/// Evaluate `expr` as a filter: only `true` counts as a match.
+fn eval_bool(expr: &Expr, doc: &Value, ctx: &EvalContext<'_>) -> Result<bool, EvalError> {
+    Ok(matches!(
+        eval_ref(expr, doc, ctx)?.as_ref(),
+        Value::Bool(true)
+    ))
+}
+
+/// Evaluate an expression, borrowing from `doc`/`params` where possible so
+/// field lookups in the per-document hot loop don't deep-clone values.
+fn eval_ref<'a>(
+    expr: &Expr,
+    doc: &'a Value,
+    ctx: &EvalContext<'a>,
+) -> Result<Cow<'a, Value>, EvalError> {
+    match expr {
+        Expr::Everything => Ok(Cow::Owned(Value::Bool(true))),
+        Expr::BoolLiteral(b) => Ok(Cow::Owned(Value::Bool(*b))),
+        Expr::IntLiteral(n) => Ok(Cow::Owned(Value::Number((*n).into()))),
+        Expr::FloatLiteral(f) => Ok(Cow::Owned(
+            serde_json::Number::from_f64(*f).map_or(Value::Null, Value::Number),
         )),
-        Expr::Or(l, r) => Ok(Value::Bool(
-            eval_filter(l, doc, params)? || eval_filter(r, doc, params)?,
+        Expr::StringLiteral(s) => Ok(Cow::Owned(Value::String(s.clone()))),
+        Expr::Null => Ok(Cow::Borrowed(&NULL)),
+        Expr::Array(items) => Ok(Cow::Owned(Value::Array(
+            items
+                .iter()
+                .map(|item| eval_expr_in(item, doc, ctx))
+                .collect::<Result<_, _>>()?,
+        ))),
+        Expr::Ident(name) => Ok(Cow::Borrowed(doc.get(name).unwrap_or(&NULL))),
+        Expr::DotAccess(base, field) => Ok(match eval_ref(base, doc, ctx)? {
+            Cow::Borrowed(v) => Cow::Borrowed(v.get(field).unwrap_or(&NULL)),
+            Cow::Owned(v) => Cow::Owned(v.get(field).cloned().unwrap_or(Value::Null)),
+        }),
+        Expr::Deref(base, field) => Ok(Cow::Owned(
+            resolve(eval_ref(base, doc, ctx)?.as_ref(), ctx)
+                .and_then(|mut target| target.get_mut(field).map(Value::take))
+                .unwrap_or(Value::Null),
         )),
-        Expr::Not(inner) => Ok(Value::Bool(!eval_filter(inner, doc, params)?)),
+        Expr::DerefDocument(base) => Ok(Cow::Owned(
+            resolve(eval_ref(base, doc, ctx)?.as_ref(), ctx).unwrap_or(Value::Null),
+        )),
+        Expr::Iterate(base) => Ok(Cow::Owned(
+            iterate(eval_expr_in(base, doc, ctx)?)
+                .map(Value::Array)
+                .unwrap_or(Value::Null),
+        )),
+        // Element pipelines such as `items[]{...}`, and projections of a
+        // single value such as `author->{name}`. Their stages run one scope
+        // in, with `^` referring to `doc`.
+        Expr::Pipeline(stages) => {
+            let scope = Scope {
+                doc,
+                parent: ctx.scope,
+            };
+            let inner = ctx.nested(&scope);
+            match stages.split_first() {
+                Some((Expr::Iterate(base), rest)) => {
+                    let Some(items) = iterate(eval_expr_in(base, doc, ctx)?) else {
+                        return Ok(Cow::Borrowed(&NULL));
+                    };
+                    let results = apply_stages(items, rest, &inner)?;
+                    Ok(Cow::Owned(single_or_array(expr, results)))
+                }
+                Some((Expr::Everything, _)) | None => Err(EvalError::Unsupported),
+                Some((first, rest)) => match eval_expr_in(first, doc, ctx)? {
+                    Value::Null => Ok(Cow::Borrowed(&NULL)),
+                    Value::Array(items) => {
+                        let results = apply_stages(items, rest, &inner)?;
+                        Ok(Cow::Owned(single_or_array(expr, results)))
+                    }
+                    value => Ok(Cow::Owned(
+                        apply_stages(vec![value], rest, &inner)?
+                            .pop()
+                            .unwrap_or(Value::Null),
+                    )),
+                },
+            }
+        }
+        Expr::Param(name) => Ok(Cow::Borrowed(ctx.params.get(name).unwrap_or(&NULL))),
+        Expr::This => Ok(Cow::Borrowed(doc)),
+        Expr::Parent(levels) => Ok(Cow::Borrowed(ctx.parent(*levels))),
+        Expr::Eq(l, r) => {
+            let lv = eval_ref(l, doc, ctx)?;
+            let rv = eval_ref(r, doc, ctx)?;
+            Ok(Cow::Owned(Value::Bool(values_equal(&lv, &rv))))
+        }
+        Expr::Neq(l, r) => {
+            let lv = eval_ref(l, doc, ctx)?;
+            let rv = eval_ref(r, doc, ctx)?;
+            Ok(Cow::Owned(Value::Bool(!values_equal(&lv, &rv))))
+        }
+        Expr::Lt(l, r) | Expr::Gt(l, r) | Expr::Lte(l, r) | Expr::Gte(l, r) => {
+            let (lv, rv) = (eval_ref(l, doc, ctx)?, eval_ref(r, doc, ctx)?);
+            let Some(ordering) = compare_ordered(&lv, &rv) else {
+                return Ok(Cow::Borrowed(&NULL));
+            };
+            Ok(Cow::Owned(Value::Bool(match expr {
+                Expr::Lt(..) => ordering.is_lt(),
+                Expr::Gt(..) => ordering.is_gt(),
+                Expr::Lte(..) => ordering.is_le(),
+                _ => ordering.is_ge(),
+            })))
+        }
+        Expr::Match(l, r) => {
+            let (text, pattern) = (eval_ref(l, doc, ctx)?, eval_ref(r, doc, ctx)?);
+            Ok(Cow::Owned(Value::Bool(text_match(&text, &pattern).0)))
+        }
+        // Membership in an array; anything else on the right is null.
+        Expr::In(l, r) => {
+            let needle = eval_ref(l, doc, ctx)?;
+            Ok(match eval_ref(r, doc, ctx)?.as_ref() {
+                Value::Array(items) => Cow::Owned(Value::Bool(
+                    items.iter().any(|item| values_equal(item, &needle)),
+                )),
+                _ => Cow::Borrowed(&NULL),
+            })
+        }
+        Expr::And(l, r) => Ok(Cow::Owned(Value::Bool(
+            eval_bool(l, doc, ctx)? && eval_bool(r, doc, ctx)?,
+        ))),
+        Expr::Or(l, r) => Ok(Cow::Owned(Value::Bool(
+            eval_bool(l, doc, ctx)? || eval_bool(r, doc, ctx)?,
+        ))),
+        Expr::Not(inner) => Ok(Cow::Owned(Value::Bool(!eval_bool(inner, doc, ctx)?))),
+        Expr::Projection(fields) | Expr::Object(fields) => {
+            Ok(Cow::Owned(eval_projection(fields, doc, ctx)?))
+        }
+        Expr::FuncCall(name, args) => {
+            let values = args
+                .iter()
+                .map(|arg| eval_ref(arg, doc, ctx))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut args: Vec<&Value> = values.iter().map(Cow::as_ref).collect();
+            // `references($id)` checks the current document implicitly.
+            if name == "references" && args.len() == 1 {
+                args.insert(0, doc);
+            }
+            Ok(Cow::Owned(call_builtin_ref(name, &args)?))
+        }
+        Expr::Select(branches) => {
+            for (cond, value) in branches {
+                let matched = match cond {
+                    Some(cond) => eval_bool(cond, doc, ctx)?,
+                    None => true,
+                };
+                if matched {
+                    return eval_ref(value, doc, ctx);
+                }
+            }
+            Ok(Cow::Borrowed(&NULL))
+        }
         _ => Err(EvalError::Unsupported),
     }
 }
 
+/// Build the object for a projection. `...` entries spread the document
+/// (`Everything`) or any object-valued expression, such as a conditional
+/// spread `cond => { ... }`; non-object spreads contribute nothing.
+/// Undefined fields are emitted as `null` unless `omit_undefined` is set.
+fn eval_projection(
+    fields: &[(String, Expr)],
+    doc: &Value,
+    ctx: &EvalContext<'_>,
+) -> Result<Value, EvalError> {
+    let mut out = serde_json::Map::new();
+    if let (true, Value::Object(map)) = (ctx.options.include_system_fields, doc) {
+        out.extend(
+            map.iter()
+                .filter(|(k, _)| k.starts_with('_'))
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+    }
+    for (key, expr) in fields {
+        if key == "..." {
+            let spread = match expr {
+                Expr::Everything => Cow::Borrowed(doc),
+                _ => eval_ref(expr, doc, ctx)?,
+            };
+            if let Value::Object(map) = spread.as_ref() {
+                out.extend(map.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
+        } else {
+            let value = eval_expr_in(expr, doc, ctx)?;
+            if value.is_null() && ctx.options.omit_undefined {
+                out.remove(key);
+            } else {
+                out.insert(key.clone(), value);
+            }
+        }
+    }
+    Ok(Value::Object(out))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,4 +682,678 @@ mod tests {
         let doc = json!({"author": {"_ref": "user1"}});
         assert!(eval_filter(&expr, &doc, &json!({})).unwrap());
     }
+
+    #[test]
+    fn eval_expr_returns_owned_field_values() {
+        let doc = json!({"author": {"_ref": "user1", "name": "Ada"}, "tags": ["a"]});
+        let params = json!({"id": "user1"});
+        assert_eq!(
+            eval_expr(&Expr::Ident("author".into()), &doc, &params).unwrap(),
+            json!({"_ref": "user1", "name": "Ada"})
+        );
+        assert_eq!(
+            eval_expr(&Expr::Ident("missing".into()), &doc, &params).unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            eval_expr(&Expr::Param("id".into()), &doc, &params).unwrap(),
+            json!("user1")
+        );
+        assert_eq!(eval_expr(&Expr::This, &doc, &params).unwrap(), doc);
+    }
+
+    #[test]
+    fn eval_filter_is_false_for_non_boolean_results() {
+        let doc = json!({"title": "x"});
+        assert!(!eval_filter(&Expr::Ident("title".into()), &doc, &json!({})).unwrap());
+        assert!(!eval_filter(&Expr::Null, &doc, &json!({})).unwrap());
+    }
+
+    #[test]
+    fn eval_not_and_or_over_borrowed_fields() {
+        let expr = Expr::Or(
+            Box::new(Expr::Not(Box::new(Expr::Eq(
+                Box::new(Expr::Ident("_type".into())),
+                Box::new(Expr::StringLiteral("post".into())),
+            )))),
+            Box::new(Expr::Neq(
+                Box::new(Expr::DotAccess(
+                    Box::new(Expr::Ident("author".into())),
+                    "_ref".into(),
+                )),
+                Box::new(Expr::Param("id".into())),
+            )),
+        );
+        let params = json!({"id": "user1"});
+        assert!(!eval_filter(
+            &expr,
+            &json!({"_type": "post", "author": {"_ref": "user1"}}),
+            &params
+        )
+        .unwrap());
+        assert!(eval_filter(
+            &expr,
+            &json!({"_type": "post", "author": {"_ref": "user2"}}),
+            &params
+        )
+        .unwrap());
+        assert!(eval_filter(&expr, &json!({"_type": "page"}), &params).unwrap());
+    }
+
+    fn eval_str(query: &str, doc: &Value) -> Value {
+        let expr = crate::parser::parse(query).unwrap();
+        eval_expr(&expr, doc, &json!({})).unwrap()
+    }
+
+    #[test]
+    fn eval_select_matched_condition() {
+        let query = r#"select(_type == "post" => "article", _type == "page" => "page", "other")"#;
+        assert_eq!(eval_str(query, &json!({"_type": "page"})), json!("page"));
+    }
+
+    #[test]
+    fn eval_select_falls_through_to_default() {
+        let query = r#"select(_type == "post" => title, "untitled")"#;
+        assert_eq!(
+            eval_str(query, &json!({"_type": "page", "title": "T"})),
+            json!("untitled")
+        );
+        assert_eq!(
+            eval_str(query, &json!({"_type": "post", "title": "T"})),
+            json!("T")
+        );
+    }
+
+    #[test]
+    fn eval_select_without_default_is_null() {
+        let query = r#"select(_type == "post" => "article")"#;
+        assert_eq!(eval_str(query, &json!({"_type": "page"})), Value::Null);
+    }
+
+    fn projection(query: &str) -> Expr {
+        match crate::parser::parse(query).unwrap() {
+            Expr::Pipeline(mut stages) => stages.pop().unwrap(),
+            other => panic!("expected Pipeline, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn eval_conditional_spread_when_true() {
+        let expr = projection(r#"*[_type == "post"]{title, isActive => { "badge": "active" }}"#);
+        let doc = json!({"_type": "post", "title": "T", "isActive": true});
+        assert_eq!(
+            eval_expr(&expr, &doc, &json!({})).unwrap(),
+            json!({"title": "T", "badge": "active"})
+        );
+    }
+
+    #[test]
+    fn eval_conditional_spread_when_false() {
+        let expr = projection(r#"*[_type == "post"]{..., isActive => { "badge": "active" }}"#);
+        let doc = json!({"_type": "post", "isActive": false});
+        assert_eq!(eval_expr(&expr, &doc, &json!({})).unwrap(), doc);
+    }
+
+    fn seeded_docs() -> Vec<Value> {
+        vec![
+            json!({"_id": "p1", "_type": "post", "tags": ["a", "b"]}),
+            json!({"_id": "p2", "_type": "post", "tags": ["c"]}),
+            json!({"_id": "a1", "_type": "author"}),
+        ]
+    }
+
+    #[test]
+    fn eval_query_counts_filtered_result_set() {
+        let expr = crate::parser::parse(r#"count(*[_type == "post"])"#).unwrap();
+        assert_eq!(
+            eval_query(&expr, &seeded_docs(), &json!({})).unwrap(),
+            json!(2)
+        );
+        let expr = crate::parser::parse("count(*)").unwrap();
+        assert_eq!(
+            eval_query(&expr, &seeded_docs(), &json!({})).unwrap(),
+            json!(3)
+        );
+    }
+
+    #[test]
+    fn eval_query_counts_array_field_per_document() {
+        let expr =
+            crate::parser::parse(r#"*[_type == "post"]{_id, "tagCount": count(tags)}"#).unwrap();
+        assert_eq!(
+            eval_query(&expr, &seeded_docs(), &json!({})).unwrap(),
+            json!([{"_id": "p1", "tagCount": 2}, {"_id": "p2", "tagCount": 1}])
+        );
+    }
+
+    #[test]
+    fn eval_projection_emits_null_for_missing_fields_by_default() {
+        let expr = crate::parser::parse(r#"*[_type == "author"]{_id, name}"#).unwrap();
+        assert_eq!(
+            eval_query(&expr, &seeded_docs(), &json!({})).unwrap(),
+            json!([{"_id": "a1", "name": null}])
+        );
+    }
+
+    #[test]
+    fn eval_projection_can_omit_undefined_fields() {
+        let expr =
+            crate::parser::parse(r#"*[_type == "author"]{_id, name, "nested": {name}}"#).unwrap();
+        let params = json!({});
+        let ctx = EvalContext::new(&params).with_options(ProjectionOptions {
+            omit_undefined: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            eval_query_in(&expr, &seeded_docs(), &ctx).unwrap(),
+            json!([{"_id": "a1", "nested": {}}])
+        );
+    }
+
+    #[test]
+    fn eval_projection_can_include_system_fields() {
+        let docs =
+            vec![json!({"_id": "p1", "_type": "post", "_rev": "r1", "title": "Hi", "body": "x"})];
+        let expr = crate::parser::parse("*{title}").unwrap();
+        let params = json!({});
+        assert_eq!(
+            eval_query(&expr, &docs, &params).unwrap(),
+            json!([{"title": "Hi"}])
+        );
+
+        let ctx = EvalContext::new(&params).with_options(ProjectionOptions {
+            include_system_fields: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            eval_query_in(&expr, &docs, &ctx).unwrap(),
+            json!([{"_id": "p1", "_type": "post", "_rev": "r1", "title": "Hi"}])
+        );
+
+        // Naming a system field overrides it; with `omit_undefined` a null
+        // value leaves it out.
+        let expr = crate::parser::parse(r#"*{title, "_type": "article", "_rev": null}"#).unwrap();
+        let ctx = EvalContext::new(&params).with_options(ProjectionOptions {
+            include_system_fields: true,
+            omit_undefined: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            eval_query_in(&expr, &docs, &ctx).unwrap(),
+            json!([{"_id": "p1", "_type": "article", "title": "Hi"}])
+        );
+    }
+
+    #[test]
+    fn in_matches_reference_ids_against_arrays() {
+        let parse = |query: &str| crate::parser::parse(query).unwrap();
+        let params = json!({"ids": ["p1", "p3"], "authorIds": ["user2"]});
+        let hit = json!({"_id": "p1", "author": {"_ref": "user1"}});
+        let miss = json!({"_id": "p2", "author": {"_ref": "user3"}});
+
+        let literal = parse(r#"author._ref in ["user1", "user2"]"#);
+        assert!(eval_filter(&literal, &hit, &params).unwrap());
+        assert!(!eval_filter(&literal, &miss, &params).unwrap());
+        let ids = parse("_id in $ids");
+        assert!(eval_filter(&ids, &hit, &params).unwrap());
+        assert!(!eval_filter(&ids, &miss, &params).unwrap());
+        let no_author = json!({"_id": "p4"});
+        assert!(!eval_filter(&parse("author._ref in $authorIds"), &no_author, &params).unwrap());
+
+        let resolver: HashMap<String, Value> = HashMap::from([
+            (
+                "user1".to_string(),
+                json!({"_id": "user1", "handle": "ada"}),
+            ),
+            (
+                "user3".to_string(),
+                json!({"_id": "user3", "handle": "bob"}),
+            ),
+        ]);
+        let ctx = EvalContext::new(&params).with_resolver(&resolver);
+        let deref = parse(r#"author->handle in ["ada", "eve"]"#);
+        assert_eq!(eval_expr_in(&deref, &hit, &ctx).unwrap(), json!(true));
+        assert_eq!(eval_expr_in(&deref, &miss, &ctx).unwrap(), json!(false));
+    }
+
+    #[test]
+    fn eval_deref_resolves_through_resolver() {
+        let resolver: HashMap<String, Value> =
+            HashMap::from([("user1".to_string(), json!({"_id": "user1", "name": "Ada"}))]);
+        let params = json!({});
+        let ctx = EvalContext::new(&params).with_resolver(&resolver);
+        let expr = crate::parser::parse("author->name").unwrap();
+
+        let hit = json!({"author": {"_ref": "user1"}});
+        assert_eq!(eval_expr_in(&expr, &hit, &ctx).unwrap(), json!("Ada"));
+
+        let miss = json!({"author": {"_ref": "user2"}});
+        assert_eq!(eval_expr_in(&expr, &miss, &ctx).unwrap(), Value::Null);
+        let not_a_ref = json!({"author": "user1"});
+        assert_eq!(eval_expr_in(&expr, &not_a_ref, &ctx).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn eval_references_with_implicit_document() {
+        let docs = vec![
+            json!({"_id": "p1", "author": {"_ref": "user-1"}}),
+            json!({"_id": "p2", "body": [{"mark": {"_ref": "user-1"}}]}),
+            json!({"_id": "p3", "author": {"_ref": "user-2"}}),
+        ];
+        let expr = crate::parser::parse("*[references($id)]{_id}").unwrap();
+        assert_eq!(
+            eval_query(&expr, &docs, &json!({"id": "user-1"})).unwrap(),
+            json!([{"_id": "p1"}, {"_id": "p2"}])
+        );
+        let expr = crate::parser::parse(r#"*[references(["user-2", "user-3"])]{_id}"#).unwrap();
+        assert_eq!(
+            eval_query(&expr, &docs, &json!({})).unwrap(),
+            json!([{"_id": "p3"}])
+        );
+    }
+
+    #[test]
+    fn eval_object_literal_in_projection() {
+        let expr = projection(r#"*[_type == "post"]{"meta": {"a": 1, "v": views}}"#);
+        let doc = json!({"_type": "post", "views": 10});
+        assert_eq!(
+            eval_expr(&expr, &doc, &json!({})).unwrap(),
+            json!({"meta": {"a": 1, "v": 10}})
+        );
+    }
+
+    #[test]
+    fn eval_iterate_with_element_projection() {
+        let doc = json!({
+            "tags": [
+                {"label": "rust", "weight": 1},
+                {"label": "groq", "weight": 2}
+            ]
+        });
+        let expr = crate::parser::parse("tags[]{label}").unwrap();
+        assert_eq!(
+            eval_expr(&expr, &doc, &json!({})).unwrap(),
+            json!([{"label": "rust"}, {"label": "groq"}])
+        );
+        let expr = crate::parser::parse("missing[]{label}").unwrap();
+        assert_eq!(eval_expr(&expr, &doc, &json!({})).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn eval_order_then_slice() {
+        let docs = vec![
+            json!({"_id": "a", "views": 5}),
+            json!({"_id": "b", "views": 9}),
+            json!({"_id": "c", "views": 1}),
+        ];
+        let expr =
+            crate::parser::parse("*[defined(views)] | order(views desc) | [0..1] | {_id}").unwrap();
+        assert_eq!(
+            eval_query(&expr, &docs, &json!({})).unwrap(),
+            json!([{"_id": "b"}, {"_id": "a"}])
+        );
+    }
+
+    #[test]
+    fn defined_is_false_through_missing_and_scalar_intermediates() {
+        let doc = json!({"nested": {"present": {"field": 0}}, "title": "x", "empty": null});
+        let eval = |query: &str| {
+            eval_expr(&crate::parser::parse(query).unwrap(), &doc, &json!({})).unwrap()
+        };
+        assert_eq!(eval("defined(nested.present.field)"), json!(true));
+        assert_eq!(eval("defined(nested.missing.field)"), json!(false));
+        assert_eq!(eval("defined(absent.missing.field)"), json!(false));
+        assert_eq!(eval("defined(empty.field)"), json!(false));
+        assert_eq!(eval("defined(title.length)"), json!(false));
+        assert_eq!(eval("nested.missing.field"), Value::Null);
+
+        let docs = vec![doc.clone(), json!({"_id": "bare"})];
+        let expr =
+            crate::parser::parse("*[!defined(nested.missing.field)]{\"f\": nested.missing.field}")
+                .unwrap();
+        assert_eq!(
+            eval_query(&expr, &docs, &json!({})).unwrap(),
+            json!([{"f": null}, {"f": null}])
+        );
+    }
+
+    #[test]
+    fn compares_against_float_literals() {
+        let parse = |query: &str| crate::parser::parse(query).unwrap();
+        let params = json!({});
+        let cheap = json!({"price": 4.5});
+        let pricey = json!({"price": 19});
+        let expr = parse("price > 9.99");
+        assert!(!eval_filter(&expr, &cheap, &params).unwrap());
+        assert!(eval_filter(&expr, &pricey, &params).unwrap());
+        assert!(eval_filter(&parse("price <= 4.5"), &cheap, &params).unwrap());
+        assert!(eval_filter(&parse("price >= 19.0"), &pricey, &params).unwrap());
+        assert_eq!(
+            eval_expr(&parse("-0.5"), &cheap, &params).unwrap(),
+            json!(-0.5)
+        );
+
+        // Only numbers and strings order; anything else compares to null.
+        assert!(eval_filter(&parse(r#"title < "b""#), &json!({"title": "a"}), &params).unwrap());
+        assert_eq!(eval_expr(&expr, &json!({}), &params).unwrap(), Value::Null);
+        assert_eq!(
+            eval_expr(&parse(r#"price > "9.99""#), &pricey, &params).unwrap(),
+            Value::Null
+        );
+
+        let docs = vec![cheap, pricey];
+        assert_eq!(
+            eval_query(&parse("count(*[price < 10.5])"), &docs, &params).unwrap(),
+            json!(1)
+        );
+    }
+
+    #[test]
+    fn non_finite_float_literals_evaluate_to_null() {
+        let params = json!({});
+        for f in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let expr = Expr::FloatLiteral(f);
+            assert_eq!(eval_expr(&expr, &json!({}), &params).unwrap(), Value::Null);
+        }
+        assert_eq!(
+            eval_expr(&Expr::FloatLiteral(1.5), &json!({}), &params).unwrap(),
+            json!(1.5)
+        );
+    }
+
+    #[test]
+    fn numbers_equal_by_value_but_not_across_types() {
+        let doc = json!({"views": 5.0, "count": 5, "flag": true});
+        let eval = |query: &str| {
+            eval_expr(
+                &crate::parser::parse(query).unwrap(),
+                &doc,
+                &json!({"n": 5.0}),
+            )
+            .unwrap()
+        };
+        assert_eq!(eval("views == 5"), json!(true));
+        assert_eq!(eval("count == $n"), json!(true));
+        assert_eq!(eval("views != count"), json!(false));
+        assert_eq!(eval("views in [1, 5]"), json!(true));
+        assert_eq!(eval("[5, 1] == [views, 1]"), json!(true));
+
+        assert_eq!(eval("flag == 1"), json!(false));
+        assert_eq!(eval("flag != 1"), json!(true));
+        assert_eq!(eval("count == \"5\""), json!(false));
+        assert_eq!(eval("null == 0"), json!(false));
+    }
+
+    #[test]
+    fn array_literals_build_and_match() {
+        let doc = json!({"title": "Hello", "category": "b", "tags": ["x", "y"]});
+        let eval = |query: &str| {
+            eval_expr(
+                &crate::parser::parse(query).unwrap(),
+                &doc,
+                &json!({"c": "b"}),
+            )
+            .unwrap()
+        };
+        assert_eq!(eval("[1, 2, title]"), json!([1, 2, "Hello"]));
+        assert_eq!(eval("[]"), json!([]));
+        assert_eq!(eval(r#"category in ["a", "b"]"#), json!(true));
+        assert_eq!(eval(r#"category in ["a", "c"]"#), json!(false));
+        assert_eq!(eval(r#"$c in ["a", category]"#), json!(true));
+        assert_eq!(eval(r#""y" in tags"#), json!(true));
+        assert_eq!(eval(r#"category in "abc""#), Value::Null);
+
+        let docs = vec![
+            json!({"_id": "1", "category": "a"}),
+            json!({"_id": "2", "category": "c"}),
+        ];
+        let expr =
+            crate::parser::parse(r#"*[category in ["a", "b"]]{_id, "pair": [_id, category]}"#)
+                .unwrap();
+        assert_eq!(
+            eval_query(&expr, &docs, &json!({})).unwrap(),
+            json!([{"_id": "1", "pair": ["1", "a"]}])
+        );
+    }
+
+    #[test]
+    fn lower_and_upper_compose_into_comparisons() {
+        let docs = vec![
+            json!({"_id": "a", "status": "Active"}),
+            json!({"_id": "b", "status": "ACTIVE"}),
+            json!({"_id": "c", "status": "archived"}),
+            json!({"_id": "d"}),
+        ];
+        let ids = |query: &str| {
+            let expr = crate::parser::parse(query).unwrap();
+            eval_query(&expr, &docs, &json!({"status": "active"})).unwrap()
+        };
+        assert_eq!(
+            ids(r#"*[lower(status) == "active"]{_id}"#),
+            ids(r#"*[upper(status) == upper($status)]{_id}"#)
+        );
+        assert_eq!(
+            ids(r#"*[lower(status) == "active"]{_id}"#),
+            json!([{"_id": "a"}, {"_id": "b"}])
+        );
+        assert_eq!(
+            ids(r#"*[lower(status) != $status]{_id}"#),
+            json!([{"_id": "c"}, {"_id": "d"}])
+        );
+        assert!(eval_filter(
+            &crate::parser::parse(r#"lower(status) == "active""#).unwrap(),
+            &docs[0],
+            &json!({})
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn score_orders_by_title_relevance() {
+        let docs = vec![
+            json!({"_id": "none", "title": "Cooking with Go"}),
+            json!({"_id": "once", "title": "Learning Rust"}),
+            json!({"_id": "twice", "title": "Rust for Rust developers"}),
+            json!({"_id": "prefix", "title": "Rusty tools", "featured": true}),
+        ];
+        let ids_and_scores = |query: &str| {
+            let expr = crate::parser::parse(query).unwrap();
+            eval_query(&expr, &docs, &json!({"q": "rust"}))
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|d| (d["_id"].clone(), d["_score"].clone()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            ids_and_scores("* | score(title match $q) | order(_score desc)"),
+            [
+                (json!("twice"), json!(2.0)),
+                (json!("once"), json!(1.0)),
+                (json!("none"), json!(0.0)),
+                (json!("prefix"), json!(0.0)),
+            ]
+        );
+        assert_eq!(
+            ids_and_scores(
+                r#"*[title match "rust*"] | score(title match "rust*", boost(featured == true, 5)) | order(_score desc)"#
+            ),
+            [
+                (json!("prefix"), json!(6.0)),
+                (json!("twice"), json!(2.0)),
+                (json!("once"), json!(1.0)),
+            ]
+        );
+
+        // As a filter, every term must match.
+        let expr = crate::parser::parse(r#"*[title match "rust developers"]{_id}"#).unwrap();
+        assert_eq!(
+            eval_query(&expr, &docs, &json!({})).unwrap(),
+            json!([{"_id": "twice"}])
+        );
+    }
+
+    #[test]
+    fn eval_slice_and_index_bounds() {
+        let docs: Vec<Value> = (0..5).map(|n| json!({"n": n})).collect();
+        let ns = |query: &str| {
+            let expr = crate::parser::parse(&format!("*{query}")).unwrap();
+            let result = eval_query(&expr, &docs, &json!({})).unwrap();
+            match result {
+                Value::Array(items) => json!(items.iter().map(|d| &d["n"]).collect::<Vec<_>>()),
+                other => other["n"].clone(),
+            }
+        };
+        assert_eq!(ns("[2...]"), json!([2, 3, 4]));
+        assert_eq!(ns("[2..]"), json!([2, 3, 4]));
+        assert_eq!(ns("[...3]"), json!([0, 1, 2]));
+        assert_eq!(ns("[..3]"), json!([0, 1, 2, 3]));
+        assert_eq!(ns("[-2...]"), json!([3, 4]));
+        assert_eq!(ns("[-3..-1]"), json!([2, 3, 4]));
+        assert_eq!(ns("[-3...-1]"), json!([2, 3]));
+        assert_eq!(ns("[1..-2]"), json!([1, 2, 3]));
+        // Out-of-range bounds are clamped; crossed bounds select nothing.
+        assert_eq!(ns("[-10..1]"), json!([0, 1]));
+        assert_eq!(ns("[3..100]"), json!([3, 4]));
+        assert_eq!(ns("[5...]"), json!([]));
+        assert_eq!(ns("[3...1]"), json!([]));
+        assert_eq!(ns("[-1...-3]"), json!([]));
+        // A single index yields the element itself, or null.
+        assert_eq!(ns("[0]"), json!(0));
+        assert_eq!(ns("[-1]"), json!(4));
+        assert_eq!(ns("[5]"), Value::Null);
+        assert_eq!(ns("[-6]"), Value::Null);
+
+        let expr = crate::parser::parse("*[_type == \"post\"] | order(_id desc)[0]{_id}").unwrap();
+        assert_eq!(
+            eval_query(&expr, &seeded_docs(), &json!({})).unwrap(),
+            json!({"_id": "p2"})
+        );
+        let expr = crate::parser::parse("*[_type == \"page\"][0]").unwrap();
+        assert_eq!(
+            eval_query(&expr, &seeded_docs(), &json!({})).unwrap(),
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn borrowed_selection_matches_owned_stages() {
+        let docs: Vec<Value> = (0..50)
+            .map(|i| {
+                json!({
+                    "_id": format!("d{i}"),
+                    "_type": if i % 2 == 0 { "post" } else { "page" },
+                    "rank": (i * 7) % 11,
+                    "title": if i % 5 == 0 { Value::Null } else { json!(format!("t{}", i % 4)) },
+                    "author": {"_ref": format!("u{}", i % 3)},
+                })
+            })
+            .collect();
+        let params = json!({});
+        let ctx = EvalContext::new(&params);
+        for query in [
+            r#"*[_type == "post"]"#,
+            r#"*[_type == "post" && references("u1")]{_id, "a": author._ref}"#,
+            "*[defined(title)] | order(title desc) | order(rank)[2...9]{_id, rank}",
+            r#"*[_type == "page"] | order(rank)[-3..]"#,
+            r#"*[_type == "page"] | order(_id desc)[1]{_id}"#,
+            r#"*{_id}[_id == "d3"]"#,
+        ] {
+            let expr = crate::parser::parse(query).unwrap();
+            let Expr::Pipeline(stages) = &expr else {
+                panic!("expected pipeline")
+            };
+            let owned = apply_stages(docs.clone(), &stages[1..], &ctx).expect(query);
+            assert_eq!(
+                eval_query_in(&expr, &docs, &ctx).unwrap(),
+                single_or_array(&expr, owned),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn eval_grouped_query_with_stages() {
+        let docs = vec![
+            json!({"_id": "a", "_type": "post", "n": 1, "draft": true}),
+            json!({"_id": "b", "_type": "post", "n": 2, "draft": false}),
+            json!({"_id": "c", "_type": "page", "n": 3, "draft": false}),
+        ];
+        let expr = crate::parser::parse(
+            r#"(*[(_type == "post" || _id == "c") && !draft] | order(n desc))[0...1]{_id}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            eval_query(&expr, &docs, &json!({})).unwrap(),
+            json!([{"_id": "c"}])
+        );
+    }
+
+    #[test]
+    fn eval_parent_in_nested_projection() {
+        let docs = vec![json!({
+            "_id": "p1",
+            "authors": [{"name": "Ada", "books": [{"title": "Notes"}]}],
+        })];
+        let expr = crate::parser::parse(
+            r#"*{"authors": authors[]{name, "post": ^._id, "books": books[]{title, "by": ^.name, "post": ^.^._id, "none": ^.^.^._id}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            eval_query(&expr, &docs, &json!({})).unwrap(),
+            json!([{"authors": [{
+                "name": "Ada",
+                "post": "p1",
+                "books": [{"title": "Notes", "by": "Ada", "post": "p1", "none": null}],
+            }]}])
+        );
+
+        // At the top level there is no enclosing document.
+        let expr = crate::parser::parse(r#"*{"p": ^._id}"#).unwrap();
+        assert_eq!(
+            eval_query(&expr, &docs, &json!({})).unwrap(),
+            json!([{"p": null}])
+        );
+    }
+
+    #[test]
+    fn eval_deref_projects_reference_array() {
+        let resolver: HashMap<String, Value> = HashMap::from([
+            (
+                "ada".to_string(),
+                json!({"_id": "ada", "name": "Ada", "born": 1815}),
+            ),
+            (
+                "alan".to_string(),
+                json!({"_id": "alan", "name": "Alan", "born": 1912}),
+            ),
+        ]);
+        let doc = json!({
+            "authors": [{"_ref": "ada"}, {"_ref": "ghost"}, {"_ref": "alan"}]
+        });
+        let params = json!({});
+        let ctx = EvalContext::new(&params).with_resolver(&resolver);
+
+        let expr = crate::parser::parse("authors[]->{name}").unwrap();
+        assert_eq!(
+            eval_expr_in(&expr, &doc, &ctx).unwrap(),
+            json!([{"name": "Ada"}, null, {"name": "Alan"}])
+        );
+
+        let ctx = ctx.with_options(ProjectionOptions {
+            drop_missing_references: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            eval_expr_in(&expr, &doc, &ctx).unwrap(),
+            json!([{"name": "Ada"}, {"name": "Alan"}])
+        );
+        let expr = crate::parser::parse("authors[]->name").unwrap();
+        assert_eq!(
+            eval_expr_in(&expr, &doc, &ctx).unwrap(),
+            json!(["Ada", "Alan"])
+        );
+    }
 }
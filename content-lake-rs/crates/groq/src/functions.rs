@@ -1,22 +1,37 @@
 // GROQ built-in functions (count, defined, references, etc.).
 // Will be fully implemented in Phase 2.
 
+use std::collections::BTreeSet;
+
 use serde_json::Value;
 
 use crate::eval::EvalError;
 
 /// Evaluate a built-in GROQ function by name.
 pub fn call_builtin(name: &str, args: &[Value]) -> Result<Value, EvalError> {
+    call_builtin_ref(name, &args.iter().collect::<Vec<_>>())
+}
+
+/// [`call_builtin`] over borrowed arguments.
+pub fn call_builtin_ref(name: &str, args: &[&Value]) -> Result<Value, EvalError> {
     match name {
         "count" => builtin_count(args),
         "defined" => builtin_defined(args),
         "length" => builtin_length(args),
         "references" => builtin_references(args),
+        "pt::text" => builtin_pt_text(args),
+        "string" => builtin_string(args),
+        "lower" => Ok(map_string(args.first().copied(), str::to_lowercase)),
+        "upper" => Ok(map_string(args.first().copied(), str::to_uppercase)),
+        "round" => builtin_round(args),
+        "floor" => Ok(integral(args.first().copied(), f64::floor)),
+        "ceil" => Ok(integral(args.first().copied(), f64::ceil)),
+        "string::startsWith" => builtin_starts_with(args),
         _ => Err(EvalError::TypeError(format!("unknown function: {name}"))),
     }
 }
 
-fn builtin_count(args: &[Value]) -> Result<Value, EvalError> {
+fn builtin_count(args: &[&Value]) -> Result<Value, EvalError> {
     match args.first() {
         Some(Value::Array(arr)) => Ok(Value::Number(arr.len().into())),
         Some(Value::Null) => Ok(Value::Number(0.into())),
@@ -24,14 +39,14 @@ fn builtin_count(args: &[Value]) -> Result<Value, EvalError> {
     }
 }
 
-fn builtin_defined(args: &[Value]) -> Result<Value, EvalError> {
+fn builtin_defined(args: &[&Value]) -> Result<Value, EvalError> {
     match args.first() {
         Some(Value::Null) | None => Ok(Value::Bool(false)),
         _ => Ok(Value::Bool(true)),
     }
 }
 
-fn builtin_length(args: &[Value]) -> Result<Value, EvalError> {
+fn builtin_length(args: &[&Value]) -> Result<Value, EvalError> {
     match args.first() {
         Some(Value::String(s)) => Ok(Value::Number(s.len().into())),
         Some(Value::Array(a)) => Ok(Value::Number(a.len().into())),
@@ -39,29 +54,192 @@ fn builtin_length(args: &[Value]) -> Result<Value, EvalError> {
     }
 }
 
-fn builtin_references(args: &[Value]) -> Result<Value, EvalError> {
+fn builtin_references(args: &[&Value]) -> Result<Value, EvalError> {
     if args.len() < 2 {
         return Err(EvalError::TypeError("references() needs 2 args".into()));
     }
     let doc = &args[0];
-    let ref_id = match &args[1] {
-        Value::String(s) => s,
-        _ => return Ok(Value::Bool(false)),
+    let matched = match &args[1] {
+        Value::String(ref_id) => value_references(doc, ref_id),
+        // An array of ids matches if any of them is referenced.
+        Value::Array(ids) => ids
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|ref_id| value_references(doc, ref_id)),
+        _ => false,
+    };
+    Ok(Value::Bool(matched))
+}
+
+/// Flatten Portable Text blocks to plain text: span texts are concatenated
+/// per block and blocks are joined with a blank line, as in Sanity.
+/// Non-block values (images, custom objects) inside the array are skipped;
+/// with no blocks at all the result is `null`.
+fn builtin_pt_text(args: &[&Value]) -> Result<Value, EvalError> {
+    let blocks = match args.first().copied() {
+        Some(Value::Array(blocks)) => blocks.iter().collect::<Vec<_>>(),
+        Some(block @ Value::Object(_)) => vec![block],
+        _ => return Ok(Value::Null),
     };
-    Ok(Value::Bool(value_references(doc, ref_id)))
+
+    let texts: Vec<String> = blocks
+        .into_iter()
+        .filter_map(|block| block.get("children")?.as_array())
+        .map(|children| {
+            children
+                .iter()
+                .filter_map(|span| span.get("text")?.as_str())
+                .collect::<String>()
+        })
+        .collect();
+
+    if texts.is_empty() {
+        return Ok(Value::Null);
+    }
+    Ok(Value::String(texts.join("\n\n")))
+}
+
+/// Coerce a scalar to its string form; `null` and containers yield `null`.
+fn builtin_string(args: &[&Value]) -> Result<Value, EvalError> {
+    match args.first() {
+        Some(Value::String(s)) => Ok(Value::String(s.clone())),
+        Some(Value::Number(n)) => Ok(Value::String(n.to_string())),
+        Some(Value::Bool(b)) => Ok(Value::String(b.to_string())),
+        _ => Ok(Value::Null),
+    }
+}
+
+/// `op` applied to a string argument; anything else yields null.
+fn map_string(value: Option<&Value>, op: fn(&str) -> String) -> Value {
+    match value {
+        Some(Value::String(s)) => Value::String(op(s)),
+        _ => Value::Null,
+    }
+}
+
+fn builtin_starts_with(args: &[&Value]) -> Result<Value, EvalError> {
+    if args.len() < 2 {
+        return Err(EvalError::TypeError(
+            "string::startsWith() needs 2 args".into(),
+        ));
+    }
+    match (&args[0], &args[1]) {
+        (Value::String(s), Value::String(prefix)) => {
+            Ok(Value::Bool(s.starts_with(prefix.as_str())))
+        }
+        _ => Ok(Value::Bool(false)),
+    }
+}
+
+/// `round(n)` rounds to an integer; `round(n, precision)` to that many
+/// decimal places.
+fn builtin_round(args: &[&Value]) -> Result<Value, EvalError> {
+    let precision = match args.get(1) {
+        None | Some(Value::Null) => return Ok(integral(args.first().copied(), f64::round)),
+        Some(p) => p.as_i64().filter(|p| (0..=15).contains(p)).ok_or_else(|| {
+            EvalError::TypeError("round() precision must be an integer 0-15".into())
+        })?,
+    };
+    let Some(n) = args.first().and_then(|v| v.as_f64()) else {
+        return Ok(Value::Null);
+    };
+    let factor = 10f64.powi(precision as i32);
+    Ok(float_value((n * factor).round() / factor))
+}
+
+/// Apply an integral rounding function, returning an integer number or
+/// `null` for non-numeric input.
+fn integral(value: Option<&Value>, op: fn(f64) -> f64) -> Value {
+    match value {
+        Some(Value::Number(n)) if n.is_i64() || n.is_u64() => Value::Number(n.clone()),
+        Some(Value::Number(n)) => n
+            .as_f64()
+            .map(|f| float_value(op(f)))
+            .unwrap_or(Value::Null),
+        _ => Value::Null,
+    }
+}
+
+/// Convert an `f64` to JSON, as an integer when it has no fractional part.
+fn float_value(f: f64) -> Value {
+    if f.fract() == 0.0 && f.abs() < i64::MAX as f64 {
+        Value::Number((f as i64).into())
+    } else {
+        serde_json::Number::from_f64(f)
+            .map(Value::Number)
+            .unwrap_or(Value::Null)
+    }
+}
+
+/// How `text match pattern` fares: whether every term of the pattern
+/// matches a word of the text, and how many words of the text some term
+/// matches. Words are case-insensitive runs of letters and digits; a term
+/// ending in `*` matches words starting with the rest of it. Either side may
+/// be a string or an array of strings.
+pub fn text_match(text: &Value, pattern: &Value) -> (bool, usize) {
+    let words = match_words(text, false);
+    let terms = match_words(pattern, true);
+    let term_matches = |term: &String, word: &String| match term.strip_suffix('*') {
+        Some(prefix) => word.starts_with(prefix),
+        None => term == word,
+    };
+    let all = !terms.is_empty()
+        && terms
+            .iter()
+            .all(|term| words.iter().any(|word| term_matches(term, word)));
+    let hits = words
+        .iter()
+        .filter(|word| terms.iter().any(|term| term_matches(term, word)))
+        .count();
+    (all, hits)
+}
+
+/// The lowercased words of a `match` operand; pattern words keep their `*`.
+fn match_words(value: &Value, pattern: bool) -> Vec<String> {
+    let strings: Vec<&str> = match value {
+        Value::String(s) => vec![s],
+        Value::Array(items) => items.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    strings
+        .iter()
+        .flat_map(|s| s.split(|c: char| !(c.is_alphanumeric() || pattern && c == '*')))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
 }
 
 fn value_references(val: &Value, ref_id: &str) -> bool {
+    any_reference(val, &mut |r, _| r == ref_id)
+}
+
+/// Every strong `_ref` id in `val`, at any depth. References marked
+/// `_weak: true` are left out: they don't keep their target alive.
+pub fn referenced_ids(val: &Value) -> BTreeSet<String> {
+    let mut ids = BTreeSet::new();
+    any_reference(val, &mut |r, weak| {
+        if !weak {
+            ids.insert(r.to_string());
+        }
+        false
+    });
+    ids
+}
+
+/// Visit the `_ref` ids in `val`, with whether each is weak, until `found`
+/// returns true.
+fn any_reference(val: &Value, found: &mut impl FnMut(&str, bool) -> bool) -> bool {
     match val {
         Value::Object(map) => {
             if let Some(Value::String(r)) = map.get("_ref") {
-                if r == ref_id {
+                let weak = map.get("_weak") == Some(&Value::Bool(true));
+                if found(r, weak) {
                     return true;
                 }
             }
-            map.values().any(|v| value_references(v, ref_id))
+            map.values().any(|v| any_reference(v, found))
         }
-        Value::Array(arr) => arr.iter().any(|v| value_references(v, ref_id)),
+        Value::Array(arr) => arr.iter().any(|v| any_reference(v, found)),
         _ => false,
     }
 }
@@ -108,4 +286,128 @@ mod tests {
             json!(true)
         );
     }
+
+    #[test]
+    fn test_referenced_ids() {
+        let doc = json!({
+            "author": {"_ref": "user-1"},
+            "body": [{"children": [{"_ref": "tag-2"}, {"_ref": "user-1"}]}],
+            "related": {"_ref": "post-3", "_weak": true},
+            "_ref": 3
+        });
+        let ids: Vec<String> = referenced_ids(&doc).into_iter().collect();
+        assert_eq!(ids, ["tag-2", "user-1"]);
+    }
+
+    #[test]
+    fn test_pt_text() {
+        let body = json!([
+            {
+                "_type": "block",
+                "children": [
+                    {"_type": "span", "text": "Hello "},
+                    {"_type": "span", "text": "world"}
+                ]
+            },
+            {"_type": "image", "asset": {"_ref": "image-1"}},
+            {
+                "_type": "block",
+                "children": [{"_type": "span", "text": "Second block"}]
+            }
+        ]);
+        assert_eq!(
+            call_builtin("pt::text", &[body]).unwrap(),
+            json!("Hello world\n\nSecond block")
+        );
+    }
+
+    #[test]
+    fn test_pt_text_non_block() {
+        assert_eq!(
+            call_builtin("pt::text", &[json!("plain")]).unwrap(),
+            json!(null)
+        );
+        assert_eq!(call_builtin("pt::text", &[json!(42)]).unwrap(), json!(null));
+        let images = json!([{"_type": "image", "asset": {"_ref": "image-1"}}]);
+        assert_eq!(call_builtin("pt::text", &[images]).unwrap(), json!(null));
+        let image = json!({"_type": "image", "asset": {"_ref": "image-1"}});
+        assert_eq!(call_builtin("pt::text", &[image]).unwrap(), json!(null));
+        assert_eq!(call_builtin("pt::text", &[json!([])]).unwrap(), json!(null));
+    }
+
+    #[test]
+    fn test_starts_with() {
+        assert_eq!(
+            call_builtin(
+                "string::startsWith",
+                &[json!("drafts.post"), json!("drafts.")]
+            )
+            .unwrap(),
+            json!(true)
+        );
+        assert_eq!(
+            call_builtin("string::startsWith", &[json!("post"), json!("drafts.")]).unwrap(),
+            json!(false)
+        );
+        assert_eq!(
+            call_builtin("string::startsWith", &[json!(42), json!("4")]).unwrap(),
+            json!(false)
+        );
+    }
+
+    #[test]
+    fn test_string() {
+        assert_eq!(call_builtin("string", &[json!(42)]).unwrap(), json!("42"));
+        assert_eq!(
+            call_builtin("string", &[json!(true)]).unwrap(),
+            json!("true")
+        );
+        assert_eq!(call_builtin("string", &[json!(null)]).unwrap(), json!(null));
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn test_round() {
+        assert_eq!(
+            call_builtin("round", &[json!(3.14159), json!(2)]).unwrap(),
+            json!(3.14)
+        );
+        assert_eq!(call_builtin("round", &[json!(2.5)]).unwrap(), json!(3));
+        assert_eq!(call_builtin("round", &[json!(7)]).unwrap(), json!(7));
+        assert_eq!(call_builtin("round", &[json!("x")]).unwrap(), json!(null));
+    }
+
+    #[test]
+    fn test_floor_and_ceil() {
+        assert_eq!(call_builtin("ceil", &[json!(2.1)]).unwrap(), json!(3));
+        assert_eq!(call_builtin("floor", &[json!(2.9)]).unwrap(), json!(2));
+        assert_eq!(call_builtin("floor", &[json!(-2.1)]).unwrap(), json!(-3));
+        assert_eq!(call_builtin("ceil", &[json!(null)]).unwrap(), json!(null));
+    }
+
+    #[test]
+    fn text_match_counts_matching_words() {
+        let title = json!("Rust, rusty and RUST tools");
+        assert_eq!(text_match(&title, &json!("rust")), (true, 2));
+        assert_eq!(text_match(&title, &json!("rust*")), (true, 3));
+        assert_eq!(text_match(&title, &json!("rust go")), (false, 2));
+        assert_eq!(text_match(&title, &json!(["tools", "and"])), (true, 2));
+        assert_eq!(text_match(&json!(["a b", "c"]), &json!("c")), (true, 1));
+        assert_eq!(text_match(&title, &json!("")), (false, 0));
+        assert_eq!(text_match(&json!(5), &json!("5")), (false, 0));
+    }
+
+    #[test]
+    fn lower_and_upper_map_strings() {
+        assert_eq!(
+            call_builtin("lower", &[json!("ÀcTive")]).unwrap(),
+            json!("àctive")
+        );
+        assert_eq!(
+            call_builtin("upper", &[json!("draft")]).unwrap(),
+            json!("DRAFT")
+        );
+        assert_eq!(call_builtin("lower", &[json!(1)]).unwrap(), Value::Null);
+        assert_eq!(call_builtin("upper", &[]).unwrap(), Value::Null);
+    }
 }
@@ -1,3 +1,12 @@
+//! GROQ tokenizer.
+//!
+//! Identifiers follow Sanity's field-name rules: an ASCII letter or `_`,
+//! then any number of ASCII letters, digits and `_`. A parameter is `$`
+//! followed by an identifier; `$` is not allowed anywhere else in a name.
+//! A word that breaks these rules, such as `1st`, `titleé` or `a$b`, is
+//! rejected as a whole with [`LexError::InvalidIdentifier`] rather than
+//! being split into several tokens.
+
 use std::fmt;
 
 use serde::{Deserialize, Serialize};
@@ -18,6 +27,8 @@ pub enum Token {
 
     /// An identifier.
     Ident(String),
+    /// A query parameter reference, without the leading `$`.
+    Param(String),
 
     /// The equality operator.
     Eq, // ==
@@ -58,11 +69,15 @@ pub enum Token {
     Pipe, // |
     /// The arrow operator.
     Arrow, // ->
+    /// The fat arrow operator.
+    FatArrow, // =>
     /// The at symbol.
     At, // @
     /// The caret operator.
     Caret, // ^
-    /// The ellipsis operator.
+    /// The inclusive range operator.
+    DotDot, // ..
+    /// The ellipsis operator, also the exclusive range operator.
     Ellipsis, // ...
 
     /// The left parenthesis.
@@ -106,6 +121,17 @@ pub struct Span {
     pub end: usize,
 }
 
+impl Span {
+    /// The 1-based line and column of `start` in `input`, counting
+    /// characters like the offsets do.
+    pub fn line_col(&self, input: &str) -> (usize, usize) {
+        let before: Vec<char> = input.chars().take(self.start).collect();
+        let line_start = before.iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1);
+        let line = before.iter().filter(|&&c| c == '\n').count() + 1;
+        (line, before.len() - line_start + 1)
+    }
+}
+
 /// A token with its source position.
 #[derive(Debug, Clone, PartialEq)]
 pub struct SpannedToken {
@@ -120,234 +146,336 @@ pub enum LexError {
     UnexpectedChar(char, usize),
     #[error("unterminated string starting at position {0}")]
     UnterminatedString(usize),
+    #[error("invalid identifier '{0}' at position {1}")]
+    InvalidIdentifier(String, usize),
+    /// An integer beyond `i64`, or a decimal beyond `f64`. Rejected rather
+    /// than widened to a float, so an id or count too large to represent
+    /// exactly can't silently match the wrong value.
+    #[error("number '{0}' at position {1} is out of range")]
+    NumberOutOfRange(String, usize),
 }
 
-/// Tokenize a GROQ query string into a sequence of tokens.
-pub fn tokenize(input: &str) -> Result<Vec<SpannedToken>, LexError> {
-    let mut tokens = Vec::new();
-    let chars: Vec<char> = input.chars().collect();
-    let mut pos = 0;
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
 
-    while pos < chars.len() {
-        let ch = chars[pos];
+fn is_ident_continue(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
 
-        // Skip whitespace
-        if ch.is_whitespace() {
-            pos += 1;
-            continue;
-        }
+/// Characters that can't separate tokens, so a name running into them is
+/// malformed rather than followed by another token. Covers non-ASCII
+/// letters and combining marks.
+fn is_word_char(c: char) -> bool {
+    is_ident_continue(c) || c == '$' || (!c.is_ascii() && !c.is_whitespace())
+}
 
-        // Skip single-line comments
-        if ch == '/' && pos + 1 < chars.len() && chars[pos + 1] == '/' {
-            while pos < chars.len() && chars[pos] != '\n' {
-                pos += 1;
+/// The number spelled by `chars[start..end]`, sign included; see
+/// [`LexError::NumberOutOfRange`].
+fn number(chars: &[char], start: usize, end: usize, is_float: bool) -> Result<Token, LexError> {
+    let text: String = chars[start..end].iter().collect();
+    let token = if is_float {
+        text.parse()
+            .ok()
+            .filter(|f: &f64| f.is_finite())
+            .map(Token::Float)
+    } else {
+        text.parse().map(Token::Integer).ok()
+    };
+    token.ok_or(LexError::NumberOutOfRange(text, start))
+}
+
+/// Scan the digits of a number starting at `from`, with at most one decimal
+/// point. A `.` followed by another `.` starts a range, and a second decimal
+/// point starts the next token. Returns the end position and whether the
+/// number has a fractional part.
+fn scan_number(chars: &[char], from: usize) -> (usize, bool) {
+    let mut pos = from;
+    let mut is_float = false;
+    while pos < chars.len() {
+        match chars[pos] {
+            c if c.is_ascii_digit() => {}
+            '.' if !is_float && chars.get(pos + 1).is_some_and(char::is_ascii_digit) => {
+                is_float = true;
             }
-            continue;
+            _ => break,
+        }
+        pos += 1;
+    }
+    (pos, is_float)
+}
+
+/// The malformed word starting at `start`, running on from `from`.
+fn invalid_identifier(chars: &[char], start: usize, from: usize) -> LexError {
+    let end = chars[from..]
+        .iter()
+        .position(|&c| !is_word_char(c))
+        .map_or(chars.len(), |i| from + i);
+    LexError::InvalidIdentifier(chars[start..end].iter().collect(), start)
+}
+
+/// Tokenize a GROQ query string into a sequence of tokens, ending with `Eof`.
+pub fn tokenize(input: &str) -> Result<Vec<SpannedToken>, LexError> {
+    Lexer::new(input).collect()
+}
+
+/// Lazy tokenizer: yields tokens one at a time, ending with `Eof`. After an
+/// error or `Eof` the iterator is exhausted.
+pub struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    done: bool,
+}
+
+impl Lexer {
+    pub fn new(input: &str) -> Self {
+        Self {
+            chars: input.chars().collect(),
+            pos: 0,
+            done: false,
         }
+    }
+
+    fn next_token(&mut self) -> Result<SpannedToken, LexError> {
+        let chars = &self.chars;
+        let mut pos = self.pos;
 
-        let start = pos;
+        while pos < chars.len() {
+            let ch = chars[pos];
 
-        let token = match ch {
-            '*' => {
+            // Skip whitespace
+            if ch.is_whitespace() {
                 pos += 1;
-                Token::Star
+                continue;
             }
-            '.' => {
-                if pos + 2 < chars.len() && chars[pos + 1] == '.' && chars[pos + 2] == '.' {
-                    pos += 3;
-                    Token::Ellipsis
-                } else {
+
+            // Skip single-line comments
+            if ch == '/' && pos + 1 < chars.len() && chars[pos + 1] == '/' {
+                while pos < chars.len() && chars[pos] != '\n' {
                     pos += 1;
-                    Token::Dot
                 }
+                continue;
             }
-            ',' => {
-                pos += 1;
-                Token::Comma
-            }
-            ':' => {
-                pos += 1;
-                Token::Colon
-            }
-            '@' => {
-                pos += 1;
-                Token::At
-            }
-            '^' => {
-                pos += 1;
-                Token::Caret
-            }
-            '(' => {
-                pos += 1;
-                Token::LParen
-            }
-            ')' => {
-                pos += 1;
-                Token::RParen
-            }
-            '[' => {
-                pos += 1;
-                Token::LBracket
-            }
-            ']' => {
-                pos += 1;
-                Token::RBracket
-            }
-            '{' => {
-                pos += 1;
-                Token::LBrace
-            }
-            '}' => {
-                pos += 1;
-                Token::RBrace
-            }
-            '=' => {
-                if pos + 1 < chars.len() && chars[pos + 1] == '=' {
-                    pos += 2;
-                    Token::Eq
-                } else {
-                    return Err(LexError::UnexpectedChar(ch, pos));
+
+            let start = pos;
+
+            let token = match ch {
+                '*' => {
+                    pos += 1;
+                    Token::Star
                 }
-            }
-            '!' => {
-                if pos + 1 < chars.len() && chars[pos + 1] == '=' {
-                    pos += 2;
-                    Token::Neq
-                } else {
+                '.' => {
+                    if pos + 2 < chars.len() && chars[pos + 1] == '.' && chars[pos + 2] == '.' {
+                        pos += 3;
+                        Token::Ellipsis
+                    } else if pos + 1 < chars.len() && chars[pos + 1] == '.' {
+                        pos += 2;
+                        Token::DotDot
+                    } else {
+                        pos += 1;
+                        Token::Dot
+                    }
+                }
+                ',' => {
                     pos += 1;
-                    Token::Not
+                    Token::Comma
                 }
-            }
-            '<' => {
-                if pos + 1 < chars.len() && chars[pos + 1] == '=' {
-                    pos += 2;
-                    Token::Lte
-                } else {
+                ':' => {
                     pos += 1;
-                    Token::Lt
+                    Token::Colon
                 }
-            }
-            '>' => {
-                if pos + 1 < chars.len() && chars[pos + 1] == '=' {
-                    pos += 2;
-                    Token::Gte
-                } else {
+                '@' => {
                     pos += 1;
-                    Token::Gt
+                    Token::At
                 }
-            }
-            '&' => {
-                if pos + 1 < chars.len() && chars[pos + 1] == '&' {
-                    pos += 2;
-                    Token::And
-                } else {
-                    return Err(LexError::UnexpectedChar(ch, pos));
+                '^' => {
+                    pos += 1;
+                    Token::Caret
                 }
-            }
-            '|' => {
-                if pos + 1 < chars.len() && chars[pos + 1] == '|' {
-                    pos += 2;
-                    Token::Or
-                } else {
+                '(' => {
                     pos += 1;
-                    Token::Pipe
+                    Token::LParen
                 }
-            }
-            '-' => {
-                if pos + 1 < chars.len() && chars[pos + 1] == '>' {
-                    pos += 2;
-                    Token::Arrow
-                } else if pos + 1 < chars.len() && chars[pos + 1].is_ascii_digit() {
-                    // Negative number
+                ')' => {
                     pos += 1;
-                    let num_start = pos;
-                    let mut is_float = false;
-                    while pos < chars.len() && (chars[pos].is_ascii_digit() || chars[pos] == '.') {
-                        if chars[pos] == '.' {
-                            is_float = true;
-                        }
+                    Token::RParen
+                }
+                '[' => {
+                    pos += 1;
+                    Token::LBracket
+                }
+                ']' => {
+                    pos += 1;
+                    Token::RBracket
+                }
+                '{' => {
+                    pos += 1;
+                    Token::LBrace
+                }
+                '}' => {
+                    pos += 1;
+                    Token::RBrace
+                }
+                '=' if pos + 1 < chars.len() && chars[pos + 1] == '=' => {
+                    pos += 2;
+                    Token::Eq
+                }
+                '=' if pos + 1 < chars.len() && chars[pos + 1] == '>' => {
+                    pos += 2;
+                    Token::FatArrow
+                }
+                '!' => {
+                    if pos + 1 < chars.len() && chars[pos + 1] == '=' {
+                        pos += 2;
+                        Token::Neq
+                    } else {
                         pos += 1;
+                        Token::Not
                     }
-                    let num_str = &input[num_start..pos];
-                    if is_float {
-                        Token::Float(-num_str.parse::<f64>().unwrap())
+                }
+                '<' => {
+                    if pos + 1 < chars.len() && chars[pos + 1] == '=' {
+                        pos += 2;
+                        Token::Lte
                     } else {
-                        Token::Integer(-num_str.parse::<i64>().unwrap())
+                        pos += 1;
+                        Token::Lt
                     }
-                } else {
-                    return Err(LexError::UnexpectedChar(ch, pos));
                 }
-            }
-            '"' | '\'' => {
-                let quote = ch;
-                pos += 1;
-                let str_start = pos;
-                while pos < chars.len() && chars[pos] != quote {
-                    if chars[pos] == '\\' {
-                        pos += 1; // skip escaped char
+                '>' => {
+                    if pos + 1 < chars.len() && chars[pos + 1] == '=' {
+                        pos += 2;
+                        Token::Gte
+                    } else {
+                        pos += 1;
+                        Token::Gt
                     }
-                    pos += 1;
                 }
-                if pos >= chars.len() {
-                    return Err(LexError::UnterminatedString(start));
+                '&' if pos + 1 < chars.len() && chars[pos + 1] == '&' => {
+                    pos += 2;
+                    Token::And
                 }
-                let s = input[str_start..pos].to_string();
-                pos += 1; // skip closing quote
-                Token::String(s)
-            }
-            c if c.is_ascii_digit() => {
-                let mut is_float = false;
-                while pos < chars.len() && (chars[pos].is_ascii_digit() || chars[pos] == '.') {
-                    if chars[pos] == '.' {
-                        // Check for .. (range) vs . (decimal)
-                        if pos + 1 < chars.len() && chars[pos + 1] == '.' {
-                            break;
+                '|' => {
+                    if pos + 1 < chars.len() && chars[pos + 1] == '|' {
+                        pos += 2;
+                        Token::Or
+                    } else {
+                        pos += 1;
+                        Token::Pipe
+                    }
+                }
+                '-' => {
+                    if pos + 1 < chars.len() && chars[pos + 1] == '>' {
+                        pos += 2;
+                        Token::Arrow
+                    } else if pos + 1 < chars.len() && chars[pos + 1].is_ascii_digit() {
+                        // Negative number
+                        let num_start = pos + 1;
+                        let is_float;
+                        (pos, is_float) = scan_number(chars, num_start);
+                        if pos < chars.len() && is_word_char(chars[pos]) {
+                            return Err(invalid_identifier(chars, start, pos));
                         }
-                        is_float = true;
+                        number(chars, start, pos, is_float)?
+                    } else {
+                        return Err(LexError::UnexpectedChar(ch, pos));
                     }
+                }
+                '"' | '\'' => {
+                    let quote = ch;
                     pos += 1;
+                    let str_start = pos;
+                    while pos < chars.len() && chars[pos] != quote {
+                        if chars[pos] == '\\' {
+                            pos += 1; // skip escaped char
+                        }
+                        pos += 1;
+                    }
+                    if pos >= chars.len() {
+                        return Err(LexError::UnterminatedString(start));
+                    }
+                    let s = chars[str_start..pos].iter().collect();
+                    pos += 1; // skip closing quote
+                    Token::String(s)
                 }
-                let num_str = &input[start..pos];
-                if is_float {
-                    Token::Float(num_str.parse().unwrap())
-                } else {
-                    Token::Integer(num_str.parse().unwrap())
+                c if c.is_ascii_digit() => {
+                    let is_float;
+                    (pos, is_float) = scan_number(chars, pos);
+                    if pos < chars.len() && is_word_char(chars[pos]) {
+                        return Err(invalid_identifier(chars, start, pos));
+                    }
+                    number(chars, start, pos, is_float)?
                 }
-            }
-            c if c.is_alphabetic() || c == '_' || c == '$' => {
-                while pos < chars.len() && (chars[pos].is_alphanumeric() || chars[pos] == '_') {
+                '$' => {
                     pos += 1;
+                    if pos < chars.len() && is_ident_start(chars[pos]) {
+                        while pos < chars.len() && is_ident_continue(chars[pos]) {
+                            pos += 1;
+                        }
+                    }
+                    if pos < chars.len() && is_word_char(chars[pos]) {
+                        return Err(invalid_identifier(chars, start, pos));
+                    }
+                    if pos == start + 1 {
+                        return Err(LexError::UnexpectedChar('$', start));
+                    }
+                    Token::Param(chars[start + 1..pos].iter().collect())
                 }
-                let word = &input[start..pos];
-                match word {
-                    "true" => Token::Bool(true),
-                    "false" => Token::Bool(false),
-                    "null" => Token::Null,
-                    "match" => Token::Match,
-                    "in" => Token::In,
-                    "asc" => Token::Asc,
-                    "desc" => Token::Desc,
-                    _ => Token::Ident(word.to_string()),
+                c if is_ident_start(c) => {
+                    while pos < chars.len() && is_ident_continue(chars[pos]) {
+                        pos += 1;
+                    }
+                    if pos < chars.len() && is_word_char(chars[pos]) {
+                        return Err(invalid_identifier(chars, start, pos));
+                    }
+                    let word: String = chars[start..pos].iter().collect();
+                    match word.as_str() {
+                        "true" => Token::Bool(true),
+                        "false" => Token::Bool(false),
+                        "null" => Token::Null,
+                        "match" => Token::Match,
+                        "in" => Token::In,
+                        "asc" => Token::Asc,
+                        "desc" => Token::Desc,
+                        _ => Token::Ident(word),
+                    }
                 }
-            }
-            _ => return Err(LexError::UnexpectedChar(ch, pos)),
-        };
+                c if c.is_alphabetic() => return Err(invalid_identifier(chars, start, start)),
+                _ => return Err(LexError::UnexpectedChar(ch, pos)),
+            };
+
+            self.pos = pos;
+            return Ok(SpannedToken {
+                token,
+                span: Span { start, end: pos },
+            });
+        }
 
-        tokens.push(SpannedToken {
-            token,
-            span: Span { start, end: pos },
-        });
+        self.pos = pos;
+        self.done = true;
+        Ok(SpannedToken {
+            token: Token::Eof,
+            span: Span {
+                start: pos,
+                end: pos,
+            },
+        })
     }
+}
 
-    tokens.push(SpannedToken {
-        token: Token::Eof,
-        span: Span {
-            start: pos,
-            end: pos,
-        },
-    });
+impl Iterator for Lexer {
+    type Item = Result<SpannedToken, LexError>;
 
-    Ok(tokens)
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let token = self.next_token();
+        if token.is_err() {
+            self.done = true;
+        }
+        Some(token)
+    }
 }
 
 #[cfg(test)]
@@ -362,6 +490,31 @@ mod tests {
             .collect()
     }
 
+    #[test]
+    fn lexer_yields_tokens_lazily() {
+        let mut lexer = Lexer::new("*[_type == \"post\"] ~");
+        assert_eq!(lexer.next().unwrap().unwrap().token, Token::Star);
+        let bracket = lexer.next().unwrap().unwrap();
+        assert_eq!(bracket.token, Token::LBracket);
+        assert_eq!(bracket.span, Span { start: 1, end: 2 });
+        // The unlexable `~` is only reached after the tokens before it.
+        let rest: Vec<_> = lexer.collect();
+        assert_eq!(rest.len(), 5);
+        assert!(matches!(rest[4], Err(LexError::UnexpectedChar('~', 19))));
+    }
+
+    #[test]
+    fn lexer_matches_tokenize() {
+        let input = "*[_type == $type && count(tags) > 2]{title, \"a\": author->name} | order(x desc)[0..9]";
+        let lazy: Vec<SpannedToken> = Lexer::new(input).map(Result::unwrap).collect();
+        assert_eq!(lazy, tokenize(input).unwrap());
+        assert_eq!(lazy.last().unwrap().token, Token::Eof);
+
+        let mut lexer = Lexer::new("");
+        assert_eq!(lexer.next().unwrap().unwrap().token, Token::Eof);
+        assert!(lexer.next().is_none());
+    }
+
     #[test]
     fn tokenize_simple_filter() {
         let tokens = tok("*[_type == \"post\"]");
@@ -428,6 +581,38 @@ mod tests {
         assert_eq!(tokens[2], Token::Ident("name".into()));
     }
 
+    #[test]
+    fn tokenize_param() {
+        let tokens = tok("_type == $type");
+        assert_eq!(tokens[2], Token::Param("type".into()));
+    }
+
+    #[test]
+    fn tokenize_fat_arrow() {
+        let tokens = tok("a == b => c");
+        assert_eq!(tokens[1], Token::Eq);
+        assert_eq!(tokens[3], Token::FatArrow);
+    }
+
+    #[test]
+    fn tokenize_ranges() {
+        let tokens = tok("[0..10] [0...10]");
+        assert_eq!(tokens[1], Token::Integer(0));
+        assert_eq!(tokens[2], Token::DotDot);
+        assert_eq!(tokens[3], Token::Integer(10));
+        assert_eq!(tokens[7], Token::Ellipsis);
+
+        let tokens = tok("[-3..-1] [2...] [1.5..2]");
+        assert_eq!(tokens[1], Token::Integer(-3));
+        assert_eq!(tokens[2], Token::DotDot);
+        assert_eq!(tokens[3], Token::Integer(-1));
+        assert_eq!(tokens[6], Token::Integer(2));
+        assert_eq!(tokens[7], Token::Ellipsis);
+        assert_eq!(tokens[8], Token::RBracket);
+        assert_eq!(tokens[10], Token::Float(1.5));
+        assert_eq!(tokens[11], Token::DotDot);
+    }
+
     #[test]
     fn tokenize_ellipsis() {
         let tokens = tok("{...}");
@@ -436,6 +621,67 @@ mod tests {
         assert_eq!(tokens[2], Token::RBrace);
     }
 
+    #[test]
+    fn identifier_grammar() {
+        assert_eq!(tok("_id")[0], Token::Ident("_id".into()));
+        assert_eq!(tok("$_from2")[0], Token::Param("_from2".into()));
+
+        let invalid = |input: &str| match tokenize(input) {
+            Err(LexError::InvalidIdentifier(word, pos)) => (word, pos),
+            other => panic!("{input}: {other:?}"),
+        };
+        // Digit-leading words are not identifiers, nor a number and one.
+        assert_eq!(invalid("2nd == 1"), ("2nd".into(), 0));
+        // Disallowed trailing and inner characters reject the whole word.
+        assert_eq!(invalid("*{titleé}"), ("titleé".into(), 2));
+        assert_eq!(invalid("cafe\u{301} == 1"), ("cafe\u{301}".into(), 0));
+        assert_eq!(invalid("a == title$x"), ("title$x".into(), 5));
+        assert_eq!(invalid("$1"), ("$1".into(), 0));
+        assert_eq!(invalid("éclair"), ("éclair".into(), 0));
+        assert!(matches!(
+            tokenize("a == $ "),
+            Err(LexError::UnexpectedChar('$', 5))
+        ));
+    }
+
+    #[test]
+    fn out_of_range_integers_are_errors() {
+        assert!(matches!(
+            tokenize("*[n == 99999999999999999999]"),
+            Err(LexError::NumberOutOfRange(n, 7)) if n == "99999999999999999999"
+        ));
+        assert!(matches!(
+            tokenize("-9223372036854775809"),
+            Err(LexError::NumberOutOfRange(..))
+        ));
+        assert_eq!(tok("-9223372036854775808")[0], Token::Integer(i64::MIN));
+        assert_eq!(tok("99999999999999999999.5")[0], Token::Float(1e20));
+    }
+
+    #[test]
+    fn numbers_at_the_edges_of_their_range() {
+        assert_eq!(tok("9223372036854775807")[0], Token::Integer(i64::MAX));
+        assert!(matches!(
+            tokenize("9223372036854775808"),
+            Err(LexError::NumberOutOfRange(n, 0)) if n == "9223372036854775808"
+        ));
+
+        let large = format!("1{}.5", "0".repeat(300));
+        assert_eq!(tok(&large)[0], Token::Float(1e300));
+        let too_large = format!("-1{}.5", "0".repeat(400));
+        assert!(matches!(
+            tokenize(&too_large),
+            Err(LexError::NumberOutOfRange(n, 0)) if n == too_large
+        ));
+    }
+
+    #[test]
+    fn positions_count_characters_not_bytes() {
+        let tokens = tok("\"é\" == 5");
+        assert_eq!(tokens[0], Token::String("é".into()));
+        assert_eq!(tokens[2], Token::Integer(5));
+    }
+
     #[test]
     fn unterminated_string_error() {
         let result = tokenize("\"hello");
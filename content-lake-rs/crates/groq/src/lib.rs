@@ -1,6 +1,9 @@
+pub mod analyze;
 pub mod ast;
 pub mod eval;
 pub mod functions;
 pub mod lexer;
+pub mod params;
 pub mod parser;
+pub mod print;
 pub mod sql_gen;
@@ -0,0 +1,240 @@
+//! Query parameter checks.
+//!
+//! Parameters arrive as untyped JSON. Before evaluation every `$name` a
+//! query references must be supplied, and parameters used where the
+//! language needs a specific type (slice bounds, `round` precision,
+//! `references` ids) must have that type.
+
+use serde_json::Value;
+
+use crate::ast::Expr;
+use crate::parser::exclusive_end;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ParamError {
+    #[error("missing parameter: ${0}")]
+    Missing(String),
+    #[error("parameter ${name} must be {expected}, got {found}")]
+    TypeMismatch {
+        name: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+/// Check `expr`'s parameter references against `params` and lower
+/// parameterised slices to plain [`Expr::Slice`]s.
+pub fn bind(expr: &Expr, params: &Value) -> Result<Expr, ParamError> {
+    Binder { params }.lower(expr)
+}
+
+/// What a parameter is used as, and so which JSON types it accepts.
+#[derive(Clone, Copy)]
+enum Usage {
+    Any,
+    Integer,
+    /// A document id or an array of them.
+    Ids,
+}
+
+struct Binder<'a> {
+    params: &'a Value,
+}
+
+impl Binder<'_> {
+    fn param(&self, name: &str, usage: Usage) -> Result<&Value, ParamError> {
+        let value = self
+            .params
+            .get(name)
+            .ok_or_else(|| ParamError::Missing(name.to_string()))?;
+        let ok = match usage {
+            Usage::Any => true,
+            Usage::Integer => value.is_i64(),
+            Usage::Ids => match value {
+                Value::String(_) => true,
+                Value::Array(items) => items.iter().all(Value::is_string),
+                _ => false,
+            },
+        };
+        if ok {
+            return Ok(value);
+        }
+        Err(ParamError::TypeMismatch {
+            name: name.to_string(),
+            expected: match usage {
+                Usage::Any => "any value",
+                Usage::Integer => "an integer",
+                Usage::Ids => "a string or an array of strings",
+            },
+            found: type_name(value),
+        })
+    }
+
+    /// Lower an argument in a typed position: a bare parameter is checked
+    /// against `usage`, anything else is lowered normally.
+    fn typed(&self, expr: &Expr, usage: Usage) -> Result<Expr, ParamError> {
+        match expr {
+            Expr::Param(name) => {
+                self.param(name, usage)?;
+                Ok(expr.clone())
+            }
+            other => self.lower(other),
+        }
+    }
+
+    fn slice_bound(&self, expr: &Expr) -> Result<i64, ParamError> {
+        match expr {
+            Expr::IntLiteral(n) => Ok(*n),
+            Expr::Param(name) => Ok(self
+                .param(name, Usage::Integer)?
+                .as_i64()
+                .expect("checked integer")),
+            _ => unreachable!("the parser only produces integer and parameter bounds"),
+        }
+    }
+
+    fn lower(&self, expr: &Expr) -> Result<Expr, ParamError> {
+        let boxed = |e: &Expr| self.lower(e).map(Box::new);
+        Ok(match expr {
+            Expr::Param(name) => {
+                self.param(name, Usage::Any)?;
+                expr.clone()
+            }
+            Expr::ParamSlice(base, start, end, inclusive) => {
+                let start = self.slice_bound(start)?;
+                let end = self.slice_bound(end)?;
+                Expr::Slice(boxed(base)?, start, exclusive_end(end, *inclusive))
+            }
+            Expr::StringLiteral(_)
+            | Expr::IntLiteral(_)
+            | Expr::FloatLiteral(_)
+            | Expr::BoolLiteral(_)
+            | Expr::Null
+            | Expr::Ident(_)
+            | Expr::This
+            | Expr::Parent(_)
+            | Expr::Everything => expr.clone(),
+            Expr::Array(items) => Expr::Array(self.lower_all(items)?),
+            Expr::Object(fields) => Expr::Object(self.lower_fields(fields)?),
+            Expr::Projection(fields) => Expr::Projection(self.lower_fields(fields)?),
+            Expr::DotAccess(base, field) => Expr::DotAccess(boxed(base)?, field.clone()),
+            Expr::Deref(base, field) => Expr::Deref(boxed(base)?, field.clone()),
+            Expr::DerefDocument(base) => Expr::DerefDocument(boxed(base)?),
+            Expr::Iterate(base) => Expr::Iterate(boxed(base)?),
+            Expr::Not(base) => Expr::Not(boxed(base)?),
+            Expr::Filter(base) => Expr::Filter(boxed(base)?),
+            Expr::Order(base, ascending) => Expr::Order(boxed(base)?, *ascending),
+            Expr::Slice(base, start, end) => Expr::Slice(boxed(base)?, *start, *end),
+            Expr::Index(base, index) => Expr::Index(boxed(base)?, *index),
+            Expr::Eq(l, r) => Expr::Eq(boxed(l)?, boxed(r)?),
+            Expr::Neq(l, r) => Expr::Neq(boxed(l)?, boxed(r)?),
+            Expr::Lt(l, r) => Expr::Lt(boxed(l)?, boxed(r)?),
+            Expr::Gt(l, r) => Expr::Gt(boxed(l)?, boxed(r)?),
+            Expr::Lte(l, r) => Expr::Lte(boxed(l)?, boxed(r)?),
+            Expr::Gte(l, r) => Expr::Gte(boxed(l)?, boxed(r)?),
+            Expr::In(l, r) => Expr::In(boxed(l)?, boxed(r)?),
+            Expr::Match(l, r) => Expr::Match(boxed(l)?, boxed(r)?),
+            Expr::And(l, r) => Expr::And(boxed(l)?, boxed(r)?),
+            Expr::Or(l, r) => Expr::Or(boxed(l)?, boxed(r)?),
+            Expr::Pipeline(stages) => Expr::Pipeline(self.lower_all(stages)?),
+            Expr::FuncCall(name, args) => {
+                let args = args
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| match (name.as_str(), i) {
+                        ("round", 1) => self.typed(arg, Usage::Integer),
+                        ("references", _) => self.typed(arg, Usage::Ids),
+                        _ => self.lower(arg),
+                    })
+                    .collect::<Result<_, _>>()?;
+                Expr::FuncCall(name.clone(), args)
+            }
+            Expr::Select(branches) => Expr::Select(
+                branches
+                    .iter()
+                    .map(|(cond, value)| {
+                        let cond = cond.as_ref().map(|c| self.lower(c)).transpose()?;
+                        Ok((cond, self.lower(value)?))
+                    })
+                    .collect::<Result<_, ParamError>>()?,
+            ),
+        })
+    }
+
+    fn lower_all(&self, exprs: &[Expr]) -> Result<Vec<Expr>, ParamError> {
+        exprs.iter().map(|e| self.lower(e)).collect()
+    }
+
+    fn lower_fields(&self, fields: &[(String, Expr)]) -> Result<Vec<(String, Expr)>, ParamError> {
+        fields
+            .iter()
+            .map(|(name, e)| Ok((name.clone(), self.lower(e)?)))
+            .collect()
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(n) if n.is_i64() => "an integer",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use serde_json::json;
+
+    #[test]
+    fn missing_param_is_reported() {
+        let expr = parse("*[_type == $type && slug == $slug]").unwrap();
+        assert_eq!(
+            bind(&expr, &json!({"type": "post"})).unwrap_err(),
+            ParamError::Missing("slug".into())
+        );
+        assert!(bind(&expr, &json!({"type": "post", "slug": null})).is_ok());
+    }
+
+    #[test]
+    fn slice_bounds_must_be_integers() {
+        let expr = parse("*[_type == \"post\"][0...$limit]").unwrap();
+        assert_eq!(
+            bind(&expr, &json!({"limit": "10"})).unwrap_err(),
+            ParamError::TypeMismatch {
+                name: "limit".into(),
+                expected: "an integer",
+                found: "a string",
+            }
+        );
+        let Expr::Pipeline(stages) = bind(&expr, &json!({"limit": 10})).unwrap() else {
+            panic!("expected pipeline")
+        };
+        assert!(matches!(stages[2], Expr::Slice(_, 0, 10)));
+
+        let expr = parse("*[$from..$to]").unwrap();
+        let Expr::Pipeline(stages) = bind(&expr, &json!({"from": 2, "to": 4})).unwrap() else {
+            panic!("expected pipeline")
+        };
+        assert!(matches!(stages[1], Expr::Slice(_, 2, 5)));
+    }
+
+    #[test]
+    fn function_arguments_are_typed() {
+        let expr = parse("*[references($ids)]{\"n\": round(n, $digits)}").unwrap();
+        assert!(bind(&expr, &json!({"ids": ["a", "b"], "digits": 2})).is_ok());
+        assert!(matches!(
+            bind(&expr, &json!({"ids": [1], "digits": 2})),
+            Err(ParamError::TypeMismatch { name, .. }) if name == "ids"
+        ));
+        assert!(matches!(
+            bind(&expr, &json!({"ids": "a", "digits": 1.5})),
+            Err(ParamError::TypeMismatch { name, .. }) if name == "digits"
+        ));
+    }
+}
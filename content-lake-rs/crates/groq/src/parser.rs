@@ -1,5 +1,5 @@
-use crate::ast::Expr;
-use crate::lexer::{tokenize, LexError, SpannedToken, Token};
+use crate::ast::{Expr, SLICE_TO_END};
+use crate::lexer::{tokenize, LexError, Span, SpannedToken, Token};
 
 /// Parser error types.
 #[derive(Debug, thiserror::Error)]
@@ -7,26 +7,142 @@ pub enum ParseError {
     #[error("lex error: {0}")]
     Lex(#[from] LexError),
     #[error("unexpected token: {found}, expected: {expected}")]
-    UnexpectedToken { found: String, expected: String },
+    UnexpectedToken {
+        found: String,
+        expected: String,
+        span: Span,
+    },
     #[error("unexpected end of input")]
-    UnexpectedEof,
+    UnexpectedEof { span: Span },
+    #[error("query is nested more than {limit} levels deep")]
+    NestingTooDeep { limit: usize, span: Span },
 }
 
-/// Parse a GROQ query string into an AST.
+impl ParseError {
+    /// Where in the query the error occurred.
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::Lex(LexError::UnexpectedChar(_, pos))
+            | ParseError::Lex(LexError::UnterminatedString(pos)) => Span {
+                start: *pos,
+                end: pos + 1,
+            },
+            ParseError::Lex(LexError::InvalidIdentifier(word, pos))
+            | ParseError::Lex(LexError::NumberOutOfRange(word, pos)) => Span {
+                start: *pos,
+                end: pos + word.chars().count(),
+            },
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::UnexpectedEof { span }
+            | ParseError::NestingTooDeep { span, .. } => *span,
+        }
+    }
+
+    /// Render the error under the line of `input` it occurred on:
+    ///
+    /// ```text
+    /// *[_type == "post"]{title subtitle}
+    ///                          ^^^^^^^^ expected `,` or `}`
+    /// ```
+    ///
+    /// For a query spanning several lines, the message ends with the line
+    /// and column, e.g. `expected expression at line 3, column 6`.
+    pub fn render(&self, input: &str) -> String {
+        let span = self.span();
+        let chars: Vec<char> = input.chars().collect();
+        let start = span.start.min(chars.len());
+        let line_start = chars[..start]
+            .iter()
+            .rposition(|&c| c == '\n')
+            .map_or(0, |i| i + 1);
+        let line_end = chars[start..]
+            .iter()
+            .position(|&c| c == '\n')
+            .map_or(chars.len(), |i| start + i);
+        let line: String = chars[line_start..line_end].iter().collect();
+        let width = span.end.min(line_end).saturating_sub(start).max(1);
+        let mut message = match self {
+            ParseError::UnexpectedToken { expected, .. } => format!("expected {expected}"),
+            other => other.to_string(),
+        };
+        if input.contains('\n') {
+            let (line, column) = span.line_col(input);
+            message.push_str(&format!(" at line {line}, column {column}"));
+        }
+        format!(
+            "{line}\n{}{} {message}",
+            " ".repeat(start - line_start),
+            "^".repeat(width)
+        )
+    }
+}
+
+/// Parser settings.
+#[derive(Debug, Clone, Copy)]
+pub struct ParseOptions {
+    /// Reject malformed projection fields and trailing input. When off,
+    /// the rest of a malformed projection and anything after the query are
+    /// skipped, which suits tooling working on partial queries.
+    pub strict: bool,
+    /// How deeply expressions, groups and projections may nest before
+    /// parsing fails with [`ParseError::NestingTooDeep`], bounding the
+    /// parser's recursion on hostile input.
+    pub max_depth: usize,
+}
+
+/// Default for [`ParseOptions::max_depth`].
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            strict: true,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+/// Parse a GROQ query string into an AST, in strict mode.
 pub fn parse(input: &str) -> Result<Expr, ParseError> {
+    parse_with_options(input, ParseOptions::default())
+}
+
+/// Parse a GROQ query string into an AST.
+pub fn parse_with_options(input: &str, options: ParseOptions) -> Result<Expr, ParseError> {
     let tokens = tokenize(input)?;
-    let mut parser = Parser::new(tokens);
-    parser.parse_expr()
+    let mut parser = Parser::new(tokens, options);
+    let expr = parser.parse_expr()?;
+    if options.strict {
+        parser.expect_end()?;
+    }
+    Ok(expr)
+}
+
+/// The exclusive form of a slice's end bound. An inclusive `-1` is the last
+/// element, so the slice runs to the end.
+pub(crate) fn exclusive_end(end: i64, inclusive: bool) -> i64 {
+    match end {
+        -1 | SLICE_TO_END if inclusive => SLICE_TO_END,
+        end if inclusive => end + 1,
+        end => end,
+    }
 }
 
 struct Parser {
     tokens: Vec<SpannedToken>,
     pos: usize,
+    options: ParseOptions,
+    depth: usize,
 }
 
 impl Parser {
-    fn new(tokens: Vec<SpannedToken>) -> Self {
-        Self { tokens, pos: 0 }
+    fn new(tokens: Vec<SpannedToken>, options: ParseOptions) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            options,
+            depth: 0,
+        }
     }
 
     fn peek(&self) -> &Token {
@@ -36,6 +152,13 @@ impl Parser {
             .unwrap_or(&Token::Eof)
     }
 
+    fn peek_at(&self, offset: usize) -> &Token {
+        self.tokens
+            .get(self.pos + offset)
+            .map(|t| &t.token)
+            .unwrap_or(&Token::Eof)
+    }
+
     fn advance(&mut self) -> &Token {
         let token = self
             .tokens
@@ -46,15 +169,74 @@ impl Parser {
         token
     }
 
+    /// Error for the token at the current position.
+    fn unexpected(&self, expected: impl Into<String>) -> ParseError {
+        let span = self
+            .tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(Span { start: 0, end: 0 }, |t| t.span);
+        match self.peek() {
+            Token::Eof => ParseError::UnexpectedEof { span },
+            found => ParseError::UnexpectedToken {
+                found: format!("{found:?}"),
+                expected: expected.into(),
+                span,
+            },
+        }
+    }
+
     fn expect(&mut self, expected: &Token) -> Result<(), ParseError> {
-        let found = self.advance().clone();
-        if &found == expected {
+        if self.peek() == expected {
+            self.advance();
             Ok(())
         } else {
-            Err(ParseError::UnexpectedToken {
-                found: format!("{found:?}"),
-                expected: format!("{expected:?}"),
-            })
+            Err(self.unexpected(format!("{expected:?}")))
+        }
+    }
+
+    fn expect_end(&self) -> Result<(), ParseError> {
+        match self.peek() {
+            Token::Eof => Ok(()),
+            _ => Err(self.unexpected("end of query")),
+        }
+    }
+
+    /// Run `parse` one nesting level deeper, failing once the configured
+    /// maximum depth is exceeded.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        if self.depth >= self.options.max_depth {
+            let span = self
+                .tokens
+                .get(self.pos)
+                .or(self.tokens.last())
+                .map_or(Span { start: 0, end: 0 }, |t| t.span);
+            return Err(ParseError::NestingTooDeep {
+                limit: self.options.max_depth,
+                span,
+            });
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Skip to the `}` closing the current projection, leaving it unconsumed.
+    fn skip_to_closing_brace(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.peek() {
+                Token::Eof => return,
+                Token::RBrace if depth == 0 => return,
+                Token::RBrace => depth -= 1,
+                Token::LBrace => depth += 1,
+                _ => {}
+            }
+            self.advance();
         }
     }
 
@@ -62,57 +244,113 @@ impl Parser {
         match self.peek().clone() {
             Token::Star => {
                 self.advance();
-                if self.peek() == &Token::LBracket {
-                    self.advance();
-                    let filter = self.parse_filter_expr()?;
-                    self.expect(&Token::RBracket)?;
-                    if self.peek() == &Token::LBrace {
-                        self.advance();
-                        let projection = self.parse_projection()?;
-                        self.expect(&Token::RBrace)?;
-                        Ok(Expr::Pipeline(vec![
-                            Expr::Everything,
-                            Expr::Filter(Box::new(filter)),
-                            Expr::Projection(projection),
-                        ]))
-                    } else if self.peek() == &Token::Pipe {
-                        self.advance();
-                        let pipe = self.parse_pipe_expr()?;
-                        Ok(Expr::Pipeline(vec![
-                            Expr::Everything,
-                            Expr::Filter(Box::new(filter)),
-                            pipe,
-                        ]))
-                    } else {
-                        Ok(Expr::Pipeline(vec![
-                            Expr::Everything,
-                            Expr::Filter(Box::new(filter)),
-                        ]))
+                let mut stages = vec![Expr::Everything];
+                loop {
+                    match self.peek() {
+                        Token::LBracket => {
+                            self.advance();
+                            stages.push(self.parse_subscript()?);
+                        }
+                        Token::LBrace => stages.push(self.parse_projection_stage()?),
+                        Token::Pipe => {
+                            self.advance();
+                            stages.push(self.parse_pipe_expr()?);
+                        }
+                        _ => break,
                     }
-                } else {
+                }
+                if stages.len() == 1 {
                     Ok(Expr::Everything)
+                } else {
+                    Ok(Expr::Pipeline(stages))
                 }
             }
+            // A bare `{...}` projects the current scope. Parsed as an
+            // object so that comparisons such as `{...} == x` still work.
+            Token::LBrace => match self.parse_filter_expr()? {
+                Expr::Object(fields) => Ok(Expr::Projection(fields)),
+                expr => Ok(expr),
+            },
             _ => self.parse_filter_expr(),
         }
     }
 
-    fn parse_filter_expr(&mut self) -> Result<Expr, ParseError> {
-        let left = self.parse_comparison()?;
+    /// Parse a `{...}` projection stage, including its braces.
+    fn parse_projection_stage(&mut self) -> Result<Expr, ParseError> {
+        self.expect(&Token::LBrace)?;
+        let projection = self.parse_projection()?;
+        self.expect(&Token::RBrace)?;
+        Ok(Expr::Projection(projection))
+    }
 
-        match self.peek().clone() {
-            Token::And => {
-                self.advance();
-                let right = self.parse_filter_expr()?;
-                Ok(Expr::And(Box::new(left), Box::new(right)))
-            }
-            Token::Or => {
+    /// Parse the inside of `[...]` after the opening bracket: an index `n`,
+    /// a slice `a..b` (inclusive) / `a...b` (exclusive) where either bound
+    /// may be left out, or a filter.
+    ///
+    /// Index and slice stages apply to the pipeline's current results, so
+    /// their base is `This`; the stored end bound is exclusive.
+    fn parse_subscript(&mut self) -> Result<Expr, ParseError> {
+        let base = Box::new(Expr::This);
+        if let (Token::Integer(n), Token::RBracket) = (self.peek().clone(), self.peek_at(1)) {
+            self.advance();
+            self.advance();
+            return Ok(Expr::Index(base, n));
+        }
+        let bound = |token: &Token| match token {
+            Token::Integer(n) => Some(Expr::IntLiteral(*n)),
+            Token::Param(name) => Some(Expr::Param(name.clone())),
+            _ => None,
+        };
+        let start = bound(self.peek());
+        let op = usize::from(start.is_some());
+        if matches!(self.peek_at(op), Token::DotDot | Token::Ellipsis) {
+            let inclusive = self.peek_at(op) == &Token::DotDot;
+            let end = bound(self.peek_at(op + 1));
+            for _ in 0..=op + usize::from(end.is_some()) {
                 self.advance();
-                let right = self.parse_filter_expr()?;
-                Ok(Expr::Or(Box::new(left), Box::new(right)))
             }
-            _ => Ok(left),
+            self.expect(&Token::RBracket)?;
+            let start = start.unwrap_or(Expr::IntLiteral(0));
+            let (end, inclusive) = match end {
+                Some(end) => (end, inclusive),
+                None => (Expr::IntLiteral(SLICE_TO_END), false),
+            };
+            return Ok(match (start, end) {
+                (Expr::IntLiteral(start), Expr::IntLiteral(end)) => {
+                    Expr::Slice(base, start, exclusive_end(end, inclusive))
+                }
+                (start, end) => Expr::ParamSlice(base, Box::new(start), Box::new(end), inclusive),
+            });
         }
+        let filter = self.parse_filter_expr()?;
+        self.expect(&Token::RBracket)?;
+        Ok(Expr::Filter(Box::new(filter)))
+    }
+
+    fn parse_filter_expr(&mut self) -> Result<Expr, ParseError> {
+        self.nested(Self::parse_or)
+    }
+
+    /// `a || b || c`, left-associative; binds looser than `&&`.
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.parse_and()?;
+        while self.peek() == &Token::Or {
+            self.advance();
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    /// `a && b && c`, left-associative.
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.parse_comparison()?;
+        while self.peek() == &Token::And {
+            self.advance();
+            let right = self.parse_comparison()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
     }
 
     fn parse_comparison(&mut self) -> Result<Expr, ParseError> {
@@ -154,52 +392,28 @@ impl Parser {
                 let right = self.parse_primary()?;
                 Ok(Expr::In(Box::new(left), Box::new(right)))
             }
+            Token::Match => {
+                self.advance();
+                let right = self.parse_primary()?;
+                Ok(Expr::Match(Box::new(left), Box::new(right)))
+            }
             _ => Ok(left),
         }
     }
 
     fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        self.nested(Self::parse_primary_inner)
+    }
+
+    fn parse_primary_inner(&mut self) -> Result<Expr, ParseError> {
         match self.peek().clone() {
             Token::Ident(name) => {
                 self.advance();
-                let mut expr = Expr::Ident(name);
-                // Handle dot access chains: a.b.c
-                while self.peek() == &Token::Dot {
-                    self.advance();
-                    match self.peek().clone() {
-                        Token::Ident(field) => {
-                            self.advance();
-                            expr = Expr::DotAccess(Box::new(expr), field);
-                        }
-                        _ => break,
-                    }
-                }
-                // Handle dereference: a->b
-                if self.peek() == &Token::Arrow {
-                    self.advance();
-                    if let Token::Ident(field) = self.peek().clone() {
-                        self.advance();
-                        expr = Expr::Deref(Box::new(expr), field);
-                    }
-                }
-                // Handle function calls: fn(args)
-                if self.peek() == &Token::LParen {
-                    if let Expr::Ident(fn_name) = &expr {
-                        let fn_name = fn_name.clone();
-                        self.advance();
-                        let mut args = Vec::new();
-                        if self.peek() != &Token::RParen {
-                            args.push(self.parse_filter_expr()?);
-                            while self.peek() == &Token::Comma {
-                                self.advance();
-                                args.push(self.parse_filter_expr()?);
-                            }
-                        }
-                        self.expect(&Token::RParen)?;
-                        expr = Expr::FuncCall(fn_name, args);
-                    }
-                }
-                Ok(expr)
+                self.parse_ident_expr(name)
+            }
+            Token::Param(name) => {
+                self.advance();
+                Ok(Expr::Param(name))
             }
             Token::String(s) => {
                 self.advance();
@@ -225,20 +439,60 @@ impl Parser {
                 self.advance();
                 Ok(Expr::This)
             }
+            // `^`, `^.^`, ... and field paths from there: `^.^._id`.
             Token::Caret => {
                 self.advance();
-                Ok(Expr::Parent)
+                let mut levels = 1;
+                while self.peek() == &Token::Dot && self.peek_at(1) == &Token::Caret {
+                    self.advance();
+                    self.advance();
+                    levels += 1;
+                }
+                let mut expr = Expr::Parent(levels);
+                while self.peek() == &Token::Dot {
+                    let Token::Ident(field) = self.peek_at(1).clone() else {
+                        break;
+                    };
+                    self.advance();
+                    self.advance();
+                    expr = Expr::DotAccess(Box::new(expr), field);
+                }
+                Ok(expr)
             }
             Token::Not => {
                 self.advance();
                 let expr = self.parse_primary()?;
                 Ok(Expr::Not(Box::new(expr)))
             }
+            // Grouping, which may hold a whole query and be followed by
+            // filter, slice and projection stages: `(*[a || b])[0...2]{c}`.
             Token::LParen => {
                 self.advance();
-                let expr = self.parse_filter_expr()?;
+                let expr = self.parse_expr()?;
                 self.expect(&Token::RParen)?;
-                Ok(expr)
+                let mut stages = vec![expr];
+                loop {
+                    match (self.peek(), self.peek_at(1)) {
+                        (Token::LBracket, Token::RBracket) => break,
+                        (Token::LBracket, _) => {
+                            self.advance();
+                            stages.push(self.parse_subscript()?);
+                        }
+                        (Token::LBrace, _) => stages.push(self.parse_projection_stage()?),
+                        _ => break,
+                    }
+                }
+                if stages.len() == 1 {
+                    Ok(stages.remove(0))
+                } else {
+                    Ok(Expr::Pipeline(stages))
+                }
+            }
+            Token::LBrace => {
+                self.advance();
+                let fields = self.parse_projection()?;
+                self.expect(&Token::RBrace)?;
+                Ok(Expr::Object(fields))
             }
             Token::LBracket => {
                 self.advance();
@@ -257,78 +511,224 @@ impl Parser {
                 self.advance();
                 Ok(Expr::Everything)
             }
-            Token::Eof => Err(ParseError::UnexpectedEof),
-            other => Err(ParseError::UnexpectedToken {
-                found: format!("{other:?}"),
-                expected: "expression".to_string(),
-            }),
+            _ => Err(self.unexpected("expression")),
         }
     }
 
-    fn parse_projection(&mut self) -> Result<Vec<(String, Expr)>, ParseError> {
-        let mut fields = Vec::new();
-
-        if self.peek() == &Token::Ellipsis {
+    /// Parse what follows an identifier: `ns::name`, field paths, `[]`,
+    /// `->` dereferences, projections and function calls.
+    fn parse_ident_expr(&mut self, name: String) -> Result<Expr, ParseError> {
+        // Namespaced function names: ns::name
+        let name = if self.peek() == &Token::Colon && self.peek_at(1) == &Token::Colon {
             self.advance();
-            fields.push(("...".to_string(), Expr::Everything));
-            if self.peek() == &Token::Comma {
-                self.advance();
+            self.advance();
+            match self.peek().clone() {
+                Token::Ident(member) => {
+                    self.advance();
+                    format!("{name}::{member}")
+                }
+                _ => return Err(self.unexpected("function name")),
+            }
+        } else {
+            name
+        };
+        let mut expr = Expr::Ident(name);
+        // Handle dot access chains: a.b.c
+        while self.peek() == &Token::Dot {
+            self.advance();
+            match self.peek().clone() {
+                Token::Ident(field) => {
+                    self.advance();
+                    expr = Expr::DotAccess(Box::new(expr), field);
+                }
+                _ => break,
             }
         }
-
-        while self.peek() != &Token::RBrace && self.peek() != &Token::Eof {
-            if self.peek() == &Token::Ellipsis {
+        // Handle array iteration `a[]`, dereference `a->b` / `a->`, and a
+        // projection `{...}` after either. Following `[]`, the deref and
+        // projection apply to each element.
+        let iterate = self.peek() == &Token::LBracket && self.peek_at(1) == &Token::RBracket;
+        if iterate {
+            self.advance();
+            self.advance();
+        }
+        let mut value = if iterate { Expr::This } else { expr.clone() };
+        let deref = self.peek() == &Token::Arrow;
+        if deref {
+            self.advance();
+            value = match self.peek().clone() {
+                Token::Ident(field) => {
+                    self.advance();
+                    Expr::Deref(Box::new(value), field)
+                }
+                _ => Expr::DerefDocument(Box::new(value)),
+            };
+        }
+        let projection = if (iterate || deref) && self.peek() == &Token::LBrace {
+            Some(self.parse_projection_stage()?)
+        } else {
+            None
+        };
+        if iterate {
+            let mut stages = vec![Expr::Iterate(Box::new(expr))];
+            if deref {
+                stages.push(value);
+            }
+            stages.extend(projection);
+            expr = if stages.len() == 1 {
+                stages.remove(0)
+            } else {
+                Expr::Pipeline(stages)
+            };
+        } else if let Some(projection) = projection {
+            expr = Expr::Pipeline(vec![value, projection]);
+        } else {
+            expr = value;
+        }
+        // Handle function calls: fn(args)
+        if self.peek() == &Token::LParen {
+            if matches!(&expr, Expr::Ident(n) if n == "select") {
                 self.advance();
-                fields.push(("...".to_string(), Expr::Everything));
-            } else if let Token::String(alias) = self.peek().clone() {
+                expr = self.parse_select_args()?;
+            } else if let Expr::Ident(fn_name) = &expr {
+                let fn_name = fn_name.clone();
                 self.advance();
-                self.expect(&Token::Colon)?;
-                let expr = self.parse_filter_expr()?;
-                fields.push((alias, expr));
-            } else if let Token::Ident(name) = self.peek().clone() {
+                let mut args = Vec::new();
+                if self.peek() != &Token::RParen {
+                    args.push(self.parse_expr()?);
+                    while self.peek() == &Token::Comma {
+                        self.advance();
+                        args.push(self.parse_expr()?);
+                    }
+                }
+                self.expect(&Token::RParen)?;
+                expr = Expr::FuncCall(fn_name, args);
+            }
+        }
+        Ok(expr)
+    }
+
+    /// Parse the arguments of `select(...)` after the opening parenthesis.
+    fn parse_select_args(&mut self) -> Result<Expr, ParseError> {
+        let mut branches = Vec::new();
+        while self.peek() != &Token::RParen {
+            let expr = self.parse_filter_expr()?;
+            if self.peek() == &Token::FatArrow {
                 self.advance();
-                if self.peek() == &Token::Colon {
+                let value = self.parse_filter_expr()?;
+                branches.push((Some(expr), value));
+            } else {
+                branches.push((None, expr));
+            }
+            if self.peek() != &Token::Comma {
+                break;
+            }
+            self.advance();
+        }
+        self.expect(&Token::RParen)?;
+        Ok(Expr::Select(branches))
+    }
+
+    fn parse_projection(&mut self) -> Result<Vec<(String, Expr)>, ParseError> {
+        self.nested(Self::parse_projection_fields)
+    }
+
+    fn parse_projection_fields(&mut self) -> Result<Vec<(String, Expr)>, ParseError> {
+        let mut fields = Vec::new();
+
+        while self.peek() != &Token::RBrace && self.peek() != &Token::Eof {
+            let next = self.peek_at(1).clone();
+            match self.peek().clone() {
+                Token::Ellipsis => {
+                    self.advance();
+                    if matches!(self.peek(), Token::Comma | Token::RBrace) {
+                        fields.push(("...".to_string(), Expr::Everything));
+                    } else {
+                        // Spread of an expression, e.g. `...select(...)`
+                        fields.push(("...".to_string(), self.parse_filter_expr()?));
+                    }
+                }
+                Token::String(alias) if next == Token::Colon => {
+                    self.advance();
+                    self.advance();
+                    let expr = self.parse_filter_expr()?;
+                    fields.push((alias, expr));
+                }
+                Token::Ident(name) if next == Token::Colon => {
+                    self.advance();
                     self.advance();
                     let expr = self.parse_filter_expr()?;
                     fields.push((name, expr));
-                } else {
+                }
+                Token::Ident(name) if matches!(next, Token::Comma | Token::RBrace) => {
+                    self.advance();
                     fields.push((name.clone(), Expr::Ident(name)));
                 }
-            } else {
-                break;
+                _ => {
+                    // Conditional spread: `cond => { ...fields }`
+                    let cond = self.parse_filter_expr()?;
+                    self.expect(&Token::FatArrow)?;
+                    let value = self.parse_filter_expr()?;
+                    fields.push(("...".to_string(), Expr::Select(vec![(Some(cond), value)])));
+                }
             }
 
-            if self.peek() == &Token::Comma {
-                self.advance();
+            match self.peek() {
+                Token::Comma => {
+                    self.advance();
+                }
+                Token::RBrace => break,
+                _ if !self.options.strict => {
+                    self.skip_to_closing_brace();
+                    break;
+                }
+                _ => return Err(self.unexpected("`,` or `}`")),
             }
         }
 
         Ok(fields)
     }
 
+    /// Parse `field [asc|desc])` after `order(`: exactly one field and at
+    /// most one direction.
+    fn parse_order_term(&mut self) -> Result<Expr, ParseError> {
+        if matches!(self.peek(), Token::Asc | Token::Desc) {
+            return Err(self.unexpected("field to order by"));
+        }
+        let field = self.parse_primary()?;
+        let ascending = match self.peek() {
+            Token::Asc => {
+                self.advance();
+                true
+            }
+            Token::Desc => {
+                self.advance();
+                false
+            }
+            _ => true,
+        };
+        if matches!(self.peek(), Token::Asc | Token::Desc) {
+            return Err(self.unexpected("`)` after the order direction"));
+        }
+        self.expect(&Token::RParen)?;
+        Ok(Expr::Order(Box::new(field), ascending))
+    }
+
+    /// Parse one stage after `|`: `order(...)`, a projection, a filter or
+    /// slice subscript, or any other expression such as a function call.
     fn parse_pipe_expr(&mut self) -> Result<Expr, ParseError> {
-        if let Token::Ident(name) = self.peek().clone() {
-            match name.as_str() {
-                "order" => {
-                    self.advance();
-                    self.expect(&Token::LParen)?;
-                    let field = self.parse_primary()?;
-                    let ascending = if self.peek() == &Token::Desc {
-                        self.advance();
-                        false
-                    } else {
-                        if self.peek() == &Token::Asc {
-                            self.advance();
-                        }
-                        true
-                    };
-                    self.expect(&Token::RParen)?;
-                    Ok(Expr::Order(Box::new(field), ascending))
-                }
-                _ => self.parse_filter_expr(),
+        match self.peek().clone() {
+            Token::Ident(name) if name == "order" && self.peek_at(1) == &Token::LParen => {
+                self.advance();
+                self.advance();
+                self.parse_order_term()
             }
-        } else {
-            self.parse_filter_expr()
+            Token::LBrace => self.parse_projection_stage(),
+            Token::LBracket => {
+                self.advance();
+                self.parse_subscript()
+            }
+            _ => self.parse_filter_expr(),
         }
     }
 }
@@ -337,6 +737,216 @@ impl Parser {
 mod tests {
     use super::*;
 
+    const LENIENT: ParseOptions = ParseOptions {
+        strict: false,
+        max_depth: DEFAULT_MAX_DEPTH,
+    };
+
+    #[test]
+    fn deep_nesting_is_rejected_cleanly() {
+        let depth = 10_000;
+        let query = format!("*[{}a{}]", "(".repeat(depth), ")".repeat(depth));
+        let err = parse(&query).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                ParseError::NestingTooDeep {
+                    limit: DEFAULT_MAX_DEPTH,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+
+        let query = format!("*{}", "{\"a\": ".repeat(depth));
+        assert!(matches!(
+            parse(&query).unwrap_err(),
+            ParseError::NestingTooDeep { .. }
+        ));
+
+        let query = format!("*[{}a{}]", "(".repeat(50), ")".repeat(50));
+        assert!(parse(&query).is_ok());
+        let shallow = ParseOptions {
+            max_depth: 8,
+            ..Default::default()
+        };
+        assert!(parse_with_options(&query, shallow).is_err());
+    }
+
+    #[test]
+    fn strict_mode_rejects_malformed_projection() {
+        let query = r#"*[_type == "post"]{"a": title subtitle, body}"#;
+        let err = parse(query).unwrap_err();
+        assert!(
+            matches!(&err, ParseError::UnexpectedToken { found, .. } if found.contains("subtitle")),
+            "{err:?}"
+        );
+
+        let expr = parse_with_options(query, LENIENT).unwrap();
+        let Expr::Pipeline(stages) = expr else {
+            panic!("expected pipeline")
+        };
+        let Expr::Projection(fields) = &stages[2] else {
+            panic!("expected projection")
+        };
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].0, "a");
+    }
+
+    #[test]
+    fn grouped_or_inside_and() {
+        let expr = parse("*[(a == 1 || b == 2) && c == 3]").unwrap();
+        let Expr::Pipeline(stages) = expr else {
+            panic!("expected pipeline")
+        };
+        let Expr::Filter(filter) = &stages[1] else {
+            panic!("expected filter")
+        };
+        let Expr::And(left, right) = filter.as_ref() else {
+            panic!("expected &&, got {filter:?}")
+        };
+        assert!(matches!(**left, Expr::Or(..)));
+        assert!(matches!(**right, Expr::Eq(..)));
+
+        let expr = parse("*[a == 1 || (b == 2 && !(c || d))]").unwrap();
+        let Expr::Pipeline(stages) = expr else {
+            panic!("expected pipeline")
+        };
+        assert!(matches!(&stages[1], Expr::Filter(f) if matches!(**f, Expr::Or(..))));
+    }
+
+    #[test]
+    fn grouped_projection_values_and_stages() {
+        let expr = parse(r#"*{"x": (a || b) && c, "n": (count(tags) > 1)}"#).unwrap();
+        let Expr::Pipeline(stages) = expr else {
+            panic!("expected pipeline")
+        };
+        let Expr::Projection(fields) = &stages[1] else {
+            panic!("expected projection")
+        };
+        let Expr::And(left, _) = &fields[0].1 else {
+            panic!("expected &&, got {:?}", fields[0].1)
+        };
+        assert!(matches!(**left, Expr::Or(..)));
+        assert!(matches!(fields[1].1, Expr::Gt(..)));
+
+        let expr = parse(r#"(*[_type == "a"] | order(x))[0...2]{b}"#).unwrap();
+        let Expr::Pipeline(stages) = expr else {
+            panic!("expected pipeline")
+        };
+        assert!(matches!(stages[0], Expr::Pipeline(_)));
+        assert!(matches!(stages[1], Expr::Slice(_, 0, 2)));
+        assert!(matches!(stages[2], Expr::Projection(_)));
+    }
+
+    #[test]
+    fn order_takes_one_field_and_direction() {
+        let expected = |query: &str| match parse(query) {
+            Err(ParseError::UnexpectedToken {
+                found, expected, ..
+            }) => (found, expected),
+            other => panic!("{query}: {other:?}"),
+        };
+        assert_eq!(
+            expected("* | order(title asc desc)"),
+            ("Desc".into(), "`)` after the order direction".into())
+        );
+        assert_eq!(
+            expected("* | order(asc)"),
+            ("Asc".into(), "field to order by".into())
+        );
+        assert!(matches!(
+            parse("* | order(title desc)").unwrap(),
+            Expr::Pipeline(stages) if matches!(stages[1], Expr::Order(_, false))
+        ));
+    }
+
+    #[test]
+    fn parent_levels_and_paths() {
+        let expr = parse("^.^._id").unwrap();
+        let Expr::DotAccess(base, field) = expr else {
+            panic!("expected field access, got {expr:?}")
+        };
+        assert_eq!(field, "_id");
+        assert_eq!(*base, Expr::Parent(2));
+        assert_eq!(parse("^").unwrap(), Expr::Parent(1));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let expr = parse("a && b || c").unwrap();
+        let Expr::Or(left, right) = expr else {
+            panic!("expected ||, got {expr:?}")
+        };
+        assert!(matches!(*left, Expr::And(..)));
+        assert!(matches!(*right, Expr::Ident(ref c) if c == "c"));
+
+        let expr = parse("a || b && c").unwrap();
+        let Expr::Or(_, right) = expr else {
+            panic!("expected ||, got {expr:?}")
+        };
+        assert!(matches!(*right, Expr::And(..)));
+
+        // Left-associative chains.
+        let expr = parse("a && b && c").unwrap();
+        let Expr::And(left, _) = expr else {
+            panic!("expected &&, got {expr:?}")
+        };
+        assert!(matches!(*left, Expr::And(..)));
+    }
+
+    #[test]
+    fn error_reports_span_and_pointer() {
+        let query = r#"*[_type == "post"]{"a": title subtitle}"#;
+        let err = parse(query).unwrap_err();
+        assert_eq!(err.span(), Span { start: 30, end: 38 });
+        assert_eq!(
+            err.render(query),
+            format!("{query}\n{}^^^^^^^^ expected `,` or `}}`", " ".repeat(30))
+        );
+
+        let query = "*[_type == \"post\"\n  && ]";
+        let err = parse(query).unwrap_err();
+        assert_eq!(
+            err.render(query),
+            "  && ]\n     ^ expected expression at line 2, column 6"
+        );
+
+        let err = parse("*[_type ==").unwrap_err();
+        assert!(matches!(err, ParseError::UnexpectedEof { .. }));
+        assert_eq!(
+            err.render("*[_type =="),
+            "*[_type ==\n          ^ unexpected end of input"
+        );
+    }
+
+    #[test]
+    fn errors_on_later_lines_report_line_and_column() {
+        let query = "*[\n  _type == \"post\"\n  && ]{title}";
+        let err = parse(query).unwrap_err();
+        assert_eq!(err.span().line_col(query), (3, 6));
+        assert_eq!(
+            err.render(query),
+            "  && ]{title}\n     ^ expected expression at line 3, column 6"
+        );
+
+        let query = "*[_type == \"post\"]{\n  title,\n  \"slug\": é\n}";
+        let err = parse(query).unwrap_err();
+        assert!(matches!(err, ParseError::Lex(_)), "{err:?}");
+        assert_eq!(err.span().line_col(query), (3, 11));
+
+        assert_eq!(Span { start: 0, end: 1 }.line_col(""), (1, 1));
+    }
+
+    #[test]
+    fn strict_mode_rejects_trailing_input() {
+        for query in ["*{title}}", "*{title} foo", "author->{name} x"] {
+            assert!(parse(query).is_err(), "{query}");
+            assert!(parse_with_options(query, LENIENT).is_ok(), "{query}");
+        }
+        assert!(parse("*{title, }").is_ok());
+    }
+
     #[test]
     fn parse_everything() {
         let expr = parse("*").unwrap();
@@ -407,4 +1017,153 @@ mod tests {
             _ => panic!("expected FuncCall"),
         }
     }
+
+    #[test]
+    fn parse_namespaced_function_call() {
+        let expr = parse("pt::text(body)").unwrap();
+        match expr {
+            Expr::FuncCall(name, args) => {
+                assert_eq!(name, "pt::text");
+                assert!(matches!(&args[0], Expr::Ident(n) if n == "body"));
+            }
+            _ => panic!("expected FuncCall, got {expr:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_select() {
+        let expr = parse("select(a == 1 => \"one\", \"other\")").unwrap();
+        match expr {
+            Expr::Select(branches) => {
+                assert_eq!(branches.len(), 2);
+                assert!(matches!(&branches[0].0, Some(Expr::Eq(_, _))));
+                assert!(matches!(&branches[0].1, Expr::StringLiteral(s) if s == "one"));
+                assert!(branches[1].0.is_none());
+            }
+            _ => panic!("expected Select, got {expr:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_conditional_spread() {
+        let expr = parse(r#"*[_type == "post"]{..., isActive => { "badge": "active" }}"#).unwrap();
+        let Expr::Pipeline(stages) = expr else {
+            panic!("expected Pipeline");
+        };
+        let Expr::Projection(fields) = &stages[2] else {
+            panic!("expected Projection");
+        };
+        assert_eq!(fields.len(), 2);
+        assert!(matches!(&fields[0], (k, Expr::Everything) if k == "..."));
+        match &fields[1] {
+            (key, Expr::Select(branches)) => {
+                assert_eq!(key, "...");
+                assert!(matches!(&branches[0].0, Some(Expr::Ident(n)) if n == "isActive"));
+                assert!(matches!(&branches[0].1, Expr::Object(f) if f[0].0 == "badge"));
+            }
+            other => panic!("expected conditional spread, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_object_literal() {
+        // At the top level braces are a projection; as a value, an object.
+        let Expr::Projection(mut outer) = parse(r#"{"o": {"a": 1, "v": views}}"#).unwrap() else {
+            panic!("expected a projection");
+        };
+        let expr = outer.remove(0).1;
+        match expr {
+            Expr::Object(fields) => {
+                assert_eq!(fields.len(), 2);
+                assert!(matches!(&fields[0], (k, Expr::IntLiteral(1)) if k == "a"));
+                assert!(matches!(&fields[1], (k, Expr::Ident(n)) if k == "v" && n == "views"));
+            }
+            _ => panic!("expected Object, got {expr:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_iterate_with_projection() {
+        let expr = parse("tags[]{label}").unwrap();
+        match expr {
+            Expr::Pipeline(stages) => {
+                assert!(
+                    matches!(&stages[0], Expr::Iterate(base) if matches!(base.as_ref(), Expr::Ident(n) if n == "tags"))
+                );
+                assert!(matches!(&stages[1], Expr::Projection(f) if f[0].0 == "label"));
+            }
+            _ => panic!("expected Pipeline, got {expr:?}"),
+        }
+        assert!(matches!(parse("tags[]").unwrap(), Expr::Iterate(_)));
+    }
+
+    #[test]
+    fn parse_two_stage_pipe() {
+        let expr = parse(r#"*[_type == "post"] | [published == true] | {title}"#).unwrap();
+        match expr {
+            Expr::Pipeline(stages) => {
+                assert_eq!(stages.len(), 4);
+                assert!(matches!(stages[1], Expr::Filter(_)));
+                assert!(matches!(stages[2], Expr::Filter(_)));
+                assert!(matches!(stages[3], Expr::Projection(_)));
+            }
+            _ => panic!("expected Pipeline, got {expr:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_order_then_slice() {
+        let expr = parse(r#"*[_type == "post"] | order(views desc) | [0..10]"#).unwrap();
+        match expr {
+            Expr::Pipeline(stages) => {
+                assert_eq!(stages.len(), 4);
+                assert!(matches!(stages[2], Expr::Order(_, false)));
+                assert!(matches!(stages[3], Expr::Slice(_, 0, 11)));
+            }
+            _ => panic!("expected Pipeline, got {expr:?}"),
+        }
+        let expr = parse("*[0...5]").unwrap();
+        assert!(matches!(&expr, Expr::Pipeline(s) if matches!(s[1], Expr::Slice(_, 0, 5))));
+    }
+
+    #[test]
+    fn parse_iterated_deref_with_projection() {
+        let expr = parse("authors[]->{name}").unwrap();
+        match expr {
+            Expr::Pipeline(stages) => {
+                assert_eq!(stages.len(), 3);
+                assert!(matches!(&stages[0], Expr::Iterate(_)));
+                assert!(
+                    matches!(&stages[1], Expr::DerefDocument(base) if matches!(base.as_ref(), Expr::This))
+                );
+                assert!(matches!(&stages[2], Expr::Projection(_)));
+            }
+            _ => panic!("expected Pipeline, got {expr:?}"),
+        }
+        assert!(matches!(
+            parse("authors[]->").unwrap(),
+            Expr::Pipeline(stages) if stages.len() == 2
+        ));
+        assert!(matches!(parse("author->name").unwrap(), Expr::Deref(_, _)));
+    }
+
+    #[test]
+    fn parse_bare_projection() {
+        let expr = parse(r#"{title, "s": slug.current}"#).unwrap();
+        assert_eq!(
+            expr,
+            Expr::Projection(vec![
+                ("title".into(), Expr::Ident("title".into())),
+                (
+                    "s".into(),
+                    Expr::DotAccess(Box::new(Expr::Ident("slug".into())), "current".into())
+                ),
+            ])
+        );
+        let doc = serde_json::json!({"title": "Hello", "slug": {"current": "hello"}});
+        assert_eq!(
+            crate::eval::eval_expr(&expr, &doc, &serde_json::json!({})).unwrap(),
+            serde_json::json!({"title": "Hello", "s": "hello"})
+        );
+    }
 }
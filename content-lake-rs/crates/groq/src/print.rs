@@ -0,0 +1,358 @@
+//! Render an [`Expr`] back into GROQ source.
+//!
+//! Output is stable: one space around binary operators and after commas, no
+//! padding inside brackets or braces, and parentheses only where the parser
+//! would otherwise read a different tree. Parsing the output yields the
+//! expression that was printed.
+
+use crate::ast::{Expr, SLICE_TO_END};
+
+/// Render `expr` as GROQ.
+pub fn to_groq(expr: &Expr) -> String {
+    let mut out = String::new();
+    write_expr(&mut out, expr, QUERY);
+    out
+}
+
+// Binding strength, loosest first. An expression printed where the parser
+// expects something binding tighter is wrapped in parentheses.
+const QUERY: u8 = 0;
+const OR: u8 = 1;
+const AND: u8 = 2;
+const COMPARISON: u8 = 3;
+const PRIMARY: u8 = 4;
+
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Pipeline(stages) if matches!(stages.first(), Some(Expr::Everything)) => QUERY,
+        Expr::Or(..) => OR,
+        Expr::And(..) => AND,
+        Expr::Eq(..)
+        | Expr::Neq(..)
+        | Expr::Lt(..)
+        | Expr::Gt(..)
+        | Expr::Lte(..)
+        | Expr::Gte(..)
+        | Expr::In(..)
+        | Expr::Match(..) => COMPARISON,
+        _ => PRIMARY,
+    }
+}
+
+/// Write `expr`, parenthesised if it binds looser than `min`.
+fn write_expr(out: &mut String, expr: &Expr, min: u8) {
+    if precedence(expr) < min {
+        out.push('(');
+        write_expr(out, expr, QUERY);
+        out.push(')');
+        return;
+    }
+    match expr {
+        Expr::StringLiteral(s) => write_string(out, s),
+        Expr::IntLiteral(n) => out.push_str(&n.to_string()),
+        Expr::FloatLiteral(n) => out.push_str(&format!("{n:?}")),
+        Expr::BoolLiteral(b) => out.push_str(&b.to_string()),
+        Expr::Null => out.push_str("null"),
+        Expr::Array(items) => {
+            out.push('[');
+            write_list(out, items);
+            out.push(']');
+        }
+        Expr::Object(fields) | Expr::Projection(fields) => write_fields(out, fields),
+        Expr::Ident(name) => out.push_str(name),
+        Expr::DotAccess(base, field) => {
+            write_expr(out, base, PRIMARY);
+            out.push('.');
+            out.push_str(field);
+        }
+        Expr::Deref(base, field) => {
+            write_deref_base(out, base);
+            out.push_str("->");
+            out.push_str(field);
+        }
+        Expr::DerefDocument(base) => {
+            write_deref_base(out, base);
+            out.push_str("->");
+        }
+        Expr::Iterate(base) => {
+            write_expr(out, base, PRIMARY);
+            out.push_str("[]");
+        }
+        Expr::This => out.push('@'),
+        Expr::Parent(levels) => out.push_str(&vec!["^"; *levels].join(".")),
+        Expr::Eq(l, r) => write_binary(out, l, "==", r, PRIMARY, PRIMARY),
+        Expr::Neq(l, r) => write_binary(out, l, "!=", r, PRIMARY, PRIMARY),
+        Expr::Lt(l, r) => write_binary(out, l, "<", r, PRIMARY, PRIMARY),
+        Expr::Gt(l, r) => write_binary(out, l, ">", r, PRIMARY, PRIMARY),
+        Expr::Lte(l, r) => write_binary(out, l, "<=", r, PRIMARY, PRIMARY),
+        Expr::Gte(l, r) => write_binary(out, l, ">=", r, PRIMARY, PRIMARY),
+        Expr::In(l, r) => write_binary(out, l, "in", r, PRIMARY, PRIMARY),
+        Expr::Match(l, r) => write_binary(out, l, "match", r, PRIMARY, PRIMARY),
+        // Both are left-associative, so only a right operand of the same
+        // operator needs parentheses.
+        Expr::And(l, r) => write_binary(out, l, "&&", r, AND, COMPARISON),
+        Expr::Or(l, r) => write_binary(out, l, "||", r, OR, AND),
+        Expr::Not(inner) => {
+            out.push('!');
+            write_expr(out, inner, PRIMARY);
+        }
+        Expr::Everything => out.push('*'),
+        Expr::Pipeline(stages) => write_pipeline(out, stages),
+        // Only meaningful inside a pipeline.
+        Expr::Filter(_)
+        | Expr::Order(..)
+        | Expr::Slice(..)
+        | Expr::Index(..)
+        | Expr::ParamSlice(..) => write_stage(out, expr),
+        Expr::FuncCall(name, args) => {
+            out.push_str(name);
+            out.push('(');
+            for (i, arg) in args.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_expr(out, arg, QUERY);
+            }
+            out.push(')');
+        }
+        Expr::Select(branches) => {
+            out.push_str("select(");
+            write_branches(out, branches);
+            out.push(')');
+        }
+        Expr::Param(name) => {
+            out.push('$');
+            out.push_str(name);
+        }
+    }
+}
+
+fn write_binary(out: &mut String, left: &Expr, op: &str, right: &Expr, lmin: u8, rmin: u8) {
+    write_expr(out, left, lmin);
+    out.push(' ');
+    out.push_str(op);
+    out.push(' ');
+    write_expr(out, right, rmin);
+}
+
+/// Comma-separated values as they appear in arrays and `select`.
+fn write_list(out: &mut String, items: &[Expr]) {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write_expr(out, item, OR);
+    }
+}
+
+fn write_branches(out: &mut String, branches: &[(Option<Expr>, Expr)]) {
+    for (i, (cond, value)) in branches.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        if let Some(cond) = cond {
+            write_expr(out, cond, OR);
+            out.push_str(" => ");
+        }
+        write_expr(out, value, OR);
+    }
+}
+
+/// `{a, "b": c, ..., cond => {...}}`
+fn write_fields(out: &mut String, fields: &[(String, Expr)]) {
+    out.push('{');
+    for (i, (key, value)) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        match (key.as_str(), value) {
+            ("...", Expr::Everything) => out.push_str("..."),
+            ("...", Expr::Select(branches)) if branches.len() == 1 && branches[0].0.is_some() => {
+                write_branches(out, branches)
+            }
+            ("...", value) => {
+                out.push_str("...");
+                write_expr(out, value, OR);
+            }
+            (key, Expr::Ident(name)) if key == name => out.push_str(name),
+            (key, value) => {
+                write_string(out, key);
+                out.push_str(": ");
+                write_expr(out, value, OR);
+            }
+        }
+    }
+    out.push('}');
+}
+
+/// The base of `->`: the parser only dereferences identifiers and field
+/// paths, so anything else is grouped.
+fn write_deref_base(out: &mut String, base: &Expr) {
+    if is_path(base) {
+        write_expr(out, base, PRIMARY);
+    } else {
+        out.push('(');
+        write_expr(out, base, QUERY);
+        out.push(')');
+    }
+}
+
+fn is_path(expr: &Expr) -> bool {
+    match expr {
+        Expr::Ident(_) => true,
+        Expr::DotAccess(base, _) => is_path(base),
+        _ => false,
+    }
+}
+
+fn write_pipeline(out: &mut String, stages: &[Expr]) {
+    let Some((first, rest)) = stages.split_first() else {
+        return;
+    };
+    match first {
+        Expr::Everything => {
+            out.push('*');
+            for stage in rest {
+                write_stage(out, stage);
+            }
+        }
+        // `a[]`, `a[]->b`, `a[]->{...}`, `a->b{...}`: stages the parser
+        // attaches to a path without grouping.
+        _ if is_path_pipeline(first, rest) => {
+            write_expr(out, first, PRIMARY);
+            for stage in rest {
+                write_stage(out, stage);
+            }
+        }
+        _ => {
+            out.push('(');
+            write_expr(out, first, QUERY);
+            out.push(')');
+            for stage in rest {
+                write_stage(out, stage);
+            }
+        }
+    }
+}
+
+fn is_path_pipeline(first: &Expr, rest: &[Expr]) -> bool {
+    match first {
+        Expr::Iterate(base) if is_path(base) => match rest {
+            [] | [Expr::Projection(_)] => true,
+            [deref] | [deref, Expr::Projection(_)] => matches!(
+                deref,
+                Expr::Deref(this, _) | Expr::DerefDocument(this) if matches!(**this, Expr::This)
+            ),
+            _ => false,
+        },
+        Expr::Deref(base, _) | Expr::DerefDocument(base) if is_path(base) => {
+            matches!(rest, [Expr::Projection(_)])
+        }
+        _ => false,
+    }
+}
+
+/// A stage following the head of a pipeline.
+fn write_stage(out: &mut String, stage: &Expr) {
+    match stage {
+        Expr::Filter(filter) => {
+            out.push('[');
+            write_expr(out, filter, OR);
+            out.push(']');
+        }
+        Expr::Slice(_, start, SLICE_TO_END) => out.push_str(&format!("[{start}...]")),
+        Expr::Slice(_, start, end) => out.push_str(&format!("[{start}...{end}]")),
+        Expr::Index(_, index) => out.push_str(&format!("[{index}]")),
+        Expr::ParamSlice(_, start, end, inclusive) => {
+            out.push('[');
+            write_expr(out, start, PRIMARY);
+            out.push_str(if *inclusive { ".." } else { "..." });
+            write_expr(out, end, PRIMARY);
+            out.push(']');
+        }
+        Expr::Projection(fields) => write_fields(out, fields),
+        Expr::Deref(this, field) if matches!(**this, Expr::This) => {
+            out.push_str("->");
+            out.push_str(field);
+        }
+        Expr::DerefDocument(this) if matches!(**this, Expr::This) => out.push_str("->"),
+        Expr::Order(field, ascending) => {
+            out.push_str(" | order(");
+            write_expr(out, field, PRIMARY);
+            if !ascending {
+                out.push_str(" desc");
+            }
+            out.push(')');
+        }
+        other => {
+            out.push_str(" | ");
+            write_expr(out, other, OR);
+        }
+    }
+}
+
+/// Quote a string literal. The lexer keeps escapes as written, so the
+/// contents go out verbatim inside whichever quote they don't contain.
+fn write_string(out: &mut String, s: &str) {
+    let quote = if s.contains('"') && !s.contains('\'') {
+        '\''
+    } else {
+        '"'
+    };
+    out.push(quote);
+    out.push_str(s);
+    out.push(quote);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn round_trip(query: &str) -> String {
+        let expr = parse(query).unwrap();
+        let printed = to_groq(&expr);
+        let reparsed = parse(&printed).unwrap_or_else(|e| panic!("{printed}: {e}"));
+        assert_eq!(reparsed, expr, "{printed}");
+        printed
+    }
+
+    #[test]
+    fn round_trips_filter_with_projection() {
+        let printed = round_trip(
+            r#"*[_type=="post"&&(published||$draft)&&!(slug.current in ["a",'b'])]{title,"author":author->name,...,defined(body)=>{body}}"#,
+        );
+        assert_eq!(
+            printed,
+            r#"*[_type == "post" && (published || $draft) && !(slug.current in ["a", "b"])]{title, "author": author->name, ..., defined(body) => {body}}"#
+        );
+    }
+
+    #[test]
+    fn round_trips_ordered_pipeline() {
+        let printed = round_trip(
+            r#"*[_type == "post"] | order(publishedAt desc)[0..9]{title, "tags": tags[]->{name}}"#,
+        );
+        assert_eq!(
+            printed,
+            r#"*[_type == "post"] | order(publishedAt desc)[0...10]{title, "tags": tags[]->{name}}"#
+        );
+    }
+
+    #[test]
+    fn round_trips_groups_and_params() {
+        round_trip(r#"(*[_type == "a"] | order(x))[0...2]{b}"#);
+        round_trip("*[$start..$end]");
+        round_trip(r#"*{"n": count(*[references(^)]), "s": select(a > 1 => "x", "y")}"#);
+        round_trip("*[a || (b || c)]");
+        round_trip(r#"*{"a": authors[]{"post": ^._id, "root": ^.^.slug.current}}"#);
+        round_trip(r#"*[title == 'say "hi"']"#);
+        round_trip("*[2...]");
+        round_trip("*[-3..-1]");
+        round_trip("*[...$limit]");
+        round_trip("*[-1]{title}");
+        round_trip(
+            r#"*[title match "rust*"] | score(boost(title match $q, 2)) | order(_score desc)"#,
+        );
+    }
+}
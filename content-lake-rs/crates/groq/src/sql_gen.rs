@@ -1,2 +1,390 @@
-// GROQ → SQL transpilation.
-// Will be fully implemented in Phase 2.
+//! GROQ → SQL translation of document filters.
+//!
+//! Only filters whose SQL form matches the in-memory evaluator exactly are
+//! translated; for anything else the functions here return `None` and the
+//! caller evaluates in memory. The generated SQL reads the `documents`
+//! table's `document_id`, `doc_type`, `revision` and `content` columns.
+//!
+//! Supported: `==`/`!=` between a field path and a string, boolean, number
+//! or null literal (or a parameter holding one), `defined(path)`, and `&&`,
+//! `||` and `!` over those.
+//!
+//! Every value and path is bound rather than spliced into the SQL, so the
+//! text depends only on the filter's shape and Postgres can reuse one
+//! prepared statement across values. [`fingerprint`] names that shape.
+
+use std::fmt::Write;
+
+use serde_json::Value;
+
+use crate::ast::Expr;
+
+/// A SQL boolean expression and the values for its `$n` placeholders, in
+/// order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlFilter {
+    pub sql: String,
+    pub binds: Vec<SqlBind>,
+}
+
+/// A value bound to a placeholder.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlBind {
+    Text(String),
+    TextArray(Vec<String>),
+}
+
+/// The filter of a `count(*[...])` query, with its placeholders numbered
+/// from `first_param`. Every stage after `*` must be a translatable filter;
+/// `count(*)` alone translates to `TRUE`.
+pub fn count_filter(expr: &Expr, params: &Value, first_param: usize) -> Option<SqlFilter> {
+    let mut gen = Generator {
+        params,
+        binds: Vec::new(),
+        first_param,
+        emit_sql: true,
+    };
+    let sql = gen.count(expr)?;
+    Some(SqlFilter {
+        sql,
+        binds: gen.binds,
+    })
+}
+
+/// The binds [`count_filter`] would return for `expr`, without building its
+/// SQL: for when the SQL is already known from a query with the same
+/// [`fingerprint`].
+pub fn count_binds(expr: &Expr, params: &Value) -> Option<Vec<SqlBind>> {
+    let mut gen = Generator {
+        params,
+        binds: Vec::new(),
+        first_param: 1,
+        emit_sql: false,
+    };
+    gen.count(expr)?;
+    Some(gen.binds)
+}
+
+/// A key for the SQL [`count_filter`] produces: expressions with equal
+/// fingerprints translate to the same SQL text, or both fail to translate.
+///
+/// Literal and parameter values are reduced to their kind, which is all the
+/// SQL depends on; untranslatable parts are kept whole.
+pub fn fingerprint(expr: &Expr, params: &Value) -> String {
+    let mut out = String::new();
+    shape(expr, params, &mut out);
+    out
+}
+
+/// Where a field path lives in a `documents` row.
+enum Field {
+    /// A text column holding a system field.
+    Column(&'static str),
+    /// A path into `content`.
+    Content(Vec<String>),
+}
+
+struct Generator<'a> {
+    params: &'a Value,
+    binds: Vec<SqlBind>,
+    first_param: usize,
+    /// Whether to build SQL text, or only collect binds.
+    emit_sql: bool,
+}
+
+impl Generator<'_> {
+    fn sql(&self, build: impl FnOnce() -> String) -> String {
+        if self.emit_sql {
+            build()
+        } else {
+            String::new()
+        }
+    }
+
+    fn bind(&mut self, value: SqlBind) -> String {
+        self.binds.push(value);
+        let n = self.first_param + self.binds.len() - 1;
+        self.sql(|| format!("${n}"))
+    }
+
+    /// The conjunction of a `count(*[...])` query's filters.
+    fn count(&mut self, expr: &Expr) -> Option<String> {
+        let Expr::FuncCall(name, args) = expr else {
+            return None;
+        };
+        let [query] = args.as_slice() else {
+            return None;
+        };
+        if name != "count" {
+            return None;
+        }
+        let filters: &[Expr] = match query {
+            Expr::Everything => &[],
+            Expr::Pipeline(stages) => match stages.split_first() {
+                Some((Expr::Everything, rest)) => rest,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let mut clauses = Vec::new();
+        for stage in filters {
+            let Expr::Filter(filter) = stage else {
+                return None;
+            };
+            clauses.push(self.condition(filter)?);
+        }
+        Some(self.sql(|| {
+            if clauses.is_empty() {
+                "TRUE".to_string()
+            } else {
+                clauses.join(" AND ")
+            }
+        }))
+    }
+
+    /// A SQL expression that is never NULL and is true exactly when GROQ's
+    /// filter would keep the document.
+    fn condition(&mut self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::And(l, r) => {
+                let (l, r) = (self.condition(l)?, self.condition(r)?);
+                Some(self.sql(|| format!("({l} AND {r})")))
+            }
+            Expr::Or(l, r) => {
+                let (l, r) = (self.condition(l)?, self.condition(r)?);
+                Some(self.sql(|| format!("({l} OR {r})")))
+            }
+            Expr::Not(inner) => {
+                let inner = self.condition(inner)?;
+                Some(self.sql(|| format!("NOT {inner}")))
+            }
+            Expr::Eq(l, r) => self.equals(l, r),
+            Expr::Neq(l, r) => {
+                let eq = self.equals(l, r)?;
+                Some(self.sql(|| format!("NOT {eq}")))
+            }
+            Expr::FuncCall(name, args) if name == "defined" => match args.as_slice() {
+                [arg] => match field(arg)? {
+                    Field::Column(_) => Some(self.sql(|| "TRUE".into())),
+                    Field::Content(path) => {
+                        let value = self.content(path);
+                        Some(self.sql(|| format!("COALESCE({value} <> 'null'::jsonb, FALSE)")))
+                    }
+                },
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn equals(&mut self, l: &Expr, r: &Expr) -> Option<String> {
+        let (field, literal) = match (field(l), field(r)) {
+            (Some(field), None) => (field, self.literal(r)?),
+            (None, Some(field)) => (field, self.literal(l)?),
+            _ => return None,
+        };
+        match field {
+            Field::Column(column) => match literal {
+                Value::String(s) => {
+                    let p = self.bind(SqlBind::Text(s));
+                    Some(self.sql(|| format!("({column} = {p})")))
+                }
+                // System fields are always strings.
+                _ => Some(self.sql(|| "FALSE".into())),
+            },
+            Field::Content(path) => {
+                if matches!(literal, Value::Array(_) | Value::Object(_)) {
+                    return None;
+                }
+                // Compared as jsonb, so one statement serves every kind of
+                // scalar, a missing field equals `null`, and `1` equals
+                // `1.0` but not `true`, as in memory.
+                let value = self.content(path);
+                let p = self.bind(SqlBind::Text(literal.to_string()));
+                Some(self.sql(|| format!("(COALESCE({value}, 'null'::jsonb) = {p}::jsonb)")))
+            }
+        }
+    }
+
+    fn content(&mut self, path: Vec<String>) -> String {
+        let p = self.bind(SqlBind::TextArray(path));
+        self.sql(|| format!("(content #> {p}::text[])"))
+    }
+
+    fn literal(&self, expr: &Expr) -> Option<Value> {
+        match expr {
+            Expr::StringLiteral(s) => Some(Value::String(s.clone())),
+            Expr::IntLiteral(n) => Some((*n).into()),
+            Expr::BoolLiteral(b) => Some(Value::Bool(*b)),
+            Expr::Null => Some(Value::Null),
+            Expr::Param(name) => self.params.get(name).cloned(),
+            _ => None,
+        }
+    }
+}
+
+/// The row location of a plain field path such as `a.b.c`.
+fn field(expr: &Expr) -> Option<Field> {
+    match expr {
+        Expr::Ident(name) => Some(match name.as_str() {
+            "_id" => Field::Column("document_id"),
+            "_type" => Field::Column("doc_type"),
+            "_rev" => Field::Column("revision"),
+            "_createdAt" | "_updatedAt" => return None,
+            _ => Field::Content(vec![name.clone()]),
+        }),
+        Expr::DotAccess(base, key) => match field(base)? {
+            Field::Content(mut path) => {
+                path.push(key.clone());
+                Some(Field::Content(path))
+            }
+            Field::Column(_) => None,
+        },
+        _ => None,
+    }
+}
+
+/// Write the part of `expr` that decides its SQL text to `out`.
+fn shape(expr: &Expr, params: &Value, out: &mut String) {
+    let list = |tag: &str, items: &[&Expr], out: &mut String| {
+        out.push_str(tag);
+        out.push('(');
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            shape(item, params, out);
+        }
+        out.push(')');
+    };
+    match expr {
+        Expr::StringLiteral(_) => out.push('s'),
+        Expr::IntLiteral(_) | Expr::BoolLiteral(_) | Expr::Null => out.push('v'),
+        Expr::Param(name) => out.push(match params.get(name) {
+            Some(Value::String(_)) => 's',
+            Some(Value::Null | Value::Bool(_) | Value::Number(_)) => 'v',
+            Some(Value::Array(_) | Value::Object(_)) | None => 'x',
+        }),
+        Expr::Everything => out.push('*'),
+        Expr::Ident(name) => {
+            let _ = write!(out, "{name:?}");
+        }
+        Expr::DotAccess(base, key) => {
+            shape(base, params, out);
+            let _ = write!(out, ".{key:?}");
+        }
+        Expr::Eq(l, r) => list("eq", &[l, r], out),
+        Expr::Neq(l, r) => list("neq", &[l, r], out),
+        Expr::And(l, r) => list("and", &[l, r], out),
+        Expr::Or(l, r) => list("or", &[l, r], out),
+        Expr::Not(inner) => list("not", &[inner], out),
+        Expr::Filter(inner) => list("filter", &[inner], out),
+        Expr::Pipeline(stages) => list("pipe", &stages.iter().collect::<Vec<_>>(), out),
+        Expr::FuncCall(name, args) => {
+            list(&format!("{name:?}"), &args.iter().collect::<Vec<_>>(), out)
+        }
+        _ => {
+            let _ = write!(out, "{expr:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use serde_json::json;
+
+    fn translate(query: &str, params: Value) -> Option<SqlFilter> {
+        count_filter(&parse(query).unwrap(), &params, 3)
+    }
+
+    #[test]
+    fn translates_count_filters() {
+        let filter = translate(
+            r#"count(*[_type == "post" && author._ref == $id])"#,
+            json!({"id": "a1"}),
+        )
+        .unwrap();
+        assert_eq!(
+            filter.sql,
+            "((doc_type = $3) AND (COALESCE((content #> $4::text[]), 'null'::jsonb) = $5::jsonb))"
+        );
+        assert_eq!(
+            filter.binds,
+            [
+                SqlBind::Text("post".into()),
+                SqlBind::TextArray(vec!["author".into(), "_ref".into()]),
+                SqlBind::Text("\"a1\"".into()),
+            ]
+        );
+
+        let all = translate("count(*)", json!({})).unwrap();
+        assert_eq!((all.sql.as_str(), all.binds.len()), ("TRUE", 0));
+
+        let chained = translate("count(*[_type == \"post\"][!defined(slug)])", json!({})).unwrap();
+        assert!(
+            chained.sql.contains(" AND NOT COALESCE("),
+            "{}",
+            chained.sql
+        );
+    }
+
+    #[test]
+    fn leaves_untranslatable_queries_to_memory() {
+        for query in [
+            "*[_type == \"post\"]",
+            "count(*[_type == \"post\"]{title})",
+            "count(*[featured])",
+            "count(*[author->name == \"Ann\"])",
+            "count(*[_updatedAt == \"2024\"])",
+            "count(*[tags == [\"a\"]])",
+            "count(*[a == b])",
+            "count(*[rating == 1.5])",
+            "count(*[0...2])",
+        ] {
+            assert_eq!(translate(query, json!({})), None, "{query}");
+        }
+    }
+
+    #[test]
+    fn same_shape_same_sql() {
+        let query = parse("count(*[_type == $type && rating == $rating][defined(slug)])").unwrap();
+        let a = json!({"type": "post", "rating": 5});
+        let b = json!({"type": "author", "rating": null});
+        let (first, second) = (
+            count_filter(&query, &a, 3).unwrap(),
+            count_filter(&query, &b, 3).unwrap(),
+        );
+        assert_eq!(first.sql, second.sql);
+        assert_ne!(first.binds, second.binds);
+        assert_eq!(fingerprint(&query, &a), fingerprint(&query, &b));
+        assert_eq!(count_binds(&query, &b), Some(second.binds));
+
+        // Literals are bound just like parameters.
+        let literal =
+            parse(r#"count(*[_type == "page" && rating == true][defined(slug)])"#).unwrap();
+        assert_eq!(fingerprint(&literal, &json!({})), fingerprint(&query, &a));
+        assert_eq!(
+            count_filter(&literal, &json!({}), 3).unwrap().sql,
+            first.sql
+        );
+
+        // A system field compared to a non-string is a different statement,
+        // as is a different field or operator.
+        let numeric_type = json!({"type": 5, "rating": 5});
+        assert_ne!(fingerprint(&query, &numeric_type), fingerprint(&query, &a));
+        for other in [
+            "count(*[_type == $type && score == $rating][defined(slug)])",
+            "count(*[_type == $type && rating != $rating][defined(slug)])",
+            "count(*[_type == $type || rating == $rating][defined(slug)])",
+        ] {
+            assert_ne!(
+                fingerprint(&parse(other).unwrap(), &a),
+                fingerprint(&query, &a),
+                "{other}"
+            );
+        }
+        assert_eq!(count_binds(&parse("count(*[a == b])").unwrap(), &a), None);
+    }
+}
@@ -0,0 +1,7 @@
+-- Phase 1: Document revision history
+
+-- Monotonic ordering for transactions committed within the same timestamp
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS seq BIGSERIAL;
+
+CREATE INDEX IF NOT EXISTS idx_transactions_seq ON transactions(dataset_id, seq);
+CREATE INDEX IF NOT EXISTS idx_transaction_documents_document ON transaction_documents(document_id);
@@ -0,0 +1,17 @@
+-- Reverse index of `_ref`s, so "what references this document" is a lookup
+
+CREATE TABLE IF NOT EXISTS document_references (
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    document_id TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    PRIMARY KEY (dataset_id, document_id, target_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_references_target ON document_references(dataset_id, target_id);
+
+-- Index documents written before the table existed
+INSERT INTO document_references (dataset_id, document_id, target_id)
+SELECT dataset_id, document_id, ref #>> '{}'
+FROM documents, jsonb_path_query(content, 'lax $.**._ref ? (@.type() == "string")') AS ref
+WHERE deleted = false
+ON CONFLICT DO NOTHING;
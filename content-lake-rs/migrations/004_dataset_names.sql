@@ -0,0 +1,4 @@
+-- Project that datasets created through the API belong to. The API
+-- addresses datasets by name within this project; names stay unique per
+-- project only.
+INSERT INTO projects (name) VALUES ('default') ON CONFLICT (name) DO NOTHING;